@@ -0,0 +1,106 @@
+use crate::config::sidecar_dir;
+use chrono::{Duration, NaiveDateTime};
+use std::cmp::Reverse;
+use std::fs;
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
+
+/// Backup names end in this stamp, so sorting them by name sorts them by age.
+const STAMP_FORMAT: &str = "%Y%m%d-%H%M%S%.3f";
+
+/// A copy of the task list taken before it was overwritten.
+#[derive(Debug, Clone)]
+pub struct Backup {
+    pub path: PathBuf,
+    pub taken: NaiveDateTime,
+}
+
+/// Directory holding the backups of a board: `<tasklist>.d/backups/`.
+pub fn backup_dir(tasklist_path: &str) -> PathBuf {
+    sidecar_dir(tasklist_path).join("backups")
+}
+
+fn file_name(tasklist_path: &str) -> String {
+    Path::new(tasklist_path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| ".tasklist".to_string())
+}
+
+/// Copies the task list into the backup directory. Returns `None` when there
+/// is no file yet, so there is nothing to lose.
+pub fn create(tasklist_path: &str, now: NaiveDateTime) -> Result<Option<PathBuf>, Error> {
+    if !Path::new(tasklist_path).exists() {
+        return Ok(None);
+    }
+    let dir = backup_dir(tasklist_path);
+    fs::create_dir_all(&dir).map_err(|e| {
+        Error::new(
+            e.kind(),
+            format!("Could not create {}: {}", dir.display(), e),
+        )
+    })?;
+    let target = dir.join(format!(
+        "{}.{}",
+        file_name(tasklist_path),
+        now.format(STAMP_FORMAT)
+    ));
+    fs::copy(tasklist_path, &target)
+        .map_err(|e| Error::new(e.kind(), format!("Could not back up task list: {}", e)))?;
+    Ok(Some(target))
+}
+
+/// Lists the backups of a board, newest first.
+pub fn list(tasklist_path: &str) -> Result<Vec<Backup>, Error> {
+    let dir = backup_dir(tasklist_path);
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => {
+            return Err(Error::new(
+                e.kind(),
+                format!("Could not read {}: {}", dir.display(), e),
+            ));
+        },
+    };
+
+    let prefix = format!("{}.", file_name(tasklist_path));
+    let mut backups = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        let Some(stamp) = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_prefix(&prefix))
+        else {
+            continue;
+        };
+        if let Ok(taken) = NaiveDateTime::parse_from_str(stamp, STAMP_FORMAT) {
+            backups.push(Backup { path, taken });
+        }
+    }
+    backups.sort_by_key(|backup| Reverse(backup.taken));
+    Ok(backups)
+}
+
+/// Deletes all but the newest `keep` backups, and any older than `max_days`
+/// (0 means no age limit).
+pub fn prune(
+    tasklist_path: &str,
+    keep: usize,
+    max_days: u64,
+    now: NaiveDateTime,
+) -> Result<(), Error> {
+    let cutoff = (max_days > 0).then(|| now - Duration::days(max_days as i64));
+    for (index, backup) in list(tasklist_path)?.into_iter().enumerate() {
+        if index >= keep || cutoff.is_some_and(|cutoff| backup.taken < cutoff) {
+            fs::remove_file(&backup.path).map_err(|e| {
+                Error::new(
+                    e.kind(),
+                    format!("Could not remove {}: {}", backup.path.display(), e),
+                )
+            })?;
+        }
+    }
+    Ok(())
+}
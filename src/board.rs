@@ -0,0 +1,241 @@
+use crate::task::{SEP, Status, Task, TaskId, parse_id};
+use chrono::NaiveDateTime;
+use colored::Colorize;
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind};
+use uuid::Uuid;
+
+/// One line of the task list: a parsed task, or a line kept verbatim.
+// Almost every entry is a task, so boxing it would only add indirection
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone)]
+pub enum Entry {
+    Task(Task),
+    Raw(String),
+}
+
+/// In-memory view of a task list used for read-modify-write updates.
+#[derive(Debug, Clone, Default)]
+pub struct Board {
+    pub max_id: TaskId,
+    pub entries: Vec<Entry>,
+}
+
+impl Board {
+    /// Parses the lines of a task list file. Lines that look like tasks must
+    /// carry a positive, unique ID and no other task's UUID; errors name the
+    /// offending line of `path`.
+    /// Comments, blank lines and anything unparseable are kept in place,
+    /// unless `strict` is set, in which case unparseable task lines are errors.
+    pub fn parse(path: &str, lines: &[String], strict: bool) -> Result<Board, Error> {
+        Board::parse_lines(path, lines, strict, false)
+    }
+
+    /// Parses a board of the merge backend (see `SortedStorage`). It has no
+    /// counter, so a plain git merge of two branches that each added a task
+    /// can leave two tasks with one ID; the later one in the file gets the
+    /// next free ID instead of failing the load. Lines are in UUID order, so
+    /// every clone renumbers the same task.
+    pub fn parse_sorted(path: &str, lines: &[String], strict: bool) -> Result<Board, Error> {
+        Board::parse_lines(path, lines, strict, true)
+    }
+
+    fn parse_lines(
+        path: &str,
+        lines: &[String],
+        strict: bool,
+        sorted: bool,
+    ) -> Result<Board, Error> {
+        let mut board = Board::default();
+        let mut seen: HashMap<TaskId, usize> = HashMap::new();
+        let mut uuids: HashMap<Uuid, usize> = HashMap::new();
+        let mut duplicates = Vec::new();
+        let invalid = |line_no: usize, message: String| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("{}:{}: {}", path, line_no, message),
+            )
+        };
+
+        for (index, line) in lines.iter().enumerate() {
+            let line_no = index + 1;
+            // The tsv counter is regenerated on write and only counts on the
+            // first line, where it may be followed by a checksum (see
+            // `TsvStorage`). Merge boards have none, and the one older
+            // versions wrote on their last line is dropped like any other.
+            if let Some(max_id) = line.strip_prefix("#max_id=") {
+                if index == 0
+                    && !sorted
+                    && let Some(Ok(max_id)) = max_id.split(SEP).next().map(str::parse::<TaskId>)
+                {
+                    board.max_id = board.max_id.max(max_id);
+                }
+                continue;
+            }
+            if line.starts_with('#') || line.is_empty() {
+                board.entries.push(Entry::Raw(line.clone()));
+                continue;
+            }
+
+            let columns: Vec<&str> = line.split(SEP).collect();
+            if columns.len() < 3 {
+                if strict {
+                    return Err(invalid(
+                        line_no,
+                        "malformed task line (expected id, status and description)".to_string(),
+                    ));
+                }
+                board.entries.push(Entry::Raw(line.clone()));
+                continue;
+            }
+            let Some(id) = parse_id(columns[0]) else {
+                return Err(invalid(
+                    line_no,
+                    format!("invalid task ID `{}`", columns[0]),
+                ));
+            };
+            if let Some(first) = seen.insert(id, line_no) {
+                if !sorted {
+                    return Err(invalid(
+                        line_no,
+                        format!("duplicate task ID {} (already used on line {})", id, first),
+                    ));
+                }
+                duplicates.push(board.entries.len());
+            }
+            if strict && Status::from_label(columns[1]).is_none() {
+                return Err(invalid(line_no, format!("unknown status `{}`", columns[1])));
+            }
+            // A stale or missing counter must never hand out an ID again
+            board.max_id = board.max_id.max(id);
+            match Task::from_file_line(line) {
+                Some(task) => {
+                    if let Some(uuid) = task.uuid
+                        && let Some(first) = uuids.insert(uuid, line_no)
+                    {
+                        return Err(invalid(
+                            line_no,
+                            format!(
+                                "duplicate task UUID {} (already used on line {})",
+                                uuid, first
+                            ),
+                        ));
+                    }
+                    board.entries.push(Entry::Task(task))
+                },
+                None => board.entries.push(Entry::Raw(line.clone())),
+            }
+        }
+
+        for index in duplicates {
+            if let Entry::Task(task) = &mut board.entries[index] {
+                board.max_id += 1;
+                eprintln!(
+                    "{} {}: task ID {} is used twice, \"{}\" is now {}",
+                    "Warning:".yellow(),
+                    path,
+                    task.id,
+                    task.description,
+                    board.max_id
+                );
+                task.id = board.max_id;
+            }
+        }
+        Ok(board)
+    }
+
+    pub fn tasks(&self) -> impl Iterator<Item = &Task> {
+        self.entries.iter().filter_map(|entry| match entry {
+            Entry::Task(task) => Some(task),
+            Entry::Raw(_) => None,
+        })
+    }
+
+    pub fn tasks_mut(&mut self) -> impl Iterator<Item = &mut Task> {
+        self.entries.iter_mut().filter_map(|entry| match entry {
+            Entry::Task(task) => Some(task),
+            Entry::Raw(_) => None,
+        })
+    }
+
+    /// A board holding only `tasks`, e.g. to diff a single task's edit.
+    pub fn of(tasks: Vec<Task>) -> Board {
+        Board {
+            max_id: tasks.iter().map(|task| task.id).max().unwrap_or(0),
+            entries: tasks.into_iter().map(Entry::Task).collect(),
+        }
+    }
+
+    pub fn task_mut(&mut self, id: TaskId) -> Result<&mut Task, Error> {
+        self.tasks_mut()
+            .find(|task| task.id == id)
+            .ok_or_else(|| not_found(id))
+    }
+
+    pub fn remove(&mut self, id: TaskId) -> Result<Task, Error> {
+        let index = self
+            .entries
+            .iter()
+            .position(|entry| matches!(entry, Entry::Task(task) if task.id == id))
+            .ok_or_else(|| not_found(id))?;
+        match self.entries.remove(index) {
+            Entry::Task(task) => Ok(task),
+            Entry::Raw(_) => Err(not_found(id)),
+        }
+    }
+
+    /// Gives every task written by an older version a UUID.
+    pub fn assign_missing_uuids(&mut self) {
+        for task in self.tasks_mut().filter(|task| task.uuid.is_none()) {
+            task.uuid = Some(Uuid::new_v4());
+        }
+    }
+
+    /// Records when tasks were completed: a task that became done since
+    /// `before` is stamped with `at`, unless it already carries a time (an
+    /// import), and a task that is no longer done loses its time.
+    pub fn stamp_completions(&mut self, before: &Board, at: NaiveDateTime) {
+        let was_done: HashMap<String, bool> = before
+            .tasks()
+            .map(|task| (task.key(), task.status == Status::Done))
+            .collect();
+        for task in self.tasks_mut() {
+            let done = was_done.get(&task.key()) == Some(&true);
+            task.stamp_completion(done, at);
+        }
+    }
+
+    /// Appends `task` under the next free ID and returns that ID.
+    pub fn push(&mut self, mut task: Task) -> Result<TaskId, Error> {
+        self.max_id = self
+            .max_id
+            .checked_add(1)
+            .ok_or_else(|| Error::other("Task IDs exhausted, run `tsk renumber`"))?;
+        task.id = self.max_id;
+        self.entries.push(Entry::Task(task));
+        Ok(self.max_id)
+    }
+
+    /// Renumbers tasks 1..n in file order and returns the `(old, new)` pairs
+    /// that changed. UUIDs are untouched, so references by UUID stay valid.
+    pub fn renumber(&mut self) -> Vec<(TaskId, TaskId)> {
+        let mut changed = Vec::new();
+        let mut next = 0;
+        for task in self.tasks_mut() {
+            next += 1;
+            if task.id != next {
+                changed.push((task.id, next));
+                task.id = next;
+            }
+        }
+        self.max_id = next;
+        changed
+    }
+}
+
+pub fn not_found(id: TaskId) -> Error {
+    Error::new(
+        ErrorKind::NotFound,
+        format!("Task with ID {} not found", id),
+    )
+}
@@ -1,4 +1,10 @@
-use crate::task::Status;
+use crate::dates::DateArg;
+use crate::export::ExportFormat;
+use crate::filter::TaskFilter;
+use crate::import::ImportFormat;
+use crate::recurrence::RecurrenceArg;
+use crate::storage::Backend;
+use crate::task::{Estimate, Status, TaskRef};
 use clap::{Parser, Subcommand};
 #[derive(Parser, Debug)]
 #[command(
@@ -26,6 +32,20 @@ pub struct Cli {
     )]
     pub kanban: bool,
 
+    #[arg(
+        long,
+        global = true,
+        help = "Fail on task lines that don't parse instead of keeping them as-is"
+    )]
+    pub strict: bool,
+
+    #[arg(
+        long,
+        global = true,
+        help = "Show what a command would do without saving anything"
+    )]
+    pub dry_run: bool,
+
     #[command(subcommand)]
     pub command: Option<Commands>,
 }
@@ -37,30 +57,271 @@ pub enum Commands {
     Add {
         #[arg(short, long)]
         description: String,
+        #[arg(
+            short,
+            long,
+            help = "Effort estimate: points (3pt) or duration (2h, 1h30m, 1d)"
+        )]
+        estimate: Option<Estimate>,
+        #[arg(long, help = "Due date: 2025-01-31, today, tomorrow, fri, 3d, 2w")]
+        due: Option<DateArg>,
+        #[arg(long, help = "Hide the task until this date (same formats as --due)")]
+        wait: Option<DateArg>,
+        #[arg(long, help = "Person responsible for the task")]
+        assignee: Option<String>,
+        #[arg(
+            long,
+            help = "Repeat when done: daily, weekly:mon,thu, monthly, every 3 days or RRULE"
+        )]
+        recur: Option<RecurrenceArg>,
     },
     #[command(about = "Update an existing task")]
     #[clap(visible_alias = "u")]
     Update {
-        #[arg(short, long, help = "ID (index) of the task to update")]
-        id: i32,
+        #[arg(short, long, help = "ID or UUID prefix of the task to update")]
+        id: TaskRef,
         #[arg(short, long, help = "New task status")]
-        status: Status,
+        status: Option<Status>,
         #[arg(short, long, help = "New description")]
         description: Option<String>,
+        #[arg(short, long, help = "New effort estimate (0 clears it)")]
+        estimate: Option<Estimate>,
+        #[arg(long, help = "New due date (none clears it)")]
+        due: Option<DateArg>,
+        #[arg(long, help = "New scheduled date (none shows the task again)")]
+        wait: Option<DateArg>,
+        #[arg(long, help = "New recurrence rule (none clears it)")]
+        recur: Option<RecurrenceArg>,
     },
     #[command(about = "View tasks")]
     #[clap(visible_alias = "ls")]
     #[clap(visible_alias = "list")]
     Show {
+        #[arg(help = "Show the full details of a single task")]
+        id: Option<TaskRef>,
         #[arg(short, long, help = "Display tasks in Kanban board view")]
         kanban: bool,
+        #[arg(long, help = "List recurring task templates")]
+        recurring: bool,
+        #[command(flatten)]
+        filter: TaskFilter,
     },
     #[command(about = "Delete task")]
     #[clap(visible_alias = "rm")]
     Delete {
-        #[arg(short, long, help = "ID of task being deleted")]
-        id: i32,
+        #[arg(short, long, help = "ID or UUID prefix of task being deleted")]
+        id: TaskRef,
+    },
+    #[command(about = "Check the task list file for problems")]
+    Doctor {
+        #[arg(long, help = "Repair what can be repaired (backs the file up first)")]
+        fix: bool,
+    },
+    #[command(about = "List or restore automatic backups of the task list")]
+    Backup {
+        #[command(subcommand)]
+        action: BackupAction,
+    },
+    #[command(about = "Convert the board in place to another storage backend")]
+    Migrate {
+        #[arg(long, help = "Backend to convert to")]
+        to: Backend,
+    },
+    #[command(about = "Write a copy of the board in another format")]
+    Convert {
+        #[arg(
+            help = "Output file; its extension picks the format (.json, .toml, .yaml, .sqlite, else tsv)"
+        )]
+        output: String,
+        #[arg(long, help = "Format to write, whatever the extension says")]
+        to: Option<Backend>,
+        #[arg(long, help = "Overwrite the output file if it exists")]
+        force: bool,
+    },
+    #[command(about = "Add the tasks from a todo.txt, Taskwarrior or CSV file")]
+    Import {
+        #[arg(long, value_enum, help = "Format of the file")]
+        from: ImportFormat,
+        #[arg(value_name = "FILE", help = "File to import")]
+        input: String,
+    },
+    #[command(
+        about = "Write the tasks as CSV, Markdown, todo.txt, an HTML kanban page or iCalendar"
+    )]
+    Export {
+        #[arg(long, value_enum, help = "Format to write")]
+        format: ExportFormat,
+        #[arg(short, long, help = "Write to this file instead of stdout")]
+        output: Option<String>,
+        #[arg(long, help = "Markdown: a section per status instead of one checklist")]
+        grouped: bool,
+        #[command(flatten)]
+        filter: TaskFilter,
+    },
+    #[command(about = "Keep an iCalendar file of the board up to date for calendar apps")]
+    IcalFeed {
+        #[arg(short, long, help = "File to write after every change")]
+        output: Option<String>,
+        #[arg(long, conflicts_with = "output", help = "Stop updating the file")]
+        off: bool,
+    },
+    #[command(about = "Sync the board with a file kept by hand, both ways")]
+    Sync {
+        #[command(subcommand)]
+        target: SyncTarget,
+    },
+    #[command(about = "Create tasks from TODO/FIXME/HACK comments in source files")]
+    Scan {
+        #[arg(help = "Files or directories to scan (default: the current directory)")]
+        paths: Vec<String>,
+    },
+    #[command(about = "Show who changed a task's status or description, and when")]
+    History {
+        #[arg(help = "ID of the task")]
+        id: TaskRef,
+    },
+    #[command(about = "Show the change log of a task (events backend)")]
+    Log {
+        #[arg(help = "ID of the task")]
+        id: TaskRef,
+    },
+    #[command(
+        about = "Encrypt the board with a passphrase ($TSK_PASSPHRASE, $TSK_KEY_FILE or prompt)"
+    )]
+    Encrypt,
+    #[command(about = "Decrypt an encrypted board back to plain text")]
+    Decrypt,
+    #[command(about = "Accept hand edits: recompute the checksum in the board header")]
+    Rehash,
+    #[command(about = "Compact task IDs to 1..n (UUIDs stay the same)")]
+    Renumber,
+    #[command(about = "Merge two versions of a board (git merge driver: %O %A %B)")]
+    MergeDriver {
+        #[arg(help = "Common ancestor version")]
+        base: String,
+        #[arg(help = "Our version; the result is written here")]
+        ours: String,
+        #[arg(help = "Their version")]
+        theirs: String,
+    },
+    #[command(about = "Assign a task to someone (none unassigns it)")]
+    Assign {
+        #[arg(help = "ID of the task")]
+        id: TaskRef,
+        #[arg(help = "Name of the assignee")]
+        name: String,
+    },
+    #[command(about = "Assign a task to yourself (git user.name or $USER)")]
+    Claim {
+        #[arg(help = "ID of the task")]
+        id: TaskRef,
+    },
+    #[command(about = "Attach a URL (PR, ticket, ...) to a task")]
+    LinkUrl {
+        #[arg(help = "ID of the task")]
+        id: TaskRef,
+        #[arg(help = "URL to attach")]
+        url: String,
+    },
+    #[command(about = "Attach a file reference to a task")]
+    Attach {
+        #[arg(help = "ID of the task")]
+        id: TaskRef,
+        #[arg(help = "Path to the file")]
+        path: String,
+    },
+    #[command(about = "Hide a task until a later date")]
+    Snooze {
+        #[arg(help = "ID of the task to snooze")]
+        id: TaskRef,
+        #[arg(help = "When to show it again: 3d, 2w, mon, 2025-01-31")]
+        until: DateArg,
+    },
+    #[command(about = "Add a timestamped comment to a task")]
+    Comment {
+        #[arg(help = "ID of the task to comment on")]
+        id: TaskRef,
+        #[arg(help = "Comment text")]
+        text: String,
+    },
+    #[command(about = "Set the Markdown notes of a task (opens $EDITOR without TEXT)")]
+    #[clap(visible_alias = "notes")]
+    Note {
+        #[arg(help = "ID of the task")]
+        id: TaskRef,
+        #[arg(help = "New notes text")]
+        text: Option<String>,
+        #[arg(
+            short,
+            long,
+            help = "Append to the existing notes instead of replacing them"
+        )]
+        append: bool,
+    },
+    #[command(about = "Start or stop time tracking")]
+    Timer {
+        #[command(subcommand)]
+        action: TimerAction,
+    },
+    #[command(about = "Summarize board data")]
+    Report {
+        #[command(subcommand)]
+        kind: ReportKind,
+    },
+    #[command(about = "Show or change board settings")]
+    Config {
+        #[arg(help = "Setting to show or change")]
+        key: Option<String>,
+        #[arg(help = "New value")]
+        value: Option<String>,
     },
     #[command(about = "Launch interactive TUI")]
     Tui,
 }
+
+#[derive(Subcommand, Debug)]
+pub enum TimerAction {
+    #[command(about = "Start a timer on a task (stops any running timer)")]
+    Start {
+        #[arg(help = "ID of the task to track")]
+        id: TaskRef,
+    },
+    #[command(about = "Stop the running timer")]
+    Stop,
+    #[command(about = "Show the running timer")]
+    Status,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SyncTarget {
+    #[command(about = "Sync `- [ ]` / `- [x]` items of a Markdown file with tasks")]
+    Markdown {
+        #[arg(value_name = "FILE", help = "Markdown file, e.g. TODO.md")]
+        path: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum BackupAction {
+    #[command(about = "List backups, newest first")]
+    #[clap(visible_alias = "ls")]
+    List,
+    #[command(about = "Restore a backup (the current file is backed up first)")]
+    Restore {
+        #[arg(help = "Backup number from `tsk backup list` (1 = newest)")]
+        number: usize,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ReportKind {
+    #[command(about = "Hours tracked per task")]
+    Time {
+        #[arg(long, help = "Only count time since Monday", conflicts_with = "today")]
+        week: bool,
+        #[arg(long, help = "Only count time since midnight")]
+        today: bool,
+    },
+    #[command(about = "Estimated vs. tracked effort and remaining work")]
+    Estimates,
+}
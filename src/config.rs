@@ -0,0 +1,189 @@
+use crate::storage::Backend;
+use std::fs;
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
+
+/// Per-board settings, stored as `key = value` lines in `<tasklist>.d/config`.
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// How the board is stored; `None` goes by the file's extension.
+    pub backend: Option<Backend>,
+    /// Start a timer whenever a task moves to `InProgress` and stop it when it leaves.
+    pub auto_timer: bool,
+    /// Refuse to read the board while it has task lines that don't parse.
+    pub strict: bool,
+    /// How many backups of the task list to keep; 0 turns backups off.
+    pub backups: usize,
+    /// Backups older than this many days are pruned; 0 keeps them regardless of age.
+    pub backup_days: u64,
+    /// Encrypt the board, its backups and its history with a passphrase.
+    pub encrypted: bool,
+    /// iCalendar file rewritten after every write, for calendar apps to
+    /// subscribe to; relative paths are from the board's directory.
+    pub ical_feed: Option<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            backend: None,
+            auto_timer: false,
+            strict: false,
+            backups: 10,
+            backup_days: 30,
+            encrypted: false,
+            ical_feed: None,
+        }
+    }
+}
+
+impl Config {
+    pub const KEYS: &'static [&'static str] = &[
+        "backend",
+        "auto_timer",
+        "strict",
+        "backups",
+        "backup_days",
+        "encrypted",
+        "ical_feed",
+    ];
+
+    pub fn path_for(tasklist_path: &str) -> PathBuf {
+        sidecar_dir(tasklist_path).join("config")
+    }
+
+    pub fn load(tasklist_path: &str) -> Result<Config, Error> {
+        let path = Self::path_for(tasklist_path);
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Config::default()),
+            Err(e) => {
+                return Err(Error::new(
+                    e.kind(),
+                    format!("Could not read config {}: {}", path.display(), e),
+                ));
+            },
+        };
+
+        let mut config = Config::default();
+        for (index, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("{}:{}: expected `key = value`", path.display(), index + 1),
+                ));
+            };
+            config.set(key.trim(), value.trim()).map_err(|e| {
+                Error::new(e.kind(), format!("{}:{}: {}", path.display(), index + 1, e))
+            })?;
+        }
+        Ok(config)
+    }
+
+    pub fn save(&self, tasklist_path: &str) -> Result<(), Error> {
+        let path = Self::path_for(tasklist_path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut content = String::new();
+        for key in Self::KEYS {
+            content.push_str(&format!(
+                "{} = {}\n",
+                key,
+                self.get(key).unwrap_or_default()
+            ));
+        }
+        fs::write(&path, content).map_err(|e| {
+            Error::new(
+                e.kind(),
+                format!("Could not write config {}: {}", path.display(), e),
+            )
+        })
+    }
+
+    pub fn get(&self, key: &str) -> Option<String> {
+        match key {
+            "backend" => Some(
+                self.backend
+                    .map(|backend| backend.to_string())
+                    .unwrap_or_else(|| "auto".to_string()),
+            ),
+            "auto_timer" => Some(self.auto_timer.to_string()),
+            "strict" => Some(self.strict.to_string()),
+            "backups" => Some(self.backups.to_string()),
+            "backup_days" => Some(self.backup_days.to_string()),
+            "encrypted" => Some(self.encrypted.to_string()),
+            "ical_feed" => Some(self.ical_feed.clone().unwrap_or_else(|| "none".to_string())),
+            _ => None,
+        }
+    }
+
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), Error> {
+        match key {
+            "backend" if value.eq_ignore_ascii_case("auto") => self.backend = None,
+            "backend" => {
+                self.backend = Some(
+                    value
+                        .parse()
+                        .map_err(|e| Error::new(ErrorKind::InvalidInput, e))?,
+                )
+            },
+            "auto_timer" => self.auto_timer = parse_bool(key, value)?,
+            "strict" => self.strict = parse_bool(key, value)?,
+            "backups" => self.backups = parse_number(key, value)?,
+            "backup_days" => self.backup_days = parse_number(key, value)?,
+            "encrypted" => self.encrypted = parse_bool(key, value)?,
+            "ical_feed" if value.is_empty() || value.eq_ignore_ascii_case("none") => {
+                self.ical_feed = None
+            },
+            "ical_feed" => self.ical_feed = Some(value.to_string()),
+            _ => return Err(Self::unknown_key(key)),
+        }
+        Ok(())
+    }
+
+    pub fn unknown_key(key: &str) -> Error {
+        Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "Unknown config key `{}` (expected one of: {})",
+                key,
+                Self::KEYS.join(", ")
+            ),
+        )
+    }
+}
+
+/// Directory next to the board holding its config and other sidecar files.
+pub fn sidecar_dir(tasklist_path: &str) -> PathBuf {
+    let path = Path::new(tasklist_path);
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| ".tasklist".to_string());
+    path.with_file_name(format!("{}.d", name))
+}
+
+fn parse_bool(key: &str, value: &str) -> Result<bool, Error> {
+    match value.to_ascii_lowercase().as_str() {
+        "true" | "yes" | "on" | "1" => Ok(true),
+        "false" | "no" | "off" | "0" => Ok(false),
+        _ => Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("`{}` expects true or false, got `{}`", key, value),
+        )),
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(key: &str, value: &str) -> Result<T, Error> {
+    value.parse().map_err(|_| {
+        Error::new(
+            ErrorKind::InvalidInput,
+            format!("`{}` expects a whole number, got `{}`", key, value),
+        )
+    })
+}
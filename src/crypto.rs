@@ -0,0 +1,127 @@
+use argon2::Argon2;
+use chacha20poly1305::aead::{Aead, Generate, KeyInit};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use std::env;
+use std::fs;
+use std::io::{Error, ErrorKind, IsTerminal};
+use std::sync::{Mutex, OnceLock};
+
+/// Encrypted files start with this line, then the salt, the nonce and the
+/// ciphertext.
+const MAGIC: &[u8] = b"tsk-encrypted-v1\n";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+
+/// The passphrase, asked for at most once per run.
+static PASSPHRASE: OnceLock<String> = OnceLock::new();
+/// The last derived key and its salt. Deriving is slow on purpose, and a
+/// random nonce per write makes reusing the key within a run safe.
+static KEY: Mutex<Option<([u8; SALT_LEN], Key)>> = Mutex::new(None);
+
+pub fn is_encrypted(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+/// Decrypts `bytes` if they are encrypted, and returns them as-is if not.
+/// `name` is the file they came from, for error messages.
+pub fn open(name: &str, bytes: Vec<u8>) -> Result<Vec<u8>, Error> {
+    let Some(body) = bytes.strip_prefix(MAGIC) else {
+        return Ok(bytes);
+    };
+    if body.len() < SALT_LEN + NONCE_LEN {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("{} is encrypted but truncated", name),
+        ));
+    }
+    let (salt, rest) = body.split_at(SALT_LEN);
+    let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+    let salt: [u8; SALT_LEN] = salt.try_into().expect("split at SALT_LEN");
+    let nonce = XNonce::try_from(nonce).expect("split at NONCE_LEN");
+    XChaCha20Poly1305::new(&key_for(salt)?)
+        .decrypt(&nonce, ciphertext)
+        .map_err(|_| {
+            Error::new(
+                ErrorKind::PermissionDenied,
+                format!(
+                    "Could not decrypt {}: wrong passphrase, or the file was damaged",
+                    name
+                ),
+            )
+        })
+}
+
+/// Encrypts `plaintext` with the board passphrase.
+pub fn seal(plaintext: &[u8]) -> Result<Vec<u8>, Error> {
+    let cached = *KEY.lock().map_err(|_| Error::other("key cache poisoned"))?;
+    let (salt, key) = match cached {
+        Some(cached) => cached,
+        None => {
+            let salt = <[u8; SALT_LEN]>::try_generate().map_err(Error::other)?;
+            (salt, key_for(salt)?)
+        },
+    };
+    let nonce = XNonce::try_generate().map_err(Error::other)?;
+    let ciphertext = XChaCha20Poly1305::new(&key)
+        .encrypt(&nonce, plaintext)
+        .map_err(|_| Error::other("Encryption failed"))?;
+
+    let mut sealed = MAGIC.to_vec();
+    sealed.extend_from_slice(&salt);
+    sealed.extend_from_slice(&nonce);
+    sealed.extend_from_slice(&ciphertext);
+    Ok(sealed)
+}
+
+/// Derives the key for `salt` from the passphrase with Argon2id.
+fn key_for(salt: [u8; SALT_LEN]) -> Result<Key, Error> {
+    let mut cache = KEY.lock().map_err(|_| Error::other("key cache poisoned"))?;
+    if let Some((cached_salt, key)) = cache.as_ref()
+        && *cached_salt == salt
+    {
+        return Ok(*key);
+    }
+    let mut key = Key::default();
+    Argon2::default()
+        .hash_password_into(passphrase(false)?.as_bytes(), &salt, &mut key)
+        .map_err(|e| Error::other(format!("Key derivation failed: {}", e)))?;
+    *cache = Some((salt, key));
+    Ok(key)
+}
+
+/// The passphrase from `$TSK_PASSPHRASE`, the file named by `$TSK_KEY_FILE`,
+/// or else asked for on the terminal (twice when `confirm`, for a new one).
+pub fn passphrase(confirm: bool) -> Result<&'static str, Error> {
+    if let Some(passphrase) = PASSPHRASE.get() {
+        return Ok(passphrase);
+    }
+    let passphrase = if let Ok(passphrase) = env::var("TSK_PASSPHRASE") {
+        passphrase
+    } else if let Ok(path) = env::var("TSK_KEY_FILE") {
+        fs::read_to_string(&path)
+            .map_err(|e| Error::new(e.kind(), format!("Could not read key file {}: {}", path, e)))?
+            .trim_end_matches(['\r', '\n'])
+            .to_string()
+    } else if std::io::stdin().is_terminal() {
+        let passphrase = rpassword::prompt_password("Board passphrase: ")?;
+        if confirm && rpassword::prompt_password("Repeat passphrase: ")? != passphrase {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "Passphrases don't match",
+            ));
+        }
+        passphrase
+    } else {
+        return Err(Error::new(
+            ErrorKind::PermissionDenied,
+            "The board is encrypted: set TSK_PASSPHRASE or TSK_KEY_FILE",
+        ));
+    };
+    if passphrase.is_empty() {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "The passphrase can't be empty",
+        ));
+    }
+    Ok(PASSPHRASE.get_or_init(|| passphrase))
+}
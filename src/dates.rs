@@ -0,0 +1,64 @@
+use chrono::{Datelike, Days, Months, NaiveDate, Weekday};
+use std::str::FromStr;
+
+pub const DAY_FORMAT: &str = "%Y-%m-%d";
+
+pub fn today() -> NaiveDate {
+    chrono::Local::now().date_naive()
+}
+
+/// Parses a calendar date relative to `today`: `2025-01-31`, `today`,
+/// `tomorrow`, a weekday (`fri`, `monday` — the next one, never today) or an
+/// offset such as `3d`, `+2w` or `1m`.
+pub fn parse_date(input: &str, today: NaiveDate) -> Result<NaiveDate, String> {
+    let input = input.trim().to_ascii_lowercase();
+    if let Ok(date) = NaiveDate::parse_from_str(&input, DAY_FORMAT) {
+        return Ok(date);
+    }
+    match input.as_str() {
+        "today" => return Ok(today),
+        "tomorrow" => return Ok(today + Days::new(1)),
+        "yesterday" => return Ok(today - Days::new(1)),
+        _ => {},
+    }
+    if let Ok(weekday) = Weekday::from_str(&input) {
+        let ahead =
+            (7 + weekday.num_days_from_monday() - today.weekday().num_days_from_monday()) % 7;
+        return Ok(today + Days::new(if ahead == 0 { 7 } else { ahead as u64 }));
+    }
+    parse_offset(&input, today).ok_or_else(|| {
+        format!(
+            "invalid date `{}` (try 2025-01-31, today, tomorrow, fri, 3d, 2w or 1m)",
+            input
+        )
+    })
+}
+
+/// Shifts `from` by an offset such as `3d`, `+2w` or `1m`.
+pub fn parse_offset(input: &str, from: NaiveDate) -> Option<NaiveDate> {
+    let input = input.trim().trim_start_matches('+');
+    let unit_start = input.find(|c: char| !c.is_ascii_digit())?;
+    let amount: u32 = input[..unit_start].parse().ok()?;
+    match &input[unit_start..] {
+        "d" | "day" | "days" => from.checked_add_days(Days::new(amount.into())),
+        "w" | "week" | "weeks" => from.checked_add_days(Days::new(7 * u64::from(amount))),
+        "m" | "month" | "months" => from.checked_add_months(Months::new(amount)),
+        "y" | "year" | "years" => from.checked_add_months(Months::new(amount.checked_mul(12)?)),
+        _ => None,
+    }
+}
+
+/// A date given on the command line, or `none` to clear it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DateArg(pub Option<NaiveDate>);
+
+impl FromStr for DateArg {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("none") {
+            return Ok(DateArg(None));
+        }
+        parse_date(s, today()).map(|date| DateArg(Some(date)))
+    }
+}
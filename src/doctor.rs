@@ -0,0 +1,241 @@
+use crate::dates::DAY_FORMAT;
+use crate::task::{DATE_FORMAT, SEP, Status, TIME_FORMAT, TaskId, parse_id};
+use chrono::{NaiveDate, NaiveDateTime};
+use clap::ValueEnum;
+use std::collections::HashMap;
+use std::fmt;
+use uuid::Uuid;
+
+/// A problem found in a task list file.
+#[derive(Debug, Clone)]
+pub struct Issue {
+    /// 1-based line number, `None` for problems with the file as a whole.
+    pub line: Option<usize>,
+    pub message: String,
+    pub fixable: bool,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {}: {}", line, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// The outcome of checking a file: what is wrong, and the file with every
+/// fixable problem repaired.
+#[derive(Debug)]
+pub struct Diagnosis {
+    pub issues: Vec<Issue>,
+    pub repaired: Vec<String>,
+}
+
+/// Checks the raw bytes of a task list. Never fails: everything it cannot
+/// make sense of is reported as an issue and left as it was.
+pub fn diagnose(bytes: &[u8]) -> Diagnosis {
+    let mut issues = Vec::new();
+    let mut lines: Vec<String> = Vec::new();
+    for (index, raw) in bytes.split(|b| *b == b'\n').enumerate() {
+        let raw = raw.strip_suffix(b"\r").unwrap_or(raw);
+        match std::str::from_utf8(raw) {
+            Ok(line) => lines.push(line.to_string()),
+            Err(_) => {
+                issues.push(Issue {
+                    line: Some(index + 1),
+                    message: "not valid UTF-8 (bad bytes become U+FFFD)".to_string(),
+                    fixable: true,
+                });
+                lines.push(String::from_utf8_lossy(raw).into_owned());
+            },
+        }
+    }
+    if lines.last().is_some_and(|line| line.is_empty()) {
+        lines.pop();
+    }
+
+    let header = lines
+        .first()
+        .and_then(|line| line.strip_prefix("#max_id="))
+        .and_then(|value| value.split(SEP).next())
+        .map(|value| value.parse::<TaskId>().ok());
+    let mut max_id = header.flatten().unwrap_or(0);
+    let largest = lines
+        .iter()
+        .filter_map(|line| line.split(SEP).next().and_then(parse_id))
+        .max()
+        .unwrap_or(0);
+    match header {
+        None => issues.push(Issue {
+            line: Some(1),
+            message: "missing #max_id= header".to_string(),
+            fixable: true,
+        }),
+        Some(None) => issues.push(Issue {
+            line: Some(1),
+            message: format!("unreadable header `{}`", lines[0]),
+            fixable: true,
+        }),
+        Some(Some(header)) if header < largest => issues.push(Issue {
+            line: Some(1),
+            message: format!(
+                "stale #max_id={} (largest ID is {}), new tasks would reuse IDs",
+                header, largest
+            ),
+            fixable: true,
+        }),
+        Some(Some(_)) => {},
+    }
+    max_id = max_id.max(largest);
+
+    let mut seen: HashMap<TaskId, usize> = HashMap::new();
+    let mut uuids: HashMap<Uuid, usize> = HashMap::new();
+    let mut repaired = Vec::with_capacity(lines.len() + 1);
+    for (index, line) in lines.iter().enumerate() {
+        let line_no = index + 1;
+        if line.starts_with('#') || line.is_empty() {
+            if !line.starts_with("#max_id=") {
+                repaired.push(line.clone());
+            }
+            continue;
+        }
+
+        let mut columns: Vec<String> = line.split(SEP).map(str::to_string).collect();
+        if columns.len() < 3 {
+            issues.push(Issue {
+                line: Some(line_no),
+                message: "malformed task line (expected id, status and description)".to_string(),
+                fixable: false,
+            });
+            repaired.push(line.clone());
+            continue;
+        }
+
+        let id = parse_id(&columns[0]);
+        let duplicate_of = id.and_then(|id| seen.get(&id).copied());
+        if id.is_none() || duplicate_of.is_some() {
+            max_id += 1;
+            let message = match duplicate_of {
+                Some(first) => format!(
+                    "duplicate task ID {} (already used on line {})",
+                    columns[0], first
+                ),
+                None => format!("invalid task ID `{}`", columns[0]),
+            };
+            issues.push(Issue {
+                line: Some(line_no),
+                message: format!("{}, gets ID {}", message, max_id),
+                fixable: true,
+            });
+            columns[0] = max_id.to_string();
+        }
+        if let Some(id) = parse_id(&columns[0]) {
+            seen.entry(id).or_insert(line_no);
+        }
+
+        // A copied line keeps its UUID, so the copy needs a new one as well
+        // as a new ID, or both tasks would still be one task to sync and
+        // history
+        let uuid_column = columns
+            .iter()
+            .skip(3)
+            .position(|column| column.starts_with("uuid="))
+            .map(|index| index + 3);
+        if let Some(column) = uuid_column
+            && let Ok(uuid) = Uuid::parse_str(&columns[column]["uuid=".len()..])
+        {
+            match uuids.get(&uuid) {
+                Some(first) => {
+                    let fresh = Uuid::new_v4();
+                    issues.push(Issue {
+                        line: Some(line_no),
+                        message: format!(
+                            "duplicate UUID {} (already used on line {}), gets UUID {}",
+                            uuid, first, fresh
+                        ),
+                        fixable: true,
+                    });
+                    columns[column] = format!("uuid={}", fresh);
+                },
+                None => {
+                    uuids.insert(uuid, line_no);
+                },
+            }
+        }
+
+        if Status::from_label(&columns[1]).is_none() {
+            match guess_status(&columns[1]) {
+                Some(status) => {
+                    issues.push(Issue {
+                        line: Some(line_no),
+                        message: format!("unknown status `{}`, means {}", columns[1], status),
+                        fixable: true,
+                    });
+                    columns[1] = status.as_label().to_string();
+                },
+                None => issues.push(Issue {
+                    line: Some(line_no),
+                    message: format!(
+                        "unknown status `{}`, read as {}",
+                        columns[1],
+                        Status::NotStarted
+                    ),
+                    fixable: false,
+                }),
+            }
+        }
+
+        for bad in bad_timestamps(&columns) {
+            issues.push(Issue {
+                line: Some(line_no),
+                message: format!("bad timestamp `{}`", bad),
+                fixable: false,
+            });
+        }
+
+        repaired.push(columns.join(SEP));
+    }
+    repaired.insert(0, format!("#max_id={}", max_id));
+
+    Diagnosis { issues, repaired }
+}
+
+/// Recognizes status labels typed by hand, with or without the emoji.
+fn guess_status(label: &str) -> Option<Status> {
+    let name = label.trim_start_matches(|c: char| !c.is_ascii_alphanumeric());
+    let name = name.trim().to_ascii_lowercase().replace([' ', '-'], "_");
+    <Status as ValueEnum>::from_str(&name, true).ok()
+}
+
+fn bad_timestamps(columns: &[String]) -> Vec<String> {
+    let date_time =
+        |value: &str, format: &str| NaiveDateTime::parse_from_str(value, format).is_ok();
+    let day = |value: &str| NaiveDate::parse_from_str(value, DAY_FORMAT).is_ok();
+
+    let mut bad = Vec::new();
+    if let Some(date) = columns.get(3)
+        && !date.is_empty()
+        && !date.contains('=')
+        && !date_time(date, DATE_FORMAT)
+    {
+        bad.push(date.clone());
+    }
+    for field in columns.iter().skip(4) {
+        let ok = match field.split_once('=') {
+            Some(("due" | "wait", value)) => day(value),
+            Some(("timer", value)) => date_time(value, TIME_FORMAT),
+            Some(("time", value)) => value.split_once('|').is_some_and(|(start, end)| {
+                date_time(start, TIME_FORMAT) && date_time(end, TIME_FORMAT)
+            }),
+            Some(("comment", value)) => value
+                .split_once('|')
+                .is_none_or(|(date, _)| date.is_empty() || date_time(date, DATE_FORMAT)),
+            _ => true,
+        };
+        if !ok {
+            bad.push(field.clone());
+        }
+    }
+    bad
+}
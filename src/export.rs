@@ -0,0 +1,400 @@
+use crate::report::EstimateTotal;
+use crate::task::DATE_FORMAT;
+use crate::task::{Link, Status, Task, unescape};
+use crate::user::initials;
+use chrono::{Local, NaiveDate, NaiveDateTime, TimeZone};
+use clap::ValueEnum;
+use std::io::Error;
+
+/// Formats `tsk export` can write.
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum ExportFormat {
+    /// One row per task, with a header row `tsk import --from csv` reads back
+    Csv,
+    /// A checklist, or a section per status with --grouped
+    Markdown,
+    /// todo.txt, one task per line
+    Todotxt,
+    /// A standalone kanban page
+    Html,
+    /// iCalendar to-dos, for calendar apps
+    Ics,
+}
+
+/// Columns of the kanban board, left to right.
+const COLUMNS: [(Status, &str, &str); 3] = [
+    (Status::NotStarted, "🚀 NOT STARTED", "not-started"),
+    (Status::InProgress, "⏳ IN PROGRESS", "in-progress"),
+    (Status::Done, "✅ DONE", "done"),
+];
+
+/// Renders `tasks` for people who don't use tsk. `title` names the board in
+/// Markdown and HTML; `grouped` gives Markdown a section per status.
+pub fn render(
+    format: ExportFormat,
+    tasks: &[Task],
+    title: &str,
+    grouped: bool,
+    today: NaiveDate,
+) -> Result<String, Error> {
+    Ok(match format {
+        ExportFormat::Csv => csv(tasks)?,
+        ExportFormat::Markdown => markdown(tasks, title, grouped),
+        ExportFormat::Todotxt => tasks.iter().map(|task| todotxt(task) + "\n").collect(),
+        ExportFormat::Html => html(tasks, title, today),
+        ExportFormat::Ics => ics(tasks, title),
+    })
+}
+
+/// Values of an extra `key=value` field, such as `tag=` from an import.
+fn field_values(task: &Task, key: &str) -> Vec<String> {
+    task.extra
+        .iter()
+        .filter_map(|field| field.strip_prefix(key)?.strip_prefix('='))
+        .map(unescape)
+        .collect()
+}
+
+fn status_name(status: Status) -> String {
+    status
+        .to_possible_value()
+        .map(|value| value.get_name().to_string())
+        .unwrap_or_default()
+}
+
+fn csv(tasks: &[Task]) -> Result<String, Error> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    let mut write = |record: &[String]| writer.write_record(record).map_err(Error::other);
+    write(
+        &[
+            "id",
+            "uuid",
+            "status",
+            "description",
+            "date",
+            "completed",
+            "due",
+            "wait",
+            "estimate",
+            "assignee",
+            "priority",
+            "project",
+            "context",
+            "tags",
+            "url",
+            "notes",
+        ]
+        .map(str::to_string),
+    )?;
+    for task in tasks {
+        let day = |day: Option<NaiveDate>| day.map(|day| day.to_string()).unwrap_or_default();
+        let url = task
+            .links
+            .iter()
+            .find(|link| matches!(link, Link::Url(_)))
+            .map(|link| link.target().to_string());
+        write(&[
+            task.id.to_string(),
+            task.uuid.map(|uuid| uuid.to_string()).unwrap_or_default(),
+            status_name(task.status),
+            task.description.clone(),
+            task.date.clone(),
+            task.completed
+                .map(|at| at.format(DATE_FORMAT).to_string())
+                .unwrap_or_default(),
+            day(task.due),
+            day(task.wait),
+            task.estimate.map(|e| e.to_string()).unwrap_or_default(),
+            task.assignee.clone().unwrap_or_default(),
+            field_values(task, "priority").join(","),
+            field_values(task, "project").join(","),
+            field_values(task, "context").join(","),
+            field_values(task, "tag").join(","),
+            url.unwrap_or_default(),
+            task.notes.clone(),
+        ])?;
+    }
+    let bytes = writer
+        .into_inner()
+        .map_err(|e| Error::other(e.to_string()))?;
+    String::from_utf8(bytes).map_err(Error::other)
+}
+
+fn markdown(tasks: &[Task], title: &str, grouped: bool) -> String {
+    let item = |task: &Task, show_status: bool| {
+        let check = if task.status == Status::Done {
+            "x"
+        } else {
+            " "
+        };
+        let mut details = vec![format!("#{}", task.id)];
+        if show_status && task.status == Status::InProgress {
+            details.push("in progress".to_string());
+        }
+        details.extend(task.estimate.map(|estimate| estimate.to_string()));
+        details.extend(task.due.map(|due| format!("due {}", due)));
+        details.extend(
+            task.assignee
+                .as_ref()
+                .map(|assignee| format!("@{}", assignee)),
+        );
+        format!(
+            "- [{}] {} ({})\n",
+            check,
+            task.description,
+            details.join(" · ")
+        )
+    };
+
+    let mut out = format!("# {}\n", title);
+    if !grouped {
+        out.push('\n');
+        for task in tasks {
+            out.push_str(&item(task, true));
+        }
+        return out;
+    }
+    for (status, _, _) in COLUMNS {
+        let column: Vec<&Task> = tasks.iter().filter(|task| task.status == status).collect();
+        if column.is_empty() {
+            continue;
+        }
+        out.push_str(&format!("\n## {}", status.as_label()));
+        let total = EstimateTotal::of(column.iter().copied());
+        if !total.is_empty() {
+            out.push_str(&format!(" ({})", total));
+        }
+        out.push_str("\n\n");
+        for task in column {
+            out.push_str(&item(task, false));
+        }
+    }
+    out
+}
+
+/// `x 2024-01-03 Pay rent +home @errands due:2024-01-05 uuid:...`
+fn todotxt(task: &Task) -> String {
+    let mut words = Vec::new();
+    let priority = field_values(task, "priority")
+        .into_iter()
+        .find(|priority| priority.len() == 1 && priority.chars().all(|c| c.is_ascii_uppercase()));
+    if task.status == Status::Done {
+        words.push("x".to_string());
+        words.extend(task.date.get(..10).map(str::to_string));
+    } else if let Some(priority) = &priority {
+        words.push(format!("({})", priority));
+    }
+    words.push(task.description.clone());
+    // todo.txt has no spaces inside projects and contexts
+    let word = |value: String| value.split_whitespace().collect::<Vec<_>>().join("-");
+    words.extend(
+        field_values(task, "project")
+            .into_iter()
+            .map(|p| format!("+{}", word(p))),
+    );
+    for context in field_values(task, "context")
+        .into_iter()
+        .chain(field_values(task, "tag"))
+    {
+        words.push(format!("@{}", word(context)));
+    }
+    if task.status == Status::Done
+        && let Some(priority) = priority
+    {
+        words.push(format!("pri:{}", priority));
+    }
+    words.extend(task.due.map(|due| format!("due:{}", due)));
+    words.extend(task.wait.map(|wait| format!("t:{}", wait)));
+    words.extend(task.uuid.map(|uuid| format!("uuid:{}", uuid)));
+    words.join(" ")
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+const STYLE: &str = "\
+body { font-family: system-ui, -apple-system, sans-serif; margin: 2rem; background: #f6f7f9; color: #1f2328; }
+h1 { font-size: 1.4rem; margin: 0 0 1rem; }
+.board { display: grid; grid-template-columns: repeat(3, minmax(0, 1fr)); gap: 1rem; align-items: start; }
+.column h2 { font-size: 0.95rem; margin: 0 0 0.6rem; padding-bottom: 0.4rem; border-bottom: 3px solid; }
+.not-started h2 { border-color: #1b9aaa; color: #13707c; }
+.in-progress h2 { border-color: #d4a72c; color: #8a6a10; }
+.done h2 { border-color: #2da44e; color: #1a7f37; }
+.card { background: #fff; border: 1px solid #d0d7de; border-radius: 6px; padding: 0.6rem 0.75rem; margin-bottom: 0.5rem; }
+.card .id { color: #656d76; }
+.card .meta { color: #656d76; font-size: 0.85rem; margin-top: 0.25rem; }
+.empty { color: #656d76; font-style: italic; }
+@media (max-width: 700px) { .board { grid-template-columns: 1fr; } }
+";
+
+/// A page with the same three columns and cards as `tsk show --kanban`.
+fn html(tasks: &[Task], title: &str, today: NaiveDate) -> String {
+    let title = escape_html(title);
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <title>{title}</title>\n<style>\n{STYLE}</style>\n</head>\n<body>\n\
+         <h1>{title}</h1>\n<div class=\"board\">\n"
+    );
+    for (status, heading, class) in COLUMNS {
+        let column: Vec<&Task> = tasks.iter().filter(|task| task.status == status).collect();
+        let total = EstimateTotal::of(column.iter().copied());
+        let heading = if total.is_empty() {
+            heading.to_string()
+        } else {
+            format!("{} ({})", heading, total)
+        };
+        out.push_str(&format!(
+            "<section class=\"column {}\">\n<h2>{}</h2>\n",
+            class,
+            escape_html(&heading)
+        ));
+        if column.is_empty() {
+            out.push_str("<p class=\"empty\">Nothing here</p>\n");
+        }
+        for task in column {
+            let id = match &task.assignee {
+                Some(assignee) => format!("[{}] ({})", task.id, initials(assignee)),
+                None => format!("[{}]", task.id),
+            };
+            out.push_str(&format!(
+                "<div class=\"card\"><span class=\"id\">{}</span> {}",
+                escape_html(&id),
+                escape_html(&task.description)
+            ));
+            let meta = task.card_meta(today);
+            if !meta.is_empty() {
+                out.push_str(&format!("<div class=\"meta\">{}</div>", escape_html(&meta)));
+            }
+            out.push_str("</div>\n");
+        }
+        out.push_str("</section>\n");
+    }
+    out.push_str("</div>\n</body>\n</html>\n");
+    out
+}
+
+/// One VTODO per task, in a calendar named `title`. A task's UID is its
+/// UUID, and its stamps come from the task, so exporting an unchanged board
+/// gives the same bytes and calendar apps see updates rather than new items.
+fn ics(tasks: &[Task], title: &str) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//tsk//taskboard-rs//EN".to_string(),
+        format!("X-WR-CALNAME:{}", escape_ics(title)),
+    ];
+    for task in tasks {
+        let updated = NaiveDateTime::parse_from_str(&task.date, DATE_FORMAT)
+            .ok()
+            .and_then(utc)
+            .unwrap_or_else(|| "19700101T000000Z".to_string());
+        lines.push("BEGIN:VTODO".to_string());
+        lines.push(format!("UID:{}", task.key()));
+        lines.push(format!("DTSTAMP:{}", updated));
+        lines.push(format!("LAST-MODIFIED:{}", updated));
+        lines.push(format!("SUMMARY:{}", escape_ics(&task.description)));
+        if !task.notes.is_empty() {
+            lines.push(format!("DESCRIPTION:{}", escape_ics(&task.notes)));
+        }
+        lines.push(
+            match task.status {
+                Status::NotStarted => "STATUS:NEEDS-ACTION",
+                Status::InProgress => "STATUS:IN-PROCESS",
+                Status::Done => "STATUS:COMPLETED",
+            }
+            .to_string(),
+        );
+        if let Some(priority) = field_values(task, "priority")
+            .first()
+            .and_then(|priority| ics_priority(priority))
+        {
+            lines.push(format!("PRIORITY:{}", priority));
+        }
+        if let Some(wait) = task.wait {
+            lines.push(format!("DTSTART;VALUE=DATE:{}", wait.format("%Y%m%d")));
+        }
+        if let Some(due) = task.due {
+            lines.push(format!("DUE;VALUE=DATE:{}", due.format("%Y%m%d")));
+        }
+        if task.status == Status::Done {
+            // Tasks completed before the time was recorded only get the percentage
+            if let Some(completed) = task.completed.and_then(utc) {
+                lines.push(format!("COMPLETED:{}", completed));
+            }
+            lines.push("PERCENT-COMPLETE:100".to_string());
+        }
+        let categories: Vec<String> = ["project", "context", "tag"]
+            .iter()
+            .flat_map(|key| field_values(task, key))
+            .map(|category| escape_ics(&category))
+            .collect();
+        if !categories.is_empty() {
+            lines.push(format!("CATEGORIES:{}", categories.join(",")));
+        }
+        if let Some(Link::Url(url)) = task.links.iter().find(|link| matches!(link, Link::Url(_))) {
+            lines.push(format!("URL:{}", url));
+        }
+        lines.push("END:VTODO".to_string());
+    }
+    lines.push("END:VCALENDAR".to_string());
+    lines.iter().map(|line| fold_ics(line)).collect()
+}
+
+/// A local time as an iCalendar UTC timestamp.
+fn utc(at: NaiveDateTime) -> Option<String> {
+    Local
+        .from_local_datetime(&at)
+        .earliest()
+        .map(|at| at.naive_utc().format("%Y%m%dT%H%M%SZ").to_string())
+}
+
+/// iCalendar priorities run from 1 (highest) to 9; 0 means none. Takes
+/// Taskwarrior's H/M/L, todo.txt's A-Z and plain numbers.
+fn ics_priority(priority: &str) -> Option<u8> {
+    match priority.to_ascii_uppercase().as_str() {
+        "H" | "HIGH" => Some(1),
+        "M" | "MEDIUM" => Some(5),
+        "L" | "LOW" => Some(9),
+        letter if letter.len() == 1 && letter.as_bytes()[0].is_ascii_uppercase() => {
+            Some((letter.as_bytes()[0] - b'A' + 1).min(9))
+        },
+        number => number.parse().ok().filter(|n| (1..=9).contains(n)),
+    }
+}
+
+fn escape_ics(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Ends a content line with CRLF, folding it into 75-octet pieces.
+fn fold_ics(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len() + 2);
+    let mut width = 0;
+    for c in line.chars() {
+        // Continuation lines start with a space, which counts toward their 75
+        if width + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            width = 1;
+        }
+        folded.push(c);
+        width += c.len_utf8();
+    }
+    folded.push_str("\r\n");
+    folded
+}
@@ -0,0 +1,76 @@
+use crate::dates::DateArg;
+use crate::storage::TaskQuery;
+use crate::task::{Status, Task};
+use crate::user::current_user;
+use chrono::NaiveDate;
+use clap::Args;
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+
+/// Which tasks the list views show.
+#[derive(Args, Debug, Clone, Default)]
+pub struct TaskFilter {
+    #[arg(short, long, help = "Include tasks scheduled for a later date")]
+    pub all: bool,
+    #[arg(long, help = "Only tasks assigned to you", conflicts_with_all = ["assignee", "unassigned"])]
+    pub mine: bool,
+    #[arg(long, help = "Only tasks assigned to this person")]
+    pub assignee: Option<String>,
+    #[arg(
+        long,
+        help = "Only tasks nobody is assigned to",
+        conflicts_with = "assignee"
+    )]
+    pub unassigned: bool,
+    #[arg(short, long, help = "Only tasks with this status")]
+    pub status: Option<Status>,
+    #[arg(
+        long,
+        help = "Only tasks due on or before this date (same formats as --due)"
+    )]
+    pub due_by: Option<DateArg>,
+}
+
+impl TaskFilter {
+    /// Turns `--mine` into an `--assignee` filter for the current user.
+    pub fn resolve(mut self, board_dir: &Path) -> Result<TaskFilter, Error> {
+        if self.mine {
+            let me = current_user(board_dir).ok_or_else(|| {
+                Error::new(
+                    ErrorKind::NotFound,
+                    "Could not determine your name (set git user.name or $USER)",
+                )
+            })?;
+            self.assignee = Some(me);
+            self.mine = false;
+        }
+        Ok(self)
+    }
+
+    /// The part of the filter the storage backend applies.
+    pub fn query(&self) -> TaskQuery {
+        TaskQuery {
+            status: self.status,
+            due_by: self.due_by.and_then(|due_by| due_by.0),
+        }
+    }
+
+    /// The rest of the filter, applied to what the query returned.
+    pub fn matches(&self, task: &Task, today: NaiveDate) -> bool {
+        if !self.all && task.is_waiting(today) {
+            return false;
+        }
+        if self.unassigned && task.assignee.is_some() {
+            return false;
+        }
+        if let Some(wanted) = &self.assignee
+            && !task
+                .assignee
+                .as_ref()
+                .is_some_and(|assignee| assignee.eq_ignore_ascii_case(wanted))
+        {
+            return false;
+        }
+        true
+    }
+}
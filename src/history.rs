@@ -0,0 +1,196 @@
+use crate::board::Board;
+use crate::config::sidecar_dir;
+use crate::crypto;
+use crate::storage::atomic_write;
+use crate::task::{SEP, Status, Task, TaskId, escape, unescape};
+use fs2::FileExt;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{Error, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+/// Something that happened to a task, as recorded in its history.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    Created(String),
+    Status(Status, Status),
+    Description(String, String),
+    Deleted(String),
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Change::Created(description) => write!(f, "created \"{}\"", description),
+            Change::Status(from, to) => write!(f, "status: {} -> {}", from, to),
+            Change::Description(from, to) => {
+                write!(f, "description: \"{}\" -> \"{}\"", from, to)
+            },
+            Change::Deleted(description) => write!(f, "deleted \"{}\"", description),
+        }
+    }
+}
+
+/// One line of `<tasklist>.d/history`.
+#[derive(Debug, Clone)]
+pub struct Record {
+    /// `YYYY-MM-DD HH:MM:SS`.
+    pub at: String,
+    pub actor: String,
+    /// `Task::key` of the task, so the history follows it through renumbering.
+    pub key: String,
+    /// Its ID at the time.
+    pub id: TaskId,
+    pub change: Change,
+}
+
+impl Record {
+    /// `<at>\t<actor>\t<key>\t<id>\t<kind>\t<from>\t<to>`, with values escaped.
+    fn to_line(&self) -> String {
+        let (kind, from, to) = match &self.change {
+            Change::Created(description) => ("created", "", description.as_str()),
+            Change::Status(from, to) => ("status", from.as_label(), to.as_label()),
+            Change::Description(from, to) => ("description", from.as_str(), to.as_str()),
+            Change::Deleted(description) => ("deleted", description.as_str(), ""),
+        };
+        [
+            self.at.as_str(),
+            &escape(&self.actor),
+            &self.key,
+            &self.id.to_string(),
+            kind,
+            &escape(from),
+            &escape(to),
+        ]
+        .join(SEP)
+    }
+
+    fn from_line(line: &str) -> Option<Record> {
+        let columns: Vec<&str> = line.split(SEP).collect();
+        let [at, actor, key, id, kind, from, to] = columns.as_slice() else {
+            return None;
+        };
+        let (from, to) = (unescape(from), unescape(to));
+        let change = match *kind {
+            "created" => Change::Created(to),
+            "status" => Change::Status(Status::from_label(&from)?, Status::from_label(&to)?),
+            "description" => Change::Description(from, to),
+            "deleted" => Change::Deleted(from),
+            _ => return None,
+        };
+        Some(Record {
+            at: at.to_string(),
+            actor: unescape(actor),
+            key: key.to_string(),
+            id: id.parse().ok()?,
+            change,
+        })
+    }
+}
+
+/// The history of every task on a board: `<tasklist>.d/history`.
+pub fn history_path(tasklist_path: &str) -> PathBuf {
+    sidecar_dir(tasklist_path).join("history")
+}
+
+/// The status changes, description edits, additions and deletions between
+/// two versions of a board, as `(task, change)` pairs.
+pub fn changes(before: &Board, after: &Board) -> Vec<(Task, Change)> {
+    let old: HashMap<String, &Task> = before.tasks().map(|task| (task.key(), task)).collect();
+    let mut changes = Vec::new();
+    let mut kept = HashSet::new();
+    for task in after.tasks() {
+        let key = task.key();
+        match old.get(&key) {
+            None => changes.push((task.clone(), Change::Created(task.description.clone()))),
+            Some(old) => {
+                if old.status != task.status {
+                    changes.push((task.clone(), Change::Status(old.status, task.status)));
+                }
+                if old.description != task.description {
+                    changes.push((
+                        task.clone(),
+                        Change::Description(old.description.clone(), task.description.clone()),
+                    ));
+                }
+            },
+        }
+        kept.insert(key);
+    }
+    for task in before.tasks() {
+        if !kept.contains(&task.key()) {
+            changes.push((task.clone(), Change::Deleted(task.description.clone())));
+        }
+    }
+    changes
+}
+
+/// Appends `changes` to the board's history in one locked write. The
+/// history of an encrypted board is encrypted as a whole.
+pub fn record(
+    tasklist_path: &str,
+    encrypted: bool,
+    at: &str,
+    actor: &str,
+    changes: &[(Task, Change)],
+) -> Result<(), Error> {
+    let path = history_path(tasklist_path);
+    let context = |e: Error| {
+        Error::new(
+            e.kind(),
+            format!("Could not write {}: {}", path.display(), e),
+        )
+    };
+    fs::create_dir_all(sidecar_dir(tasklist_path)).map_err(context)?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(context)?;
+    file.lock_exclusive().map_err(context)?;
+
+    let mut lines = String::new();
+    for (task, change) in changes {
+        let record = Record {
+            at: at.to_string(),
+            actor: actor.to_string(),
+            key: task.key(),
+            id: task.id,
+            change: change.clone(),
+        };
+        lines.push_str(&record.to_line());
+        lines.push('\n');
+    }
+    if !encrypted {
+        return file.write_all(lines.as_bytes()).map_err(context);
+    }
+    // An encrypted file can't be appended to, so it is rewritten whole
+    let mut content = read_bytes(&path)?;
+    content.extend_from_slice(lines.as_bytes());
+    let sealed = crypto::seal(&content)?;
+    atomic_write(&path.to_string_lossy(), |writer| writer.write_all(&sealed)).map_err(context)
+}
+
+/// The history file's contents, decrypted; empty if there is none yet.
+pub fn read_bytes(path: &Path) -> Result<Vec<u8>, Error> {
+    match fs::read(path) {
+        Ok(bytes) => crypto::open(&path.display().to_string(), bytes),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(Error::new(
+            e.kind(),
+            format!("Could not read {}: {}", path.display(), e),
+        )),
+    }
+}
+
+/// The recorded history of the task with `key`, oldest first. Lines that
+/// don't parse are skipped.
+pub fn read(tasklist_path: &str, key: &str) -> Result<Vec<Record>, Error> {
+    let content = read_bytes(&history_path(tasklist_path))?;
+    Ok(String::from_utf8_lossy(&content)
+        .lines()
+        .filter_map(Record::from_line)
+        .filter(|record| record.key == key)
+        .collect())
+}
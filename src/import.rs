@@ -0,0 +1,512 @@
+use crate::dates::DAY_FORMAT;
+use crate::task::{Comment, DATE_FORMAT, Link, Status, TIME_FORMAT, Task, escape, timestamp};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, Utc};
+use clap::ValueEnum;
+use serde_json::{Map, Value};
+use std::io::{Error, ErrorKind};
+use uuid::Uuid;
+
+/// Files `tsk import` can read.
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum ImportFormat {
+    /// todo.txt, one task per line
+    Todotxt,
+    /// The output of `task export`
+    TaskwarriorJson,
+    /// A header row naming the columns, then one task per row
+    Csv,
+}
+
+/// Tasks read from another tool's file, not on the board yet.
+#[derive(Debug, Default)]
+pub struct Imported {
+    pub tasks: Vec<Task>,
+    /// What had no place on a task, e.g. `line 3: no field for rec:1w`.
+    pub unmapped: Vec<String>,
+}
+
+/// Reads tasks from `content`. Priorities, projects, contexts and tags,
+/// which boards have no columns for, become `priority=`, `project=`,
+/// `context=` and `tag=` fields. IDs are left at 0 for the board to assign.
+pub fn parse(format: ImportFormat, content: &str) -> Result<Imported, Error> {
+    let mut imported = Imported::default();
+    match format {
+        ImportFormat::Todotxt => {
+            for (index, line) in content.lines().enumerate() {
+                if let Some(task) = parse_todotxt(line, index + 1, &mut imported.unmapped) {
+                    imported.tasks.push(task);
+                }
+            }
+        },
+        ImportFormat::TaskwarriorJson => {
+            // `task export` writes an array; older versions one object per line
+            let values: Vec<Value> = if content.trim_start().starts_with('[') {
+                serde_json::from_str(content).map_err(invalid)?
+            } else {
+                content
+                    .lines()
+                    .filter(|line| !line.trim().is_empty())
+                    .map(serde_json::from_str)
+                    .collect::<Result<_, _>>()
+                    .map_err(invalid)?
+            };
+            for (index, value) in values.iter().enumerate() {
+                let Some(object) = value.as_object() else {
+                    imported
+                        .unmapped
+                        .push(format!("task {}: not an object, skipped", index + 1));
+                    continue;
+                };
+                if let Some(task) = parse_taskwarrior(object, index + 1, &mut imported.unmapped) {
+                    imported.tasks.push(task);
+                }
+            }
+        },
+        ImportFormat::Csv => parse_csv(content, &mut imported)?,
+    }
+    Ok(imported)
+}
+
+fn invalid(e: impl std::fmt::Display) -> Error {
+    Error::new(ErrorKind::InvalidData, e.to_string())
+}
+
+fn push_field(task: &mut Task, key: &str, value: &str) {
+    task.extra.push(format!("{}={}", key, escape(value)));
+}
+
+/// Descriptions are written as-is, so they must stay on one line.
+fn one_line(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn parse_day(value: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(value, DAY_FORMAT).ok()
+}
+
+/// `x (A) 2024-01-03 2024-01-01 Call Bob +Home @phone due:2024-01-05`
+fn parse_todotxt(line: &str, line_no: usize, unmapped: &mut Vec<String>) -> Option<Task> {
+    let mut words = line.split_whitespace().peekable();
+    let done = words.next_if_eq(&"x").is_some();
+    let priority = words
+        .next_if(|word| {
+            let bytes = word.as_bytes();
+            bytes.len() == 3
+                && bytes[0] == b'('
+                && bytes[1].is_ascii_uppercase()
+                && bytes[2] == b')'
+        })
+        .map(|word| word[1..2].to_string());
+    // A completed task may carry its completion date, then its creation date
+    let mut dates = Vec::new();
+    while dates.len() < if done { 2 } else { 1 } {
+        match words.peek().and_then(|word| parse_day(word)) {
+            Some(date) => {
+                dates.push(date);
+                words.next();
+            },
+            None => break,
+        }
+    }
+
+    let mut task = Task::new(
+        0,
+        if done {
+            Status::Done
+        } else {
+            Status::NotStarted
+        },
+        String::new(),
+        match dates.first() {
+            Some(date) => format!("{} 00:00", date.format(DAY_FORMAT)),
+            None => timestamp(),
+        },
+    );
+    if done {
+        task.completed = dates.first().and_then(|date| date.and_hms_opt(0, 0, 0));
+    }
+    // The task date is the completion date, so the creation date needs a field
+    if let Some(created) = dates.get(1) {
+        push_field(
+            &mut task,
+            "created",
+            &created.format(DAY_FORMAT).to_string(),
+        );
+    }
+    if let Some(priority) = priority {
+        push_field(&mut task, "priority", &priority);
+    }
+    let mut description = Vec::new();
+    for word in words {
+        if let Some(project) = word.strip_prefix('+').filter(|p| !p.is_empty()) {
+            push_field(&mut task, "project", project);
+            continue;
+        }
+        if let Some(context) = word.strip_prefix('@').filter(|c| !c.is_empty()) {
+            push_field(&mut task, "context", context);
+            continue;
+        }
+        let tag = word.split_once(':').filter(|(key, value)| {
+            !key.is_empty()
+                && key
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+                && !value.is_empty()
+                && !value.starts_with("//")
+        });
+        let mapped = match tag {
+            None => false,
+            Some(("due", value)) => parse_day(value).map(|due| task.due = Some(due)).is_some(),
+            Some(("t", value)) => parse_day(value)
+                .map(|wait| task.wait = Some(wait))
+                .is_some(),
+            Some(("pri", value)) => {
+                push_field(&mut task, "priority", value);
+                true
+            },
+            Some(("uuid", value)) => Uuid::parse_str(value)
+                .map(|uuid| task.uuid = Some(uuid))
+                .is_ok(),
+            Some(_) => {
+                unmapped.push(format!(
+                    "line {}: no field for {}, kept in the description",
+                    line_no, word
+                ));
+                description.push(word);
+                continue;
+            },
+        };
+        if !mapped {
+            if tag.is_some() {
+                unmapped.push(format!(
+                    "line {}: could not read {}, kept in the description",
+                    line_no, word
+                ));
+            }
+            description.push(word);
+        }
+    }
+    if description.is_empty() {
+        if !line.trim().is_empty() {
+            unmapped.push(format!("line {}: no description, skipped", line_no));
+        }
+        return None;
+    }
+    task.description = description.join(" ");
+    Some(task)
+}
+
+/// Fields of `task export` that are derived or internal to Taskwarrior.
+const TASKWARRIOR_INTERNAL: &[&str] = &["id", "urgency", "imask", "mask", "parent"];
+
+fn parse_taskwarrior(
+    object: &Map<String, Value>,
+    number: usize,
+    unmapped: &mut Vec<String>,
+) -> Option<Task> {
+    let description = object
+        .get("description")
+        .and_then(Value::as_str)
+        .map(one_line)
+        .unwrap_or_default();
+    let label = format!("task {} ({})", number, description);
+    if description.is_empty() {
+        unmapped.push(format!("task {}: no description, skipped", number));
+        return None;
+    }
+    let status = match object.get("status").and_then(Value::as_str) {
+        Some("completed") => Status::Done,
+        Some("deleted") => {
+            unmapped.push(format!("{}: deleted, skipped", label));
+            return None;
+        },
+        Some("recurring") => {
+            unmapped.push(format!(
+                "{}: recurrence template, skipped (its pending instances are imported)",
+                label
+            ));
+            return None;
+        },
+        _ if object.contains_key("start") => Status::InProgress,
+        _ => Status::NotStarted,
+    };
+    let time = |key: &str| {
+        object
+            .get(key)
+            .and_then(Value::as_str)
+            .and_then(parse_taskwarrior_time)
+    };
+    let date = time("end")
+        .or_else(|| time("modified"))
+        .or_else(|| time("entry"))
+        .map(|at| at.format(DATE_FORMAT).to_string())
+        .unwrap_or_else(timestamp);
+
+    let mut task = Task::new(0, status, description, date);
+    if status == Status::Done {
+        task.completed = time("end");
+    }
+    if time("end").or_else(|| time("modified")).is_some()
+        && let Some(entry) = time("entry")
+    {
+        push_field(&mut task, "created", &entry.format(DATE_FORMAT).to_string());
+    }
+    for (key, value) in object {
+        let text = value.as_str();
+        match (key.as_str(), text) {
+            ("description" | "status" | "start" | "end" | "modified" | "entry", _) => {},
+            (key, _) if TASKWARRIOR_INTERNAL.contains(&key) => {},
+            ("uuid", Some(text)) if Uuid::parse_str(text).is_ok() => {
+                task.uuid = Uuid::parse_str(text).ok();
+            },
+            ("due", Some(text)) if parse_taskwarrior_time(text).is_some() => {
+                task.due = parse_taskwarrior_time(text).map(|at| at.date());
+            },
+            ("wait", Some(text)) if parse_taskwarrior_time(text).is_some() => {
+                task.wait = parse_taskwarrior_time(text).map(|at| at.date());
+            },
+            ("project", Some(text)) => push_field(&mut task, "project", text),
+            ("priority", Some(text)) => push_field(&mut task, "priority", text),
+            ("tags", _) if value.is_array() => {
+                for tag in value
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(Value::as_str)
+                {
+                    push_field(&mut task, "tag", tag);
+                }
+            },
+            ("annotations", _) if value.is_array() => {
+                for annotation in value.as_array().into_iter().flatten() {
+                    let Some(text) = annotation.get("description").and_then(Value::as_str) else {
+                        continue;
+                    };
+                    let date = annotation
+                        .get("entry")
+                        .and_then(Value::as_str)
+                        .and_then(parse_taskwarrior_time)
+                        .map(|at| at.format(DATE_FORMAT).to_string())
+                        .unwrap_or_default();
+                    task.comments.push(Comment {
+                        date,
+                        text: text.to_string(),
+                    });
+                }
+            },
+            _ => unmapped.push(format!("{}: no field for {}={}", label, key, value)),
+        }
+    }
+    Some(task)
+}
+
+/// Taskwarrior stores UTC times as `20240201T103000Z`; boards use local time.
+fn parse_taskwarrior_time(text: &str) -> Option<NaiveDateTime> {
+    let utc = NaiveDateTime::parse_from_str(text, "%Y%m%dT%H%M%SZ")
+        .map(|at| at.and_utc())
+        .or_else(|_| DateTime::parse_from_rfc3339(text).map(|at| at.with_timezone(&Utc)))
+        .ok()?;
+    Some(utc.with_timezone(&Local).naive_local())
+}
+
+/// What a CSV column holds, going by its header.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Column {
+    Description,
+    Status,
+    Updated,
+    Completed,
+    Created,
+    Due,
+    Wait,
+    Estimate,
+    Assignee,
+    Priority,
+    Project,
+    Context,
+    Tags,
+    Url,
+    Notes,
+    Uuid,
+    /// Boards number their tasks themselves.
+    Id,
+}
+
+impl Column {
+    fn from_header(header: &str) -> Option<Column> {
+        let name = header.trim().to_lowercase().replace([' ', '-'], "_");
+        Some(match name.as_str() {
+            "description" | "title" | "task" | "summary" | "name" => Column::Description,
+            "status" | "state" => Column::Status,
+            "date" | "updated" | "modified" | "last_modified" => Column::Updated,
+            "completed" | "completion_date" | "done_date" | "end" => Column::Completed,
+            "created" | "creation_date" | "entry" => Column::Created,
+            "due" | "due_date" => Column::Due,
+            "wait" | "start_date" | "scheduled" => Column::Wait,
+            "estimate" => Column::Estimate,
+            "assignee" | "owner" | "assigned_to" => Column::Assignee,
+            "priority" => Column::Priority,
+            "project" => Column::Project,
+            "context" | "contexts" => Column::Context,
+            "tags" | "tag" | "labels" => Column::Tags,
+            "url" | "link" => Column::Url,
+            "notes" | "note" | "details" | "body" => Column::Notes,
+            "uuid" => Column::Uuid,
+            "id" | "#" => Column::Id,
+            _ => return None,
+        })
+    }
+}
+
+fn parse_csv(content: &str, imported: &mut Imported) -> Result<(), Error> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(content.as_bytes());
+    let columns: Vec<Option<Column>> = reader
+        .headers()
+        .map_err(invalid)?
+        .iter()
+        .map(|header| {
+            let column = Column::from_header(header);
+            if column.is_none() {
+                imported
+                    .unmapped
+                    .push(format!("column {}: no matching field, ignored", header));
+            }
+            column
+        })
+        .collect();
+    if !columns.contains(&Some(Column::Description)) {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "No description column (expected one named description, title, task or summary)",
+        ));
+    }
+
+    for (index, record) in reader.records().enumerate() {
+        let record = record.map_err(invalid)?;
+        // The header is line 1
+        let row = index + 2;
+        let mut task = Task::new(0, Status::NotStarted, String::new(), String::new());
+        let (mut updated, mut completed, mut created) = (None, None, None);
+        let mut could_not_read = |what: &str, value: &str| {
+            imported
+                .unmapped
+                .push(format!("row {}: could not read {} {}", row, what, value));
+        };
+        for (column, value) in columns.iter().zip(record.iter()) {
+            let value = value.trim();
+            let Some(column) = column else { continue };
+            if value.is_empty() {
+                continue;
+            }
+            match column {
+                Column::Description => task.description = one_line(value),
+                Column::Status => match parse_status(value) {
+                    Some(status) => task.status = status,
+                    None => could_not_read("status", value),
+                },
+                Column::Updated | Column::Completed | Column::Created => {
+                    let Some(at) = parse_time(value) else {
+                        could_not_read("date", value);
+                        continue;
+                    };
+                    match column {
+                        Column::Updated => updated = Some(at),
+                        Column::Completed => completed = Some(at),
+                        _ => created = Some(at),
+                    }
+                },
+                Column::Due | Column::Wait => {
+                    match parse_day(value).or_else(|| parse_time(value).map(|at| at.date())) {
+                        Some(day) if *column == Column::Due => task.due = Some(day),
+                        Some(day) => task.wait = Some(day),
+                        None => could_not_read("date", value),
+                    }
+                },
+                Column::Estimate => match value.parse() {
+                    Ok(estimate) => task.estimate = Some(estimate),
+                    Err(_) => could_not_read("estimate", value),
+                },
+                Column::Assignee => task.assignee = Some(value.to_string()),
+                Column::Priority => push_field(&mut task, "priority", value),
+                Column::Project => push_field(&mut task, "project", value),
+                Column::Context | Column::Tags => {
+                    let key = if *column == Column::Context {
+                        "context"
+                    } else {
+                        "tag"
+                    };
+                    for item in value.split([',', ' ']).filter(|item| !item.is_empty()) {
+                        push_field(&mut task, key, item.trim_start_matches(['@', '+', '#']));
+                    }
+                },
+                Column::Url => task.links.push(Link::Url(value.to_string())),
+                Column::Notes => task.notes = value.to_string(),
+                Column::Uuid => match Uuid::parse_str(value) {
+                    Ok(uuid) => task.uuid = Some(uuid),
+                    Err(_) => could_not_read("uuid", value),
+                },
+                Column::Id => {},
+            }
+        }
+        if task.description.is_empty() {
+            imported
+                .unmapped
+                .push(format!("row {}: no description, skipped", row));
+            continue;
+        }
+        if completed.is_some() && task.status == Status::NotStarted {
+            task.status = Status::Done;
+        }
+        if task.status == Status::Done {
+            task.completed = completed;
+        }
+        task.date = updated
+            .or(completed)
+            .or(created)
+            .map(|at| at.format(DATE_FORMAT).to_string())
+            .unwrap_or_else(timestamp);
+        if let Some(created) = created.filter(|_| updated.or(completed).is_some()) {
+            push_field(
+                &mut task,
+                "created",
+                &created.format(DATE_FORMAT).to_string(),
+            );
+        }
+        imported.tasks.push(task);
+    }
+    Ok(())
+}
+
+fn parse_status(value: &str) -> Option<Status> {
+    if let Some(status) = Status::from_label(value) {
+        return Some(status);
+    }
+    let name = value.to_lowercase().replace([' ', '-'], "_");
+    match name.as_str() {
+        "completed" | "complete" | "closed" | "finished" | "x" | "true" | "yes" => {
+            Some(Status::Done)
+        },
+        "started" | "doing" | "active" | "wip" => Some(Status::InProgress),
+        "todo" | "open" | "pending" | "new" | "false" | "no" => Some(Status::NotStarted),
+        _ => <Status as ValueEnum>::from_str(&name, true).ok(),
+    }
+}
+
+fn parse_time(value: &str) -> Option<NaiveDateTime> {
+    [
+        DATE_FORMAT,
+        TIME_FORMAT,
+        "%Y-%m-%dT%H:%M:%S",
+        "%Y-%m-%dT%H:%M",
+    ]
+    .iter()
+    .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+    .or_else(|| {
+        DateTime::parse_from_rfc3339(value)
+            .ok()
+            .map(|at| at.with_timezone(&Local).naive_local())
+    })
+    .or_else(|| parse_day(value).map(|day| day.and_time(Default::default())))
+}
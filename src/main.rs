@@ -1,19 +1,64 @@
+mod backup;
+mod board;
 mod cli;
+mod config;
+mod crypto;
+mod dates;
+mod doctor;
+mod export;
+mod filter;
+mod history;
+mod import;
 mod manager;
+mod merge;
+mod recurrence;
+mod report;
+mod scan;
+mod storage;
+mod sync;
 mod task;
 mod tui;
+mod user;
 
-use crate::cli::{Cli, Commands};
+use crate::cli::{BackupAction, Cli, Commands, ReportKind, SyncTarget, TimerAction};
+use crate::filter::TaskFilter;
 use crate::manager::Mngr;
+use crate::report::Period;
+use crate::task::TaskChanges;
 use clap::Parser;
 
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+/// Default board names, in the order they are looked for.
+const DEFAULT_BOARDS: &[&str] = &[
+    ".tasklist",
+    ".tasklist.toml",
+    ".tasklist.json",
+    ".tasklist.yaml",
+    ".tasklist.yml",
+];
 
 fn get_tasklist_path(custom: Option<String>) -> (String, String) {
-    let raw_path = custom.unwrap_or_else(|| ".tasklist".to_string());
+    let raw_path = custom.unwrap_or_else(|| {
+        DEFAULT_BOARDS
+            .iter()
+            .find(|name| Path::new(name).exists())
+            .unwrap_or(&DEFAULT_BOARDS[0])
+            .to_string()
+    });
 
-    let path_buf = fs::canonicalize(raw_path).unwrap_or_else(|_| PathBuf::from(".tasklist"));
+    // A board that doesn't exist yet can't be canonicalized, its directory can
+    let path_buf = fs::canonicalize(&raw_path)
+        .or_else(|_| {
+            let path = Path::new(&raw_path);
+            let dir = path
+                .parent()
+                .filter(|dir| !dir.as_os_str().is_empty())
+                .unwrap_or_else(|| Path::new("."));
+            fs::canonicalize(dir).map(|dir| dir.join(path.file_name().unwrap_or_default()))
+        })
+        .unwrap_or_else(|_| PathBuf::from(&raw_path));
     let path_string = path_buf.to_string_lossy().to_string();
 
     let title = match path_buf.parent() {
@@ -35,23 +80,142 @@ fn main() {
         eprintln!("Using tasklist file: {}", tasklist_path);
     }
 
-    let mngr = Mngr::new(tasklist_path, Some(project_title));
+    let mngr = Mngr::new(tasklist_path, Some(project_title))
+        .with_strict(args.strict)
+        .with_dry_run(args.dry_run);
 
     let result = match args.command {
-        Some(Commands::Add { description }) => mngr.add_task(description),
+        Some(Commands::Add {
+            description,
+            estimate,
+            due,
+            wait,
+            assignee,
+            recur,
+        }) => mngr.add_task(
+            description,
+            TaskChanges {
+                estimate,
+                due: due.map(|due| due.0),
+                wait: wait.map(|wait| wait.0),
+                assignee: assignee.map(Some),
+                recurrence: recur.map(|recur| recur.0),
+                ..TaskChanges::default()
+            },
+        ),
         Some(Commands::Update {
             id,
             status,
             description,
-        }) => mngr.update_task(id, status, description),
-        Some(Commands::Show { kanban }) => mngr.list_tasks(kanban),
-        Some(Commands::Delete { id }) => mngr.delete_task(id),
+            estimate,
+            due,
+            wait,
+            recur,
+        }) => mngr.resolve(&id).and_then(|id| {
+            mngr.update_task(
+                id,
+                TaskChanges {
+                    status,
+                    description,
+                    estimate,
+                    due: due.map(|due| due.0),
+                    wait: wait.map(|wait| wait.0),
+                    recurrence: recur.map(|recur| recur.0),
+                    ..TaskChanges::default()
+                },
+            )
+        }),
+        Some(Commands::Show { id: Some(id), .. }) => {
+            mngr.resolve(&id).and_then(|id| mngr.show_task(id))
+        },
+        Some(Commands::Show {
+            recurring: true, ..
+        }) => mngr.list_recurring(),
+        Some(Commands::Show {
+            id: None,
+            kanban,
+            filter,
+            ..
+        }) => mngr.list_tasks(kanban, &filter),
+        Some(Commands::Assign { id, name }) => {
+            let name = (!name.eq_ignore_ascii_case("none")).then_some(name);
+            mngr.resolve(&id).and_then(|id| mngr.assign_task(id, name))
+        },
+        Some(Commands::Doctor { fix }) => mngr.doctor(fix),
+        Some(Commands::Backup { action }) => match action {
+            BackupAction::List => mngr.list_backups(),
+            BackupAction::Restore { number } => mngr.restore_backup(number),
+        },
+        Some(Commands::Migrate { to }) => mngr.migrate(to),
+        Some(Commands::Convert { output, to, force }) => mngr.convert(&output, to, force),
+        Some(Commands::Import { from, input }) => mngr.import_tasks(from, &input),
+        Some(Commands::Export {
+            format,
+            output,
+            grouped,
+            filter,
+        }) => mngr.export_tasks(format, &filter, output.as_deref(), grouped),
+        Some(Commands::IcalFeed { output, off }) => mngr.ical_feed(output, off),
+        Some(Commands::Sync {
+            target: SyncTarget::Markdown { path },
+        }) => mngr.sync_markdown(&path),
+        Some(Commands::Scan { paths }) => mngr.scan(paths),
+        Some(Commands::History { id }) => mngr.resolve(&id).and_then(|id| mngr.show_history(id)),
+        Some(Commands::Log { id }) => mngr.resolve(&id).and_then(|id| mngr.task_log(id)),
+        Some(Commands::Encrypt) => mngr.set_encryption(true),
+        Some(Commands::Decrypt) => mngr.set_encryption(false),
+        Some(Commands::Rehash) => mngr.rehash(),
+        Some(Commands::Renumber) => mngr.renumber_tasks(),
+        Some(Commands::MergeDriver { base, ours, theirs }) => {
+            merge::run_driver(&base, &ours, &theirs)
+        },
+        Some(Commands::Claim { id }) => mngr.resolve(&id).and_then(|id| mngr.claim_task(id)),
+        Some(Commands::LinkUrl { id, url }) => {
+            mngr.resolve(&id).and_then(|id| mngr.link_url(id, url))
+        },
+        Some(Commands::Attach { id, path }) => {
+            mngr.resolve(&id).and_then(|id| mngr.attach_file(id, path))
+        },
+        Some(Commands::Snooze { id, until }) => mngr
+            .resolve(&id)
+            .and_then(|id| mngr.snooze_task(id, until.0)),
+        Some(Commands::Delete { id }) => mngr.resolve(&id).and_then(|id| mngr.delete_task(id)),
+        Some(Commands::Comment { id, text }) => {
+            mngr.resolve(&id).and_then(|id| mngr.add_comment(id, text))
+        },
+        Some(Commands::Note { id, text, append }) => mngr
+            .resolve(&id)
+            .and_then(|id| mngr.set_notes(id, text, append)),
+        Some(Commands::Timer { action }) => match action {
+            TimerAction::Start { id } => mngr.resolve(&id).and_then(|id| mngr.start_timer(id)),
+            TimerAction::Stop => mngr.stop_timer(),
+            TimerAction::Status => mngr.timer_status(),
+        },
+        Some(Commands::Report {
+            kind: ReportKind::Time { week, today },
+        }) => {
+            let period = if week {
+                Period::Week
+            } else if today {
+                Period::Today
+            } else {
+                Period::All
+            };
+            mngr.time_report(period)
+        },
+        Some(Commands::Report {
+            kind: ReportKind::Estimates,
+        }) => mngr.estimate_report(),
+        Some(Commands::Config { key, value }) => mngr.show_config(key, value),
         Some(Commands::Tui) => tui::run(mngr),
-        None => mngr.list_tasks(args.kanban), // Default: show tasks
+        None => mngr.list_tasks(args.kanban, &TaskFilter::default()), // Default: show tasks
     };
 
     if let Err(e) = result {
         eprintln!("Error: {}", e);
         std::process::exit(1);
     }
+    if args.dry_run {
+        eprintln!("Dry run: nothing was saved");
+    }
 }
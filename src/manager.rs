@@ -1,16 +1,41 @@
-use crate::task::{Status, Task};
+use crate::backup;
+use crate::board::Board;
+use crate::config::Config;
+use crate::crypto;
+use crate::dates::today;
+use crate::doctor;
+use crate::export::{self, ExportFormat};
+use crate::filter::TaskFilter;
+use crate::history;
+use crate::import::{self, ImportFormat};
+use crate::report::{self, EstimateTotal, Period};
+use crate::scan;
+use crate::storage::{self, Backend, Change, EventStorage, MemoryStorage, Storage, TsvStorage};
+use crate::sync;
+use crate::task::{
+    Comment, DATE_FORMAT, Interval, Link, Status, TIME_FORMAT, Task, TaskChanges, TaskId, TaskRef,
+    format_duration, now, timestamp,
+};
+use crate::user::{current_user, initials};
+use chrono::{NaiveDate, NaiveDateTime};
 use colored::Colorize;
-use fs2::FileExt;
-use std::fs::{File, OpenOptions};
-use std::io::{BufRead, BufReader, BufWriter, Error, Write};
-use std::path::Path;
+use std::cell::OnceCell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{Error, Write};
+use std::path::{Path, PathBuf};
 use tabled::settings::object::Segment;
 use tabled::settings::{Modify, Width};
 use tabled::{Table, settings::Style};
+use uuid::Uuid;
 #[derive(Debug)]
 pub struct Mngr {
     tasklist_path: String,
     title: Option<String>,
+    strict: bool,
+    dry_run: bool,
+    /// Opened on first use, so commands that don't touch the board never
+    /// read its config.
+    storage: OnceCell<Box<dyn Storage>>,
 }
 
 impl Mngr {
@@ -18,10 +43,43 @@ impl Mngr {
         Self {
             tasklist_path,
             title,
+            strict: false,
+            dry_run: false,
+            storage: OnceCell::new(),
         }
     }
 
-    pub fn add_task(&self, description: String) -> Result<(), Error> {
+    /// Refuse to read files with unparseable task lines, on top of the
+    /// board's `strict` setting.
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Work on an in-memory copy of the board and never write it back.
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    fn storage(&self) -> Result<&dyn Storage, Error> {
+        if let Some(storage) = self.storage.get() {
+            return Ok(storage.as_ref());
+        }
+        let config = Config::load(&self.tasklist_path)?;
+        let mut storage = storage::open(&self.tasklist_path, &config, self.strict);
+        if self.dry_run {
+            let board = match storage.load() {
+                Ok(board) => Some(board),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+                Err(e) => return Err(e),
+            };
+            storage = Box::new(MemoryStorage::new(board));
+        }
+        Ok(self.storage.get_or_init(|| storage).as_ref())
+    }
+
+    pub fn add_task(&self, description: String, changes: TaskChanges) -> Result<(), Error> {
         if description.is_empty() {
             return Err(Error::new(
                 std::io::ErrorKind::InvalidInput,
@@ -29,167 +87,758 @@ impl Mngr {
             ));
         }
 
-        let (mut max_id, has_metadata) = self.read_metadata()?;
+        let mut task = Task::new(0, Status::NotStarted, description, timestamp());
+        changes.apply(&mut task);
+        let task = self.update_board(|board| {
+            let id = board.push(task)?;
+            Ok(board.task_mut(id)?.clone())
+        })?;
+
+        println!("{} {}", "Added task:".green(), format!("{task}").yellow());
+        Ok(())
+    }
 
-        if !has_metadata {
-            max_id = self.scan_max_id()?;
+    pub fn update_task(&self, id: TaskId, changes: TaskChanges) -> Result<(), Error> {
+        if changes.is_empty() {
+            return Err(Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "Nothing to update (pass --status, --description, --estimate, --due, --wait or --recur)",
+            ));
         }
+        let auto_timer = Config::load(&self.tasklist_path)?.auto_timer;
+        let now = now();
+        let (task, spawned) = self.update_board(|board| {
+            let task = board.task_mut(id)?;
+            let previous = task.status;
+            changes.apply(task);
+            task.date = timestamp();
 
-        let mut existing_tasks = Vec::new();
-        if let Ok(file) = OpenOptions::new().read(true).open(&self.tasklist_path) {
-            let reader = BufReader::new(&file);
-            for line in reader.lines() {
-                let line =
-                    line.map_err(|e| Error::new(e.kind(), format!("Failed to read line: {}", e)))?;
-                if !line.starts_with("#") && !line.is_empty() {
-                    existing_tasks.push(line);
+            let status = task.status;
+            if auto_timer && status != previous {
+                if status == Status::InProgress {
+                    stop_running_timers(board, now);
+                    board.task_mut(id)?.start_timer(now);
+                } else {
+                    board.task_mut(id)?.stop_timer(now);
                 }
             }
+
+            // Completing a recurring task hands its rule on to the next occurrence
+            let task = board.task_mut(id)?;
+            let mut spawned = None;
+            if status == Status::Done
+                && previous != Status::Done
+                && let Some(next) = task.next_occurrence(today())?
+            {
+                task.recurrence = None;
+                let next_id = board.push(next)?;
+                spawned = Some(board.task_mut(next_id)?.clone());
+            }
+            Ok((board.task_mut(id)?.clone(), spawned))
+        })?;
+        println!("{} {}", "Updated task:".green(), format!("{task}").yellow());
+        if let Some(next) = spawned {
+            println!(
+                "{} {} (due {})",
+                "Next occurrence:".green(),
+                format!("{next}").yellow(),
+                next.due.map(|due| due.to_string()).unwrap_or_default()
+            );
         }
+        Ok(())
+    }
 
-        let new_id = max_id + 1;
-        let today = chrono::Local::now().format("%Y-%m-%d %H:%M").to_string();
-        let task = Task::new(new_id, Status::NotStarted, description.clone(), today);
+    /// Starts a timer on a task, stopping whichever timer was running on the board.
+    pub fn start_timer(&self, id: TaskId) -> Result<(), Error> {
+        let now = now();
+        let (task, stopped) = self.update_board(|board| {
+            if board.task_mut(id)?.timer.is_some() {
+                return Err(Error::new(
+                    std::io::ErrorKind::AlreadyExists,
+                    format!("Timer is already running for task {}", id),
+                ));
+            }
+            let stopped = stop_running_timers(board, now);
+            let task = board.task_mut(id)?;
+            task.start_timer(now);
+            Ok((task.clone(), stopped))
+        })?;
 
-        self.atomic_write(|writer| {
-            self.write_metadata(writer, new_id)?;
+        for (stopped, interval) in stopped {
+            println!(
+                "{} {} ({})",
+                "Stopped timer:".green(),
+                format!("{stopped}").yellow(),
+                format_duration(interval.duration())
+            );
+        }
+        println!(
+            "{} {}",
+            "Started timer:".green(),
+            format!("{task}").yellow()
+        );
+        Ok(())
+    }
 
-            for task_line in &existing_tasks {
-                writeln!(writer, "{}", task_line)?;
+    pub fn stop_timer(&self) -> Result<(), Error> {
+        let now = now();
+        let stopped = self.update_board(|board| {
+            let stopped = stop_running_timers(board, now);
+            if stopped.is_empty() {
+                return Err(Error::new(
+                    std::io::ErrorKind::NotFound,
+                    "No timer is running",
+                ));
             }
-            task.write_to(writer)?;
-
-            Ok(())
+            Ok(stopped)
         })?;
 
-        println!("{} {}", "Added task:".green(), format!("{task}").yellow());
+        for (task, interval) in stopped {
+            println!(
+                "{} {} ({}, total {})",
+                "Stopped timer:".green(),
+                format!("{task}").yellow(),
+                format_duration(interval.duration()),
+                format_duration(task.tracked(now))
+            );
+        }
         Ok(())
     }
 
-    pub fn update_task(
-        &self,
-        id: i32,
-        status: Status,
-        description: Option<String>,
-    ) -> Result<(), Error> {
-        let (max_id, has_metadata) = self.read_metadata()?;
+    pub fn timer_status(&self) -> Result<(), Error> {
+        let now = now();
+        match self.get_tasks()?.iter().find(|task| task.timer.is_some()) {
+            Some(task) => println!(
+                "{} {} ({})",
+                "Timer running:".green(),
+                format!("{task}").yellow(),
+                format_duration(now - task.timer.unwrap_or(now))
+            ),
+            None => println!("{}", "No timer is running".yellow()),
+        }
+        Ok(())
+    }
 
-        let tasklist = OpenOptions::new()
-            .read(true)
-            .open(&self.tasklist_path)
-            .map_err(|e| {
-                Error::new(
-                    e.kind(),
-                    format!("Could not open task list {}: {}", self.tasklist_path, e),
-                )
-            })?;
-        let reader = BufReader::new(&tasklist);
-        let lines: Vec<String> = reader
-            .lines()
-            .collect::<Result<Vec<String>, _>>()
-            .map_err(|e| Error::new(e.kind(), format!("Failed to read lines: {}", e)))?;
+    pub fn time_report(&self, period: Period) -> Result<(), Error> {
+        let tasks = self.get_tasks()?;
+        report::print_time_report(&tasks, period, now());
+        Ok(())
+    }
 
-        let mut task_found = false;
-        let mut updated_lines = Vec::new();
+    pub fn estimate_report(&self) -> Result<(), Error> {
+        let tasks = self.get_tasks()?;
+        report::print_estimate_report(&tasks, now());
+        Ok(())
+    }
 
-        for line in lines {
-            if line.starts_with("#") {
-                continue;
+    pub fn show_config(&self, key: Option<String>, value: Option<String>) -> Result<(), Error> {
+        let mut config = Config::load(&self.tasklist_path)?;
+        match (key, value) {
+            (Some(key), Some(value)) => {
+                config.set(&key, &value)?;
+                if !self.dry_run {
+                    config.save(&self.tasklist_path)?;
+                }
+                println!("{} {} = {}", "Set".green(), key, value);
+            },
+            (Some(key), None) => match config.get(&key) {
+                Some(value) => println!("{}", value),
+                None => return Err(Config::unknown_key(&key)),
+            },
+            (None, _) => {
+                for key in Config::KEYS {
+                    println!("{} = {}", key, config.get(key).unwrap_or_default());
+                }
+            },
+        }
+        Ok(())
+    }
+
+    pub fn assign_task(&self, id: TaskId, assignee: Option<String>) -> Result<(), Error> {
+        let (task, previous) = self.update_board(|board| {
+            let task = board.task_mut(id)?;
+            let previous = std::mem::replace(&mut task.assignee, assignee);
+            Ok((task.clone(), previous))
+        })?;
+        let was = previous
+            .filter(|previous| Some(previous) != task.assignee.as_ref())
+            .map(|previous| format!(" (was {})", previous))
+            .unwrap_or_default();
+        match &task.assignee {
+            Some(assignee) => println!(
+                "{} {}{}",
+                format!("Assigned to {}:", assignee).green(),
+                format!("{task}").yellow(),
+                was.bright_black()
+            ),
+            None => println!(
+                "{} {}{}",
+                "Unassigned:".green(),
+                format!("{task}").yellow(),
+                was.bright_black()
+            ),
+        }
+        Ok(())
+    }
+
+    /// Assigns a task to whoever is running `tsk`.
+    pub fn claim_task(&self, id: TaskId) -> Result<(), Error> {
+        let me = current_user(&self.board_dir()).ok_or_else(|| {
+            Error::new(
+                std::io::ErrorKind::NotFound,
+                "Could not determine your name (set git user.name or $USER)",
+            )
+        })?;
+        self.assign_task(id, Some(me))
+    }
+
+    pub fn link_url(&self, id: TaskId, url: String) -> Result<(), Error> {
+        if !url.contains("://") && !url.starts_with("mailto:") {
+            return Err(Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "`{}` does not look like a URL (expected e.g. https://...)",
+                    url
+                ),
+            ));
+        }
+        self.add_link(id, Link::Url(url))
+    }
+
+    /// Attaches a file reference. The path is taken relative to the current
+    /// directory and stored relative to the board's directory when possible.
+    pub fn attach_file(&self, id: TaskId, path: String) -> Result<(), Error> {
+        let absolute = std::fs::canonicalize(&path).or_else(|_| std::path::absolute(&path))?;
+        if !absolute.exists() {
+            eprintln!(
+                "{}",
+                format!("Warning: {} does not exist", absolute.display()).yellow()
+            );
+        }
+        let stored = self.board_relative(&path)?;
+        self.add_link(id, Link::File(stored.to_string_lossy().to_string()))
+    }
+
+    /// `path`, taken relative to the current directory, as it is stored:
+    /// relative to the board's directory when it lives under it.
+    fn board_relative(&self, path: &str) -> Result<PathBuf, Error> {
+        let absolute = std::fs::canonicalize(path).or_else(|_| std::path::absolute(path))?;
+        let board_dir = std::fs::canonicalize(self.board_dir())
+            .or_else(|_| std::path::absolute(self.board_dir()))?;
+        Ok(absolute
+            .strip_prefix(&board_dir)
+            .map(Path::to_path_buf)
+            .unwrap_or(absolute))
+    }
+
+    fn add_link(&self, id: TaskId, link: Link) -> Result<(), Error> {
+        let task = self.modify_task(id, |task| {
+            if task.links.contains(&link) {
+                return Err(Error::new(
+                    std::io::ErrorKind::AlreadyExists,
+                    format!("Task {} already links to {}", id, link.target()),
+                ));
             }
+            task.links.push(link);
+            Ok(())
+        })?;
+        println!("{} {}", "Linked:".green(), format!("{task}").yellow());
+        Ok(())
+    }
 
-            let parts: Vec<&str> = line.split("\t").collect();
-            if parts.len() >= 3 {
-                if let Ok(task_id) = parts[0].parse::<i32>() {
-                    if task_id == id {
-                        task_found = true;
-                        let new_description =
-                            String::from(description.as_deref().unwrap_or(parts[2]));
-                        let today = chrono::Local::now().format("%Y-%m-%d %H:%M").to_string();
-                        let task = Task::new(id, status, new_description, today);
-                        updated_lines.push(task.to_file_string());
-                        println!("{} {}", "Updated task:".green(), format!("{task}").yellow());
-                    } else {
-                        updated_lines.push(line);
-                    }
+    /// URL or absolute path a link points to, with file paths resolved
+    /// against the board's directory.
+    pub fn resolve_link(&self, link: &Link) -> String {
+        match link {
+            Link::Url(url) => url.clone(),
+            Link::File(path) => self.board_dir().join(path).to_string_lossy().to_string(),
+        }
+    }
+
+    /// Opens a link with the system opener (`open` on macOS, `xdg-open` elsewhere).
+    pub fn open_link(&self, link: &Link) -> Result<(), Error> {
+        let opener = if cfg!(target_os = "macos") {
+            "open"
+        } else {
+            "xdg-open"
+        };
+        let mut target = self.resolve_link(link);
+        // `file:line` links from `tsk scan` open the file
+        if let Link::File(_) = link
+            && !Path::new(&target).exists()
+            && let Some((file, line)) = target.rsplit_once(':')
+            && line.parse::<usize>().is_ok()
+        {
+            target = file.to_string();
+        }
+        std::process::Command::new(opener)
+            .arg(target)
+            .stdin(std::process::Stdio::null())
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .spawn()
+            .map_err(|e| Error::new(e.kind(), format!("Failed to run {}: {}", opener, e)))?;
+        Ok(())
+    }
+
+    /// Directory containing the task list.
+    fn board_dir(&self) -> PathBuf {
+        Path::new(&self.tasklist_path)
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| PathBuf::from("."))
+    }
+
+    /// Hides a task from the list views until `until`.
+    pub fn snooze_task(&self, id: TaskId, until: Option<NaiveDate>) -> Result<(), Error> {
+        let task = self.modify_task(id, |task| {
+            task.wait = until;
+            Ok(())
+        })?;
+        match until {
+            Some(until) => println!(
+                "{} {} {}",
+                format!("Snoozed until {}:", until).green(),
+                format!("{task}").yellow(),
+                if until <= today() {
+                    "(already visible)".bright_black()
                 } else {
-                    updated_lines.push(line);
+                    "".normal()
                 }
-            } else if !line.is_empty() {
-                updated_lines.push(line);
-            }
+            ),
+            None => println!("{} {}", "Unsnoozed:".green(), format!("{task}").yellow()),
         }
+        Ok(())
+    }
 
-        if !task_found {
+    pub fn add_comment(&self, id: TaskId, text: String) -> Result<(), Error> {
+        if text.trim().is_empty() {
             return Err(Error::new(
-                std::io::ErrorKind::NotFound,
-                format!("Task with ID {} not found", id),
+                std::io::ErrorKind::InvalidInput,
+                "Comment cannot be empty",
             ));
         }
+        let task = self.modify_task(id, |task| {
+            task.comments.push(Comment {
+                date: timestamp(),
+                text,
+            });
+            Ok(())
+        })?;
+        println!(
+            "{} {}",
+            "Commented on task:".green(),
+            format!("{task}").yellow()
+        );
+        Ok(())
+    }
 
-        let current_max_id = if has_metadata {
-            max_id
-        } else {
-            self.scan_max_id()?
+    /// Replaces (or appends to) the notes of a task. Without `text` the
+    /// current notes are opened in `$VISUAL`/`$EDITOR`.
+    pub fn set_notes(&self, id: TaskId, text: Option<String>, append: bool) -> Result<(), Error> {
+        let text = match text {
+            Some(text) => text,
+            None => edit_in_editor(&self.get_task(id)?.notes)?,
         };
+        let task = self.modify_task(id, |task| {
+            if append && !task.notes.is_empty() {
+                task.notes.push('\n');
+                task.notes.push_str(&text);
+            } else {
+                task.notes = text;
+            }
+            Ok(())
+        })?;
+        println!(
+            "{} {}",
+            "Updated notes:".green(),
+            format!("{task}").yellow()
+        );
+        Ok(())
+    }
 
-        self.atomic_write(|writer| {
-            self.write_metadata(writer, current_max_id)?;
-
-            for line in &updated_lines {
-                writeln!(writer, "{}", line)?;
+    /// Rewrites the task with the given ID after applying `edit` to it and
+    /// returns the updated task.
+    /// Lets `edit` change one task and saves only that task through the
+    /// board's storage backend. Nothing is saved if `edit` fails.
+    fn modify_task<F>(&self, id: TaskId, edit: F) -> Result<Task, Error>
+    where
+        F: FnOnce(&mut Task) -> Result<(), Error>,
+    {
+        let mut edit = Some(edit);
+        let mut before = None;
+        let task = self.storage()?.update_task(id, &mut |task| {
+            task.uuid.get_or_insert_with(Uuid::new_v4);
+            before = Some(task.clone());
+            if let Some(edit) = edit.take() {
+                edit(task)?;
             }
+            task.stamp_completion(
+                before
+                    .as_ref()
+                    .is_some_and(|task| task.status == Status::Done),
+                now(),
+            );
+            Ok(())
+        })?;
+        if let Some(before) = before {
+            self.record_changes(history::changes(
+                &Board::of(vec![before]),
+                &Board::of(vec![task.clone()]),
+            ))?;
+        }
+        Ok(task)
+    }
 
+    /// Loads the whole board, lets `edit` change it and saves it through the
+    /// board's storage backend. Nothing is saved if `edit` fails.
+    fn update_board<F, R>(&self, edit: F) -> Result<R, Error>
+    where
+        F: FnOnce(&mut Board) -> Result<R, Error>,
+    {
+        let mut edit = Some(edit);
+        let mut result = None;
+        let mut changes = Vec::new();
+        self.storage()?.update(&mut |board| {
+            board.assign_missing_uuids();
+            let before = board.clone();
+            if let Some(edit) = edit.take() {
+                result = Some(edit(board)?);
+            }
+            board.assign_missing_uuids();
+            board.stamp_completions(&before, now());
+            changes = history::changes(&before, board);
             Ok(())
         })?;
+        self.record_changes(changes)?;
+        result.ok_or_else(|| Error::other("Board update did not run"))
+    }
 
+    /// Adds a saved write to the board's history and refreshes its feed.
+    fn record_changes(&self, changes: Vec<(Task, history::Change)>) -> Result<(), Error> {
+        if !self.dry_run {
+            let config = Config::load(&self.tasklist_path)?;
+            if !changes.is_empty() {
+                let actor =
+                    current_user(&self.board_dir()).unwrap_or_else(|| "unknown".to_string());
+                history::record(
+                    &self.tasklist_path,
+                    config.encrypted,
+                    &now().format(TIME_FORMAT).to_string(),
+                    &actor,
+                    &changes,
+                )?;
+            }
+            self.refresh_ical_feed(&config);
+        }
         Ok(())
     }
 
-    pub fn get_tasks(&self) -> Result<Vec<Task>, Error> {
-        let tasklist = OpenOptions::new()
-            .read(true)
-            .open(&self.tasklist_path)
-            .map_err(|e| {
+    /// Rewrites the board's iCalendar feed, if it has one, with the tasks a
+    /// plain `tsk export` would write. Every command that writes the board
+    /// calls this; the board is already saved, so a failure is only a warning.
+    fn refresh_ical_feed(&self, config: &Config) {
+        let Some(feed) = &config.ical_feed else {
+            return;
+        };
+        // The feed is plain text
+        if config.encrypted {
+            return;
+        }
+        let path = self.board_dir().join(feed);
+        // Opened afresh, as `migrate` may have changed the backend
+        let storage = storage::open(&self.tasklist_path, config, self.strict);
+        let written = self.filter_in(storage.as_ref(), &TaskFilter::default());
+        let written = written.and_then(|(tasks, _)| {
+            let title = self.title.as_deref().unwrap_or("My Tasks");
+            let rendered = export::render(ExportFormat::Ics, &tasks, title, false, today())?;
+            storage::atomic_write(&path.to_string_lossy(), |writer| {
+                writer.write_all(rendered.as_bytes())
+            })
+        });
+        if let Err(e) = written {
+            eprintln!(
+                "{} Could not update the iCalendar feed {}: {}",
+                "Warning:".yellow(),
+                path.display(),
+                e
+            );
+        }
+    }
+
+    /// Shows, sets or turns off the iCalendar file kept in step with the
+    /// board. Setting it writes the file straight away.
+    pub fn ical_feed(&self, output: Option<String>, off: bool) -> Result<(), Error> {
+        let mut config = Config::load(&self.tasklist_path)?;
+        if off {
+            config.ical_feed = None;
+            if !self.dry_run {
+                config.save(&self.tasklist_path)?;
+            }
+            println!("{}", "iCalendar feed turned off".green());
+            return Ok(());
+        }
+        let Some(output) = output else {
+            match &config.ical_feed {
+                Some(feed) => println!("{}", self.board_dir().join(feed).display()),
+                None => println!("No iCalendar feed (set one with --output)"),
+            }
+            return Ok(());
+        };
+        if config.encrypted {
+            return Err(Error::new(
+                std::io::ErrorKind::Unsupported,
+                "An encrypted board can't have a plain-text iCalendar feed",
+            ));
+        }
+        config.ical_feed = Some(self.board_relative(&output)?.to_string_lossy().to_string());
+        if !self.dry_run {
+            config.save(&self.tasklist_path)?;
+            self.refresh_ical_feed(&config);
+        }
+        println!(
+            "{} {} (rewritten after every change)",
+            "iCalendar feed:".green(),
+            output
+        );
+        Ok(())
+    }
+
+    pub fn get_task(&self, id: TaskId) -> Result<Task, Error> {
+        self.storage()?.find(id)?.ok_or_else(|| {
+            Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("Task with ID {} not found", id),
+            )
+        })
+    }
+
+    /// Resolves a command-line task reference to its short ID. Numeric IDs win;
+    /// anything else must be a prefix of exactly one task's UUID.
+    pub fn resolve(&self, task_ref: &TaskRef) -> Result<TaskId, Error> {
+        if task_ref.0.chars().all(|c| c.is_ascii_digit()) {
+            let found = match task_ref.0.parse::<TaskId>() {
+                Ok(id) => self.storage()?.find(id)?,
+                Err(_) => None,
+            };
+            return found.map(|task| task.id).ok_or_else(|| {
                 Error::new(
-                    e.kind(),
-                    format!("Could not read task list {}: {}", self.tasklist_path, e),
+                    std::io::ErrorKind::NotFound,
+                    format!("Task with ID {} not found", task_ref),
                 )
-            })?;
-        let reader = BufReader::new(&tasklist);
-        let mut tasks: Vec<Task> = vec![];
-        for line in reader.lines() {
-            let line =
-                line.map_err(|e| Error::new(e.kind(), format!("Failed to read line: {}", e)))?;
-            if line.starts_with("#") {
-                continue;
+            });
+        }
+
+        let tasks = self.get_tasks()?;
+
+        let matches: Vec<&Task> = tasks
+            .iter()
+            .filter(|task| {
+                task.uuid
+                    .is_some_and(|uuid| uuid.to_string().starts_with(&task_ref.0))
+            })
+            .collect();
+        match matches.as_slice() {
+            [task] => Ok(task.id),
+            [] => Err(Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("Task with ID {} not found", task_ref),
+            )),
+            _ => Err(Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "UUID prefix {} is ambiguous ({} tasks match)",
+                    task_ref,
+                    matches.len()
+                ),
+            )),
+        }
+    }
+
+    /// Prints the full detail view of a single task, including notes and comments.
+    pub fn show_task(&self, id: TaskId) -> Result<(), Error> {
+        let task = self.get_task(id)?;
+
+        println!(
+            "{} {}",
+            format!("[{}]", task.id).bright_black(),
+            task.description.bold()
+        );
+        println!("{:10}{}", "Status:", task.status);
+        if let Some(uuid) = task.uuid {
+            println!("{:10}{}", "UUID:", uuid);
+        }
+        if !task.date.is_empty() {
+            println!("{:10}{}", "Updated:", task.date);
+        }
+        if let Some(completed) = task.completed {
+            println!("{:10}{}", "Done at:", completed.format(DATE_FORMAT));
+        }
+        if let Some(assignee) = &task.assignee {
+            println!("{:10}{}", "Assignee:", assignee);
+        }
+        if let Some(due) = task.due {
+            println!("{:10}{}", "Due:", due);
+        }
+        if let Some(wait) = task.wait {
+            println!("{:10}{}", "Waits:", wait);
+        }
+        if let Some(recurrence) = &task.recurrence {
+            println!("{:10}{}", "Repeats:", recurrence.describe());
+        }
+        if let Some(estimate) = task.estimate {
+            println!("{:10}{}", "Estimate:", estimate);
+        }
+        let now = now();
+        if !task.time_log.is_empty() || task.timer.is_some() {
+            println!("{:10}{}", "Tracked:", format_duration(task.tracked(now)));
+        }
+        if let Some(start) = task.timer {
+            println!(
+                "{:10}{} {}",
+                "Timer:",
+                "running since".green(),
+                start.format(TIME_FORMAT)
+            );
+        }
+
+        println!();
+        println!("{}", "Notes".cyan().bold());
+        if task.notes.is_empty() {
+            println!("  {}", "(none)".bright_black());
+        } else {
+            for line in task.notes.lines() {
+                println!("  {}", line);
             }
-            let parts: Vec<&str> = line.split("\t").collect();
-            if parts.len() >= 3
-                && let Ok(id) = parts[0].parse::<i32>()
-            {
-                let status = Status::from_str(parts[1]);
-                let today = String::from(match parts.get(3) {
-                    Some(x) => x,
-                    None => "",
-                });
-                let task = Task::new(id, status, parts[2].to_string(), today);
-                tasks.push(task);
+        }
+
+        if !task.links.is_empty() {
+            println!();
+            println!("{}", "Links".cyan().bold());
+            for (index, link) in task.links.iter().enumerate() {
+                println!(
+                    "  {} {}",
+                    format!("{}.", index + 1).bright_black(),
+                    self.resolve_link(link)
+                );
             }
         }
-        Ok(tasks)
+
+        println!();
+        println!("{}", "Comments".cyan().bold());
+        if task.comments.is_empty() {
+            println!("  {}", "(none)".bright_black());
+        }
+        for comment in &task.comments {
+            let mut lines = comment.text.lines();
+            println!(
+                "  {} {}",
+                comment.date.bright_black(),
+                lines.next().unwrap_or("")
+            );
+            for line in lines {
+                println!("  {:width$} {}", "", line, width = comment.date.len());
+            }
+        }
+        Ok(())
     }
 
-    pub fn list_tasks(&self, kanban: bool) -> Result<(), Error> {
+    /// Status changes, description edits and the deletion of a task, oldest
+    /// first, with who made them.
+    pub fn history_of(&self, task: &Task) -> Result<Vec<history::Record>, Error> {
+        history::read(&self.tasklist_path, &task.key())
+    }
+
+    pub fn show_history(&self, id: TaskId) -> Result<(), Error> {
+        let task = self.get_task(id)?;
+        let records = self.history_of(&task)?;
+        println!(
+            "{} {}",
+            format!("Task {}:", task.id).bold(),
+            task.description
+        );
+        if records.is_empty() {
+            println!("{}", "No recorded changes".bright_black());
+        }
+        for record in records {
+            println!(
+                "{}  {}  {}",
+                record.at.bright_black(),
+                record.actor.cyan(),
+                record.change
+            );
+        }
+        Ok(())
+    }
+
+    /// Prints every recorded change to a task, oldest first. Only boards
+    /// stored as an event log keep them.
+    pub fn task_log(&self, id: TaskId) -> Result<(), Error> {
+        let config = Config::load(&self.tasklist_path)?;
+        if storage::backend_of(&self.tasklist_path, &config) != Backend::Events {
+            return Err(Error::new(
+                std::io::ErrorKind::Unsupported,
+                "Only event log boards keep a change log (tsk migrate --to events)",
+            ));
+        }
+        let task = self.get_task(id)?;
+        let key = task.key();
+
+        println!(
+            "{} {}",
+            format!("Task {}:", task.id).bold(),
+            task.description
+        );
+        let mut previous: Option<Task> = None;
+        for event in EventStorage::new(&self.tasklist_path).events()? {
+            let (action, version) = match event.change {
+                Change::Add(task) => ("added".green(), task),
+                Change::Update(task) => ("updated".yellow(), task),
+                Change::Delete(task) => ("deleted".red(), task),
+                _ => continue,
+            };
+            if version.key() != key {
+                continue;
+            }
+            let changes = match &previous {
+                Some(previous) => describe_changes(previous, &version),
+                None => version.description.clone(),
+            };
+            println!("{}  {:<8} {}", event.at.bright_black(), action, changes);
+            previous = Some(version);
+        }
+        Ok(())
+    }
+
+    pub fn get_tasks(&self) -> Result<Vec<Task>, Error> {
+        Ok(self.storage()?.load()?.tasks().cloned().collect())
+    }
+
+    pub fn list_tasks(&self, kanban: bool, filter: &TaskFilter) -> Result<(), Error> {
         println!(
             "Project: {}",
             self.title.as_ref().unwrap_or(&String::from("My Tasks"))
         );
-        let tasks = self.get_tasks()?;
+        let today = today();
+        let (tasks, hidden) = self.filtered_tasks(filter)?;
 
-        if tasks.is_empty() {
+        if tasks.is_empty() && hidden.is_empty() {
             println!("{}", "No tasks found. Add a task to get started!".yellow());
             return Ok(());
         }
+        if !hidden.is_empty() {
+            let waiting = hidden.iter().filter(|task| task.is_waiting(today)).count();
+            if waiting > 0 {
+                println!(
+                    "{}",
+                    format!("{} scheduled task(s) hidden, use --all to show", waiting)
+                        .bright_black()
+                );
+            }
+        }
+        if tasks.is_empty() {
+            return Ok(());
+        }
 
         if kanban {
             self.display_kanban(&tasks);
@@ -204,6 +853,83 @@ impl Mngr {
         Ok(())
     }
 
+    /// The tasks `filter` lets through, and the ones it hid.
+    fn filtered_tasks(&self, filter: &TaskFilter) -> Result<(Vec<Task>, Vec<Task>), Error> {
+        self.filter_in(self.storage()?, filter)
+    }
+
+    fn filter_in(
+        &self,
+        storage: &dyn Storage,
+        filter: &TaskFilter,
+    ) -> Result<(Vec<Task>, Vec<Task>), Error> {
+        let filter = filter.clone().resolve(&self.board_dir())?;
+        let today = today();
+        Ok(storage
+            .query(&filter.query())?
+            .into_iter()
+            .partition(|task| filter.matches(task, today)))
+    }
+
+    /// Writes the tasks `filter` lets through in a format for people without
+    /// tsk, to `output` or else to stdout.
+    pub fn export_tasks(
+        &self,
+        format: ExportFormat,
+        filter: &TaskFilter,
+        output: Option<&str>,
+        grouped: bool,
+    ) -> Result<(), Error> {
+        let (tasks, _) = self.filtered_tasks(filter)?;
+        let title = self.title.as_deref().unwrap_or("My Tasks");
+        let rendered = export::render(format, &tasks, title, grouped, today())?;
+        let Some(output) = output else {
+            return std::io::stdout().write_all(rendered.as_bytes());
+        };
+        if !self.dry_run {
+            storage::atomic_write(output, |writer| writer.write_all(rendered.as_bytes()))?;
+        }
+        println!(
+            "{} {} task(s) to {}",
+            "Exported".green(),
+            tasks.len(),
+            output
+        );
+        Ok(())
+    }
+
+    /// Lists tasks that carry a recurrence rule, i.e. the template of each series.
+    pub fn list_recurring(&self) -> Result<(), Error> {
+        let tasks: Vec<Task> = self
+            .get_tasks()?
+            .into_iter()
+            .filter(|task| task.recurrence.is_some())
+            .collect();
+        if tasks.is_empty() {
+            println!("{}", "No recurring tasks.".yellow());
+            return Ok(());
+        }
+
+        let mut builder = tabled::builder::Builder::default();
+        builder.push_record(["id", "description", "repeats", "next due", "status"]);
+        for task in &tasks {
+            builder.push_record([
+                task.id.to_string(),
+                task.description.clone(),
+                task.recurrence
+                    .as_ref()
+                    .map(|recurrence| recurrence.describe())
+                    .unwrap_or_default(),
+                task.due.map(|due| due.to_string()).unwrap_or_default(),
+                task.status.to_string(),
+            ]);
+        }
+        let mut table = builder.build();
+        table.with(Style::modern());
+        println!("{table}");
+        Ok(())
+    }
+
     fn display_kanban(&self, tasks: &[Task]) {
         use std::collections::HashMap;
 
@@ -218,10 +944,24 @@ impl Mngr {
 
         let column_width = ((terminal_width - 6) / 3).clamp(25, 50);
 
+        let header = |status: Status, title: &str| {
+            let total = EstimateTotal::of(grouped.get(&status).into_iter().flatten().copied());
+            if total.is_empty() {
+                title.to_string()
+            } else {
+                format!("{} ({})", title, total)
+            }
+        };
         let columns = vec![
-            (Status::NotStarted, "🚀 NOT STARTED".cyan().bold()),
-            (Status::InProgress, "⏳ IN PROGRESS".yellow().bold()),
-            (Status::Done, "✅ DONE".green().bold()),
+            (
+                Status::NotStarted,
+                header(Status::NotStarted, "🚀 NOT STARTED").cyan().bold(),
+            ),
+            (
+                Status::InProgress,
+                header(Status::InProgress, "⏳ IN PROGRESS").yellow().bold(),
+            ),
+            (Status::Done, header(Status::Done, "✅ DONE").green().bold()),
         ];
 
         // Print column headers
@@ -246,7 +986,10 @@ impl Mngr {
                 if let Some(task_list) = grouped.get(status) {
                     if let Some(task) = task_list.get(i) {
                         // Include date in the display (first line: ID + desc, second line: date)
-                        let id_prefix = format!("[{}] ", task.id);
+                        let id_prefix = match &task.assignee {
+                            Some(assignee) => format!("[{}] ({}) ", task.id, initials(assignee)),
+                            None => format!("[{}] ", task.id),
+                        };
                         let desc_max_len = column_width.saturating_sub(id_prefix.len() + 3);
 
                         let truncated = if task.description.len() > desc_max_len {
@@ -269,8 +1012,9 @@ impl Mngr {
             for (status, _) in &columns {
                 if let Some(task_list) = grouped.get(status) {
                     if let Some(task) = task_list.get(i) {
-                        let date_display = if !task.date.is_empty() {
-                            format!("  {}", task.date.bright_black())
+                        let meta = task.card_meta(today());
+                        let date_display = if !meta.is_empty() {
+                            format!("  {}", meta.bright_black())
                         } else {
                             String::new()
                         };
@@ -287,153 +1031,591 @@ impl Mngr {
         println!();
     }
 
-    pub fn delete_task(&self, id: i32) -> Result<(), Error> {
-        let (max_id, has_metadata) = self.read_metadata()?;
+    /// Reports problems in the task list file and, with `fix`, repairs what it
+    /// can after backing the file up.
+    pub fn doctor(&self, fix: bool) -> Result<(), Error> {
+        let config = Config::load(&self.tasklist_path)?;
+        let backend = storage::backend_of(&self.tasklist_path, &config);
+        if backend != Backend::Tsv {
+            return Err(Error::new(
+                std::io::ErrorKind::Unsupported,
+                format!(
+                    "doctor only checks tsv boards, this one is stored as {}",
+                    backend
+                ),
+            ));
+        }
+        let bytes = std::fs::read(&self.tasklist_path).map_err(|e| {
+            Error::new(
+                e.kind(),
+                format!("Could not read task list {}: {}", self.tasklist_path, e),
+            )
+        })?;
+        let bytes = crypto::open(&self.tasklist_path, bytes)?;
+        let diagnosis = doctor::diagnose(&bytes);
+        if diagnosis.issues.is_empty() {
+            println!("{} {}", "No problems found in".green(), self.tasklist_path);
+            return Ok(());
+        }
 
-        let tasklist = OpenOptions::new()
-            .read(true)
-            .open(&self.tasklist_path)
-            .map_err(|e| {
+        let fixable = diagnosis
+            .issues
+            .iter()
+            .filter(|issue| issue.fixable)
+            .count();
+        if !fix {
+            for issue in &diagnosis.issues {
+                let hint = if issue.fixable { " (fixable)" } else { "" };
+                println!("{}{}", issue.to_string().yellow(), hint.bright_black());
+            }
+            if fixable > 0 {
+                println!("Run `tsk doctor --fix` to repair {} of them", fixable);
+            }
+            return Err(Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("{} problem(s) found", diagnosis.issues.len()),
+            ));
+        }
+
+        if fixable > 0 && !self.dry_run {
+            // Always, even with rotating backups turned off
+            if let Some(backup) = backup::create(&self.tasklist_path, now())? {
+                println!("{} {}", "Backed up to".bright_black(), backup.display());
+            }
+            let unbacked = Config {
+                backups: 0,
+                ..config.clone()
+            };
+            storage::persist(&self.tasklist_path, &unbacked, |writer| {
+                for line in &diagnosis.repaired {
+                    writeln!(writer, "{}", line)?;
+                }
+                Ok(())
+            })?;
+            self.refresh_ical_feed(&config);
+        }
+        for issue in &diagnosis.issues {
+            if issue.fixable {
+                println!("{} {}", "Fixed:".green(), issue);
+            } else {
+                println!("{} {}", "Not fixed:".red(), issue);
+            }
+        }
+        match diagnosis.issues.len() - fixable {
+            0 => Ok(()),
+            left => Err(Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("{} problem(s) need fixing by hand", left),
+            )),
+        }
+    }
+
+    /// Lists the backups of the task list, newest first.
+    pub fn list_backups(&self) -> Result<(), Error> {
+        let backups = backup::list(&self.tasklist_path)?;
+        if backups.is_empty() {
+            println!("No backups yet");
+            return Ok(());
+        }
+        for (index, backup) in backups.iter().enumerate() {
+            let content = std::fs::read(&backup.path).unwrap_or_default();
+            let summary = if crypto::is_encrypted(&content) {
+                "encrypted".to_string()
+            } else {
+                let tasks = String::from_utf8_lossy(&content)
+                    .lines()
+                    .filter(|line| Task::from_file_line(line).is_some())
+                    .count();
+                format!("{} task(s)", tasks)
+            };
+            println!(
+                "{:>3}  {}  {}",
+                index + 1,
+                backup.taken.format(TIME_FORMAT),
+                summary.bright_black()
+            );
+        }
+        Ok(())
+    }
+
+    /// Puts backup `number` (1 = newest) back in place. The current file is
+    /// backed up first, so a restore can itself be undone.
+    pub fn restore_backup(&self, number: usize) -> Result<(), Error> {
+        let backups = backup::list(&self.tasklist_path)?;
+        let chosen = number
+            .checked_sub(1)
+            .and_then(|index| backups.get(index))
+            .ok_or_else(|| {
                 Error::new(
-                    e.kind(),
-                    format!("Could not open task list {}: {}", self.tasklist_path, e),
+                    std::io::ErrorKind::NotFound,
+                    format!("Backup {} not found ({} available)", number, backups.len()),
                 )
             })?;
-        let reader = BufReader::new(&tasklist);
-        let lines: Vec<String> = reader
-            .lines()
-            .collect::<Result<Vec<String>, _>>()
-            .map_err(|e| Error::new(e.kind(), format!("Failed to read lines: {}", e)))?;
+        let content = std::fs::read(&chosen.path).map_err(|e| {
+            Error::new(
+                e.kind(),
+                format!("Could not read backup {}: {}", chosen.path.display(), e),
+            )
+        })?;
 
-        let mut task_found = false;
-        let mut filtered_lines = Vec::new();
+        if !self.dry_run {
+            // Copied byte for byte: an encrypted backup stays encrypted
+            let config = Config::load(&self.tasklist_path)?;
+            storage::take_backup(&self.tasklist_path, &config)?;
+            storage::atomic_write(&self.tasklist_path, |writer| writer.write_all(&content))?;
+            self.refresh_ical_feed(&config);
+        }
+        println!(
+            "{} {}",
+            "Restored backup from".green(),
+            chosen.taken.format(TIME_FORMAT)
+        );
+        Ok(())
+    }
 
-        for line in lines {
-            if line.starts_with("#") {
-                continue;
-            }
+    /// Converts the board in place to another storage backend and switches
+    /// its config over. The old file is backed up first.
+    pub fn migrate(&self, to: Backend) -> Result<(), Error> {
+        let mut config = Config::load(&self.tasklist_path)?;
+        let from = storage::backend_of(&self.tasklist_path, &config);
+        if from == to {
+            return Err(Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("Board is already stored as {}", to),
+            ));
+        }
+        let board = self.storage()?.load()?;
+        let count = board.tasks().count();
+        if self.dry_run {
+            println!("Would migrate {} task(s) from {} to {}", count, from, to);
+            return Ok(());
+        }
 
-            let parts: Vec<&str> = line.split("\t").collect();
-            if let Some(first_part) = parts.first()
-                && let Ok(task_id) = first_part.parse::<i32>()
-                && task_id == id
-            {
-                task_found = true;
-                continue; // Skip this line (delete it)
-            }
-            filtered_lines.push(line);
+        if let Some(backup) = backup::create(&self.tasklist_path, now())? {
+            println!("{} {}", "Backed up to".bright_black(), backup.display());
         }
+        // Build the new file next to the old one, then swap it in
+        let parent = Path::new(&self.tasklist_path)
+            .parent()
+            .unwrap_or_else(|| Path::new("."));
+        let temp = tempfile::Builder::new()
+            .prefix(".tasklist.tmp")
+            .tempfile_in(parent)
+            .map_err(|e| Error::new(e.kind(), format!("Failed to create temporary file: {}", e)))?
+            .into_temp_path();
+        let unbacked = Config {
+            backups: 0,
+            ..config.clone()
+        };
+        storage::open_as(to, &temp.to_string_lossy(), &unbacked, false).save(&board)?;
+        temp.persist(&self.tasklist_path)
+            .map_err(|e| Error::other(format!("Failed to replace task list: {}", e)))?;
+
+        // Only pin the backend when the file name doesn't already imply it
+        config.backend = (Backend::from_extension(&self.tasklist_path) != to).then_some(to);
+        config.save(&self.tasklist_path)?;
+        self.refresh_ical_feed(&config);
+        println!(
+            "{} {} task(s) from {} to {}",
+            "Migrated".green(),
+            count,
+            from,
+            to
+        );
+        Ok(())
+    }
 
-        if !task_found {
+    /// Writes a copy of the board to `output` in another format, picked by
+    /// `to` or else by the output's extension. The board itself is untouched.
+    pub fn convert(&self, output: &str, to: Option<Backend>, force: bool) -> Result<(), Error> {
+        let to = to.unwrap_or_else(|| Backend::from_extension(output));
+        if Path::new(output).exists() && !force {
             return Err(Error::new(
-                std::io::ErrorKind::NotFound,
-                format!("Task with ID {} not found", id),
+                std::io::ErrorKind::AlreadyExists,
+                format!("{} already exists (use --force to overwrite it)", output),
             ));
         }
+        let board = self.storage()?.load()?;
+        let count = board.tasks().count();
+        if !self.dry_run {
+            let config = Config {
+                backups: 0,
+                ..Config::default()
+            };
+            storage::open_as(to, output, &config, false).save(&board)?;
+        }
+        println!(
+            "{} {} task(s) to {} ({})",
+            "Converted".green(),
+            count,
+            output,
+            to
+        );
+        Ok(())
+    }
 
-        let current_max_id = if has_metadata {
-            max_id
-        } else {
-            self.scan_max_id()?
-        };
+    /// Adds the tasks in another tool's file to the board in one write, under
+    /// fresh IDs. Tasks whose UUID is already on the board are skipped, so a
+    /// file can be imported again after adding to it.
+    pub fn import_tasks(&self, format: ImportFormat, file: &str) -> Result<(), Error> {
+        let content = std::fs::read_to_string(file)
+            .map_err(|e| Error::new(e.kind(), format!("Could not read {}: {}", file, e)))?;
+        let mut imported = import::parse(format, &content)
+            .map_err(|e| Error::new(e.kind(), format!("Could not import {}: {}", file, e)))?;
 
-        self.atomic_write(|writer| {
-            self.write_metadata(writer, current_max_id)?;
+        let added = self.update_board(|board| {
+            let known: BTreeSet<String> = board.tasks().map(Task::key).collect();
+            let mut added = Vec::new();
+            for task in imported.tasks.drain(..) {
+                if known.contains(&task.key()) {
+                    imported.unmapped.push(format!(
+                        "{}: already on the board, skipped",
+                        task.description
+                    ));
+                    continue;
+                }
+                let id = board.push(task)?;
+                added.push(board.task_mut(id)?.clone());
+            }
+            Ok(added)
+        })?;
 
-            for line in &filtered_lines {
-                writeln!(writer, "{}", line)?;
+        for task in &added {
+            println!("{} {}", "Imported:".green(), format!("{task}").yellow());
+        }
+        println!(
+            "{} {} task(s) from {}",
+            "Imported".green(),
+            added.len(),
+            file
+        );
+        if !imported.unmapped.is_empty() {
+            println!("{}", "Not mapped:".yellow());
+            for item in &imported.unmapped {
+                println!("  {}", item);
             }
+        }
+        Ok(())
+    }
 
-            Ok(())
-        })?;
+    /// Syncs the checklist items of a Markdown file with the board, both ways.
+    pub fn sync_markdown(&self, file: &str) -> Result<(), Error> {
+        let content = std::fs::read_to_string(file)
+            .map_err(|e| Error::new(e.kind(), format!("Could not read {}: {}", file, e)))?;
+        let key = self.board_relative(file)?.to_string_lossy().to_string();
+        let lines: Vec<String> = content.lines().map(str::to_string).collect();
+        let state = sync::load_state(&self.tasklist_path, &key)?;
 
-        println!("{}", format!("Deleted task with ID {}", id).yellow());
+        let outcome = self.update_board(|board| sync::sync(board, &lines, &state))?;
+        let mut synced = outcome.lines.join("\n");
+        if content.ends_with('\n') {
+            synced.push('\n');
+        }
+        if !self.dry_run {
+            if synced != content {
+                storage::atomic_write(file, |writer| writer.write_all(synced.as_bytes()))?;
+            }
+            let encrypted = Config::load(&self.tasklist_path)?.encrypted;
+            sync::save_state(&self.tasklist_path, encrypted, &key, &outcome.state)?;
+        }
+
+        for change in &outcome.report {
+            println!("{}", change);
+        }
+        println!(
+            "{} {} ({} item(s), {} change(s))",
+            "Synced".green(),
+            file,
+            outcome.state.len(),
+            outcome.report.len()
+        );
         Ok(())
     }
 
-    fn read_metadata(&self) -> Result<(i32, bool), Error> {
-        let file = OpenOptions::new().read(true).open(&self.tasklist_path);
+    /// Turns the TODO, FIXME and HACK comments under `paths` into tasks that
+    /// link to their `file:line`. Running it again only updates those links;
+    /// tasks whose comment is gone are marked done.
+    pub fn scan(&self, paths: Vec<String>) -> Result<(), Error> {
+        let paths = if paths.is_empty() {
+            vec![".".to_string()]
+        } else {
+            paths
+        };
+        let scopes = paths
+            .iter()
+            .map(|path| self.board_relative(path))
+            .collect::<Result<Vec<_>, _>>()?;
+        // The board and its sidecar directory quote task descriptions
+        let own_files = self.board_relative(&self.tasklist_path)?;
+        let mut comments = Vec::new();
+        for found in scan::find(&paths)? {
+            let file = self.board_relative(&found.path.to_string_lossy())?;
+            if file
+                .to_string_lossy()
+                .starts_with(&*own_files.to_string_lossy())
+            {
+                continue;
+            }
+            comments.push((
+                file.to_string_lossy().to_string(),
+                found.line,
+                found.description,
+            ));
+        }
 
-        match file {
-            Ok(f) => {
-                let reader = BufReader::new(f);
-                let mut lines = reader.lines();
+        let outcome = self.update_board(|board| scan::apply(board, &comments, &scopes))?;
+        let location = |task: &Task| {
+            task.links
+                .iter()
+                .find_map(|link| match link {
+                    Link::File(target) => Some(target.clone()),
+                    Link::Url(_) => None,
+                })
+                .unwrap_or_default()
+        };
+        for task in &outcome.added {
+            println!(
+                "{} {} ({})",
+                "Added:".green(),
+                format!("{task}").yellow(),
+                location(task)
+            );
+        }
+        for task in &outcome.moved {
+            println!("{} {} -> {}", "Moved:".green(), task.id, location(task));
+        }
+        for task in &outcome.renamed {
+            println!("{} {}", "Renamed:".green(), format!("{task}").yellow());
+        }
+        for task in &outcome.reopened {
+            println!(
+                "{} {} (comment is back)",
+                "Reopened:".green(),
+                format!("{task}").yellow()
+            );
+        }
+        for task in &outcome.closed {
+            println!(
+                "{} {} (comment removed)",
+                "Done:".green(),
+                format!("{task}").yellow()
+            );
+        }
+        println!(
+            "Found {} comment(s): {} added, {} moved, {} renamed, {} reopened, {} done",
+            comments.len(),
+            outcome.added.len(),
+            outcome.moved.len(),
+            outcome.renamed.len(),
+            outcome.reopened.len(),
+            outcome.closed.len()
+        );
+        Ok(())
+    }
 
-                if let Some(Ok(first_line)) = lines.next()
-                    && first_line.starts_with("#max_id=")
-                    && let Some(id_str) = first_line.strip_prefix("#max_id=")
-                    && let Ok(max_id) = id_str.parse::<i32>()
-                {
-                    return Ok((max_id, true));
-                }
-                Ok((0, false))
-            },
-            Err(_) => Ok((0, false)),
+    /// Encrypts the board, its backups and its history with a passphrase, or
+    /// decrypts them again.
+    pub fn set_encryption(&self, encrypt: bool) -> Result<(), Error> {
+        let mut config = Config::load(&self.tasklist_path)?;
+        let backend = storage::backend_of(&self.tasklist_path, &config);
+        if matches!(backend, Backend::Sqlite | Backend::Events) {
+            return Err(Error::new(
+                std::io::ErrorKind::Unsupported,
+                format!("{} boards can't be encrypted", backend),
+            ));
+        }
+        if config.encrypted == encrypt {
+            return Err(Error::new(
+                std::io::ErrorKind::InvalidInput,
+                if encrypt {
+                    "The board is already encrypted"
+                } else {
+                    "The board is not encrypted"
+                },
+            ));
+        }
+        if encrypt {
+            // Ask for a new passphrase twice before anything else
+            crypto::passphrase(true)?;
+        }
+        let board = self.storage()?.load()?;
+        let verb = if encrypt { "Encrypted" } else { "Decrypted" };
+        if self.dry_run {
+            println!("Would have {} {}", verb.to_lowercase(), self.tasklist_path);
+            return Ok(());
+        }
+
+        // No backup of the old file: it would be a plaintext copy
+        config.encrypted = encrypt;
+        let unbacked = Config {
+            backups: 0,
+            ..config.clone()
+        };
+        storage::open_as(backend, &self.tasklist_path, &unbacked, false).save(&board)?;
+        let mut others: Vec<PathBuf> = backup::list(&self.tasklist_path)?
+            .into_iter()
+            .map(|backup| backup.path)
+            .collect();
+        for sidecar in [
+            history::history_path(&self.tasklist_path),
+            sync::state_path(&self.tasklist_path),
+        ] {
+            if sidecar.exists() {
+                others.push(sidecar);
+            }
         }
+        for path in &others {
+            let name = path.display().to_string();
+            let plaintext = crypto::open(&name, std::fs::read(path)?)?;
+            let content = if encrypt {
+                crypto::seal(&plaintext)?
+            } else {
+                plaintext
+            };
+            storage::atomic_write(&name, |writer| writer.write_all(&content))?;
+        }
+        config.save(&self.tasklist_path)?;
+        self.refresh_ical_feed(&config);
+
+        println!(
+            "{} {} ({} backup, history and sync state file(s))",
+            verb.green(),
+            self.tasklist_path,
+            others.len()
+        );
+        Ok(())
     }
 
-    fn write_metadata<W: Write>(&self, writer: &mut W, max_id: i32) -> Result<(), Error> {
-        writeln!(writer, "#max_id={}", max_id)
+    /// Accepts hand edits to a TSV board by recomputing its header checksum.
+    pub fn rehash(&self) -> Result<(), Error> {
+        let config = Config::load(&self.tasklist_path)?;
+        let backend = storage::backend_of(&self.tasklist_path, &config);
+        if backend != Backend::Tsv {
+            return Err(Error::new(
+                std::io::ErrorKind::Unsupported,
+                format!("{} boards have no checksum", backend),
+            ));
+        }
+        if self.dry_run {
+            println!("Would have rehashed {}", self.tasklist_path);
+            return Ok(());
+        }
+        let strict = self.strict || config.strict;
+        let tasks = TsvStorage::new(&self.tasklist_path, strict, config.clone()).rehash()?;
+        self.refresh_ical_feed(&config);
+        println!(
+            "{} {} ({} task(s))",
+            "Rehashed".green(),
+            self.tasklist_path,
+            tasks
+        );
+        Ok(())
     }
 
-    fn scan_max_id(&self) -> Result<i32, Error> {
-        let file = OpenOptions::new().read(true).open(&self.tasklist_path);
+    /// Compacts task IDs to 1..n in file order.
+    pub fn renumber_tasks(&self) -> Result<(), Error> {
+        let changed = self.update_board(|board| Ok(board.renumber()))?;
 
-        match file {
-            Ok(f) => {
-                let reader = BufReader::new(f);
-                let mut max_id = 0;
-                for line in reader.lines() {
-                    let line = line
-                        .map_err(|e| Error::new(e.kind(), format!("Failed to read line: {}", e)))?;
-                    if line.starts_with("#") {
-                        continue; // Skip metadata
-                    }
-                    if let Some(id_str) = line.split("\t").next()
-                        && let Ok(id) = id_str.parse::<i32>()
-                    {
-                        max_id = max_id.max(id);
-                    }
-                }
-                Ok(max_id)
-            },
-            Err(_) => Ok(0),
+        if changed.is_empty() {
+            println!("{}", "Task IDs are already compact".green());
+        }
+        for (old, new) in changed {
+            println!("{} {} -> {}", "Renumbered:".green(), old, new);
         }
+        Ok(())
     }
 
-    fn atomic_write<F>(&self, write_fn: F) -> Result<(), Error>
-    where
-        F: FnOnce(&mut BufWriter<&File>) -> Result<(), Error>,
-    {
-        let path = Path::new(&self.tasklist_path);
-        let parent = path.parent().unwrap_or_else(|| Path::new("."));
+    pub fn delete_task(&self, id: TaskId) -> Result<(), Error> {
+        let task = self.storage()?.delete_task(id)?;
+        self.record_changes(history::changes(&Board::of(vec![task]), &Board::default()))?;
 
-        // Create a temporary file in the same directory
-        let temp_file = tempfile::Builder::new()
-            .prefix(".tasklist.tmp")
-            .tempfile_in(parent)
-            .map_err(|e| Error::new(e.kind(), format!("Failed to create temporary file: {}", e)))?;
+        println!("{}", format!("Deleted task with ID {}", id).yellow());
+        Ok(())
+    }
+}
 
-        // Get the file handle and lock it exclusively
-        let file = temp_file.as_file();
-        file.lock_exclusive()
-            .map_err(|e| Error::other(format!("Failed to lock temporary file: {}", e)))?;
+/// Lists the fields that differ between two versions of a task, e.g.
+/// `status: 🚀 Not Started -> ⏳ In Progress`.
+fn describe_changes(before: &Task, after: &Task) -> String {
+    let (before, after) = (before.fields(), after.fields());
+    let value = |fields: &BTreeMap<String, Vec<String>>, name: &str| {
+        let values: Vec<&str> = fields
+            .get(name)
+            .into_iter()
+            .flatten()
+            .map(|value| value.strip_prefix(&format!("{}=", name)).unwrap_or(value))
+            .collect();
+        if values.is_empty() {
+            "-".to_string()
+        } else {
+            values.join(", ")
+        }
+    };
+    let names: BTreeSet<&String> = before.keys().chain(after.keys()).collect();
+    let changes: Vec<String> = names
+        .into_iter()
+        .filter(|name| *name != "date" && before.get(*name) != after.get(*name))
+        .map(|name| {
+            format!(
+                "{}: {} -> {}",
+                name,
+                value(&before, name),
+                value(&after, name)
+            )
+        })
+        .collect();
+    if changes.is_empty() {
+        "touched".to_string()
+    } else {
+        changes.join("; ")
+    }
+}
 
-        // Write to the temporary file
-        {
-            let mut writer = BufWriter::new(file);
-            write_fn(&mut writer)?;
-            writer.flush()?;
-        } // Writer dropped here, releasing the file reference
+/// Closes every running timer, returning the affected tasks and their new intervals.
+fn stop_running_timers(board: &mut Board, now: NaiveDateTime) -> Vec<(Task, Interval)> {
+    board
+        .tasks_mut()
+        .filter_map(|task| {
+            let interval = task.stop_timer(now)?;
+            Some((task.clone(), interval))
+        })
+        .collect()
+}
 
-        // Unlock the file
-        temp_file.as_file().unlock().ok();
+/// Opens `initial` in the user's editor and returns the saved text.
+fn edit_in_editor(initial: &str) -> Result<String, Error> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
 
-        // Atomically replace the original file
-        temp_file
-            .persist(&self.tasklist_path)
-            .map_err(|e| Error::other(format!("Failed to persist temporary file: {}", e)))?;
+    let mut file = tempfile::Builder::new()
+        .prefix("tsk-notes")
+        .suffix(".md")
+        .tempfile()
+        .map_err(|e| Error::new(e.kind(), format!("Failed to create temporary file: {}", e)))?;
+    file.write_all(initial.as_bytes())?;
+    file.flush()?;
 
-        Ok(())
+    // Run through the shell so editors configured with arguments (`code -w`) work
+    let status = std::process::Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} \"$1\"", editor))
+        .arg("sh")
+        .arg(file.path())
+        .status()
+        .map_err(|e| {
+            Error::new(
+                e.kind(),
+                format!("Failed to launch editor {}: {}", editor, e),
+            )
+        })?;
+    if !status.success() {
+        return Err(Error::other(format!(
+            "Editor {} exited with {}",
+            editor, status
+        )));
     }
+
+    let text = std::fs::read_to_string(file.path())?;
+    Ok(text.trim_end_matches('\n').to_string())
 }
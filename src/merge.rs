@@ -0,0 +1,280 @@
+use crate::board::{Board, Entry};
+use crate::config::Config;
+use crate::crypto;
+use crate::storage::{self, Backend};
+use crate::task::{SEP, Task, TaskId};
+use colored::Colorize;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::io::Error;
+
+/// Fields that hold one value; conflicting edits to them can't be combined.
+/// Every other field (comments, time, links, unknown ones) is a list, and
+/// additions from both sides are kept.
+const SINGLE_FIELDS: &[&str] = &[
+    "id",
+    "status",
+    "description",
+    "date",
+    "uuid",
+    "estimate",
+    "due",
+    "wait",
+    "assignee",
+    "recur",
+    "notes",
+    "timer",
+    "completed",
+];
+
+/// The result of a three-way board merge.
+#[derive(Debug)]
+pub struct MergeOutcome {
+    pub board: Board,
+    /// Changes from both sides that could not be combined. Ours were kept.
+    pub conflicts: Vec<String>,
+    /// `(old, new)` short IDs of tasks moved out of the way of another task.
+    pub renumbered: Vec<(TaskId, TaskId)>,
+}
+
+/// Entry point of `tsk merge-driver %O %A %B`: merges the three versions and
+/// writes the result over `ours`, in the format `ours` was in. Encrypted
+/// versions are decrypted with the board passphrase, and the result is
+/// encrypted again. Fails when a conflict had to be settled by keeping our
+/// side, so git flags the file.
+pub fn run_driver(base: &str, ours: &str, theirs: &str) -> Result<(), Error> {
+    let read = |path: &str| -> Result<(Board, bool, bool), Error> {
+        let bytes = fs::read(path)
+            .map_err(|e| Error::new(e.kind(), format!("Could not read {}: {}", path, e)))?;
+        let encrypted = crypto::is_encrypted(&bytes);
+        let content = crypto::open(path, bytes)?;
+        let content = String::from_utf8_lossy(&content);
+        let lines: Vec<String> = content.lines().map(str::to_string).collect();
+        // A leading counter means tsv; the merge backend has none
+        let has_counter = lines
+            .first()
+            .is_some_and(|line| line.starts_with("#max_id="));
+        let board = if has_counter {
+            Board::parse(path, &lines, false)?
+        } else {
+            Board::parse_sorted(path, &lines, false)?
+        };
+        Ok((board, has_counter, encrypted))
+    };
+    let (base, _, base_encrypted) = read(base)?;
+    let (ours_board, ours_counter, ours_encrypted) = read(ours)?;
+    let (theirs_board, theirs_counter, theirs_encrypted) = read(theirs)?;
+
+    let outcome = merge(&base, &ours_board, &theirs_board);
+    let empty = ours_board.entries.is_empty();
+    let backend = if ours_counter || (empty && theirs_counter) {
+        Backend::Tsv
+    } else {
+        Backend::Merge
+    };
+    let config = Config {
+        backups: 0,
+        // Never write out in the clear what one side had encrypted
+        encrypted: base_encrypted || ours_encrypted || theirs_encrypted,
+        ..Config::default()
+    };
+    storage::open_as(backend, ours, &config, false).save(&outcome.board)?;
+
+    for (old, new) in &outcome.renumbered {
+        eprintln!("{} {} -> {}", "Renumbered:".yellow(), old, new);
+    }
+    for conflict in &outcome.conflicts {
+        eprintln!("{} {}", "Conflict:".red(), conflict);
+    }
+    match outcome.conflicts.len() {
+        0 => Ok(()),
+        n => Err(Error::other(format!("{} conflict(s) in {}", n, ours))),
+    }
+}
+
+/// Merges two boards that both descend from `base`, task by task. Tasks are
+/// matched by UUID, so a task keeps its changes even if its short ID differs.
+pub fn merge(base: &Board, ours: &Board, theirs: &Board) -> MergeOutcome {
+    let base_tasks = by_key(base);
+    let theirs_tasks = by_key(theirs);
+    let ours_keys: HashSet<String> = ours.tasks().map(Task::key).collect();
+    let mut conflicts = Vec::new();
+
+    let mut entries = Vec::new();
+    for entry in &ours.entries {
+        let Entry::Task(task) = entry else {
+            entries.push(entry.clone());
+            continue;
+        };
+        let key = task.key();
+        let merged = merge_task(
+            base_tasks.get(&key).copied(),
+            Some(task),
+            theirs_tasks.get(&key).copied(),
+            &mut conflicts,
+        );
+        entries.extend(merged.map(Entry::Task));
+    }
+    for entry in &theirs.entries {
+        match entry {
+            Entry::Raw(line) => {
+                if !ours
+                    .entries
+                    .iter()
+                    .any(|ours| matches!(ours, Entry::Raw(l) if l == line))
+                {
+                    entries.push(entry.clone());
+                }
+            },
+            Entry::Task(task) => {
+                let key = task.key();
+                if ours_keys.contains(&key) {
+                    continue;
+                }
+                let merged = merge_task(
+                    base_tasks.get(&key).copied(),
+                    None,
+                    Some(task),
+                    &mut conflicts,
+                );
+                entries.extend(merged.map(Entry::Task));
+            },
+        }
+    }
+
+    let mut board = Board {
+        max_id: base.max_id.max(ours.max_id).max(theirs.max_id),
+        entries,
+    };
+    board.max_id = board
+        .tasks()
+        .map(|task| task.id)
+        .fold(board.max_id, TaskId::max);
+
+    // Ours come first, so tasks added on their side move out of the way
+    let mut used = HashSet::new();
+    let mut renumbered = Vec::new();
+    let mut max_id = board.max_id;
+    for task in board.tasks_mut() {
+        if !used.insert(task.id) {
+            max_id += 1;
+            renumbered.push((task.id, max_id));
+            task.id = max_id;
+            used.insert(max_id);
+        }
+    }
+    board.max_id = max_id;
+
+    MergeOutcome {
+        board,
+        conflicts,
+        renumbered,
+    }
+}
+
+fn by_key(board: &Board) -> HashMap<String, &Task> {
+    board.tasks().map(|task| (task.key(), task)).collect()
+}
+
+fn merge_task(
+    base: Option<&Task>,
+    ours: Option<&Task>,
+    theirs: Option<&Task>,
+    conflicts: &mut Vec<String>,
+) -> Option<Task> {
+    let line = |task: Option<&Task>| task.map(Task::to_file_string);
+    let (base_line, ours_line, theirs_line) = (line(base), line(ours), line(theirs));
+    if ours_line == theirs_line || theirs_line == base_line {
+        return ours.cloned();
+    }
+    if ours_line == base_line {
+        return theirs.cloned();
+    }
+
+    let (ours, theirs) = match (ours, theirs) {
+        (Some(ours), Some(theirs)) => (ours, theirs),
+        (Some(task), None) | (None, Some(task)) => {
+            conflicts.push(format!(
+                "task {} ({}): deleted on one side and changed on the other, kept it",
+                task.id, task.description
+            ));
+            return Some(task.clone());
+        },
+        (None, None) => return None,
+    };
+
+    let base_fields = base.map(Task::fields).unwrap_or_default();
+    let ours_fields = ours.fields();
+    let theirs_fields = theirs.fields();
+    let keys: Vec<&String> = ours_fields
+        .keys()
+        .chain(theirs_fields.keys())
+        .chain(base_fields.keys())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+
+    let none = Vec::new();
+    let mut merged = BTreeMap::new();
+    for key in keys {
+        let base = base_fields.get(key).unwrap_or(&none);
+        let a = ours_fields.get(key).unwrap_or(&none);
+        let b = theirs_fields.get(key).unwrap_or(&none);
+        let value = if a == b || b == base {
+            a.clone()
+        } else if a == base {
+            b.clone()
+        } else if key == "date" {
+            // The last-update stamp: the later edit wins
+            a.max(b).clone()
+        } else if SINGLE_FIELDS.contains(&key.as_str()) {
+            conflicts.push(format!(
+                "task {} ({}): {} changed on both sides ({} vs. {}), kept ours",
+                ours.id,
+                ours.description,
+                key,
+                a.join(", "),
+                b.join(", ")
+            ));
+            a.clone()
+        } else {
+            // Keep what either side added, drop what either side removed
+            let removed =
+                |item: &String, other: &Vec<String>| base.contains(item) && !other.contains(item);
+            a.iter()
+                .filter(|item| !removed(item, b))
+                .chain(
+                    b.iter()
+                        .filter(|item| !a.contains(item) && !base.contains(item)),
+                )
+                .cloned()
+                .collect()
+        };
+        merged.insert(key.clone(), value);
+    }
+
+    let column = |name: &str| {
+        merged
+            .get(name)
+            .and_then(|v| v.first())
+            .cloned()
+            .unwrap_or_default()
+    };
+    let mut line = [
+        column("id"),
+        column("status"),
+        column("description"),
+        column("date"),
+    ]
+    .join(SEP);
+    for (key, values) in &merged {
+        if ["id", "status", "description", "date"].contains(&key.as_str()) {
+            continue;
+        }
+        for value in values {
+            line.push_str(SEP);
+            line.push_str(value);
+        }
+    }
+    Task::from_file_line(&line).or_else(|| Some(ours.clone()))
+}
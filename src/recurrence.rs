@@ -0,0 +1,277 @@
+use chrono::{Datelike, Days, Months, NaiveDate, Weekday};
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+/// How a task repeats. Stored in the task list as an RRULE subset
+/// (`FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,TH`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recurrence {
+    pub frequency: Frequency,
+    pub interval: u32,
+    /// Only used by weekly rules; empty means "same weekday as before".
+    pub weekdays: Vec<Weekday>,
+    /// Only used by monthly rules: the day of the month to fall on, or the
+    /// last day of shorter months. `None` means "same day as before".
+    pub month_day: Option<u32>,
+}
+
+impl Recurrence {
+    fn new(frequency: Frequency, interval: u32) -> Self {
+        Recurrence {
+            frequency,
+            interval,
+            weekdays: Vec::new(),
+            month_day: None,
+        }
+    }
+
+    /// First occurrence strictly after `date`; `None` past the last date chrono
+    /// can represent.
+    pub fn next_after(&self, date: NaiveDate) -> Option<NaiveDate> {
+        let interval = self.interval.max(1);
+        match self.frequency {
+            Frequency::Daily => date.checked_add_days(Days::new(interval.into())),
+            Frequency::Monthly => {
+                let day = self.month_day.unwrap_or(date.day());
+                date.with_day(1)
+                    .and_then(|first| first.checked_add_months(Months::new(interval)))
+                    .and_then(|first| (1..=day).rev().find_map(|day| first.with_day(day)))
+            },
+            Frequency::Weekly if self.weekdays.is_empty() => {
+                date.checked_add_days(Days::new(7 * u64::from(interval)))
+            },
+            Frequency::Weekly => {
+                let offset = date.weekday().num_days_from_monday();
+                let later_this_week = (offset + 1..7).find(|day| {
+                    self.weekdays
+                        .iter()
+                        .any(|w| w.num_days_from_monday() == *day)
+                });
+                match later_this_week {
+                    Some(day) => date.checked_add_days(Days::new((day - offset).into())),
+                    None => {
+                        // Jump to the Monday `interval` weeks on and take the first listed day
+                        let first = self
+                            .weekdays
+                            .iter()
+                            .map(|w| w.num_days_from_monday())
+                            .min()
+                            .unwrap_or(0);
+                        let monday = date - Days::new(offset.into());
+                        monday
+                            .checked_add_days(Days::new(7 * u64::from(interval) + u64::from(first)))
+                    },
+                }
+            },
+        }
+    }
+
+    /// Next occurrence after `anchor` that is also after `today`, so a chore
+    /// finished late is not scheduled in the past.
+    pub fn next_due(&self, anchor: NaiveDate, today: NaiveDate) -> Option<NaiveDate> {
+        let mut next = self.next_after(anchor)?;
+        while next <= today {
+            next = self.next_after(next)?;
+        }
+        Some(next)
+    }
+
+    pub fn describe(&self) -> String {
+        let unit = match self.frequency {
+            Frequency::Daily => "day",
+            Frequency::Weekly => "week",
+            Frequency::Monthly => "month",
+        };
+        let mut text = match (self.frequency, self.interval) {
+            (Frequency::Daily, 1) => "daily".to_string(),
+            (Frequency::Weekly, 1) => "weekly".to_string(),
+            (Frequency::Monthly, 1) => "monthly".to_string(),
+            (_, n) => format!("every {} {}s", n, unit),
+        };
+        if !self.weekdays.is_empty() {
+            let days: Vec<String> = self.weekdays.iter().map(|w| w.to_string()).collect();
+            text.push_str(&format!(" on {}", days.join(", ")));
+        }
+        if let Some(day) = self.month_day {
+            text.push_str(&format!(" on day {}", day));
+        }
+        text
+    }
+
+    fn parse_rrule(rule: &str) -> Option<Self> {
+        let mut recurrence = None;
+        let mut interval = 1;
+        let mut weekdays = Vec::new();
+        let mut month_day = None;
+        for part in rule.split(';').filter(|part| !part.is_empty()) {
+            let (key, value) = part.split_once('=')?;
+            match key {
+                "FREQ" => {
+                    recurrence = Some(match value {
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        _ => return None,
+                    })
+                },
+                "INTERVAL" => interval = value.parse().ok().filter(|n| *n > 0)?,
+                "BYDAY" => weekdays = parse_weekdays(value)?,
+                "BYMONTHDAY" => {
+                    month_day = Some(value.parse().ok().filter(|day| (1..=31).contains(day))?)
+                },
+                _ => return None,
+            }
+        }
+        let mut recurrence = Recurrence::new(recurrence?, interval);
+        match recurrence.frequency {
+            Frequency::Weekly => recurrence.weekdays = weekdays,
+            _ if !weekdays.is_empty() => return None,
+            _ => {},
+        }
+        match recurrence.frequency {
+            Frequency::Monthly => recurrence.month_day = month_day,
+            _ if month_day.is_some() => return None,
+            _ => {},
+        }
+        Some(recurrence)
+    }
+}
+
+impl FromStr for Recurrence {
+    type Err = String;
+
+    /// Accepts `daily`, `weekly`, `weekly:mon,thu`, `monthly`, `every 3 days`,
+    /// `every:2w` or RRULE syntax (`FREQ=WEEKLY;BYDAY=MO`).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "invalid recurrence `{}` (try daily, weekly:mon,thu, monthly, every 3 days or FREQ=WEEKLY;BYDAY=MO)",
+                s
+            )
+        };
+        let input = s.trim();
+        let upper = input.to_ascii_uppercase();
+        if let Some(rule) = upper.strip_prefix("RRULE:").or(Some(upper.as_str()))
+            && rule.starts_with("FREQ=")
+        {
+            return Recurrence::parse_rrule(rule).ok_or_else(invalid);
+        }
+
+        let lower = input.to_ascii_lowercase();
+        let (base, days) = match lower.split_once(':') {
+            Some((base, days)) if base != "every" => (base.to_string(), Some(days)),
+            _ => (lower.clone(), None),
+        };
+        let mut recurrence = match base.as_str() {
+            "daily" => Recurrence::new(Frequency::Daily, 1),
+            "weekly" => Recurrence::new(Frequency::Weekly, 1),
+            "monthly" => Recurrence::new(Frequency::Monthly, 1),
+            _ => {
+                let every = base
+                    .strip_prefix("every")
+                    .ok_or_else(invalid)?
+                    .trim_start_matches([' ', ':']);
+                let unit_start = every
+                    .find(|c: char| !c.is_ascii_digit())
+                    .ok_or_else(invalid)?;
+                let interval: u32 = every[..unit_start].parse().map_err(|_| invalid())?;
+                if interval == 0 {
+                    return Err(invalid());
+                }
+                let frequency = match every[unit_start..].trim() {
+                    "d" | "day" | "days" => Frequency::Daily,
+                    "w" | "week" | "weeks" => Frequency::Weekly,
+                    "m" | "month" | "months" => Frequency::Monthly,
+                    _ => return Err(invalid()),
+                };
+                Recurrence::new(frequency, interval)
+            },
+        };
+        if let Some(days) = days {
+            if recurrence.frequency != Frequency::Weekly {
+                return Err(invalid());
+            }
+            recurrence.weekdays = days
+                .split(',')
+                .map(|day| Weekday::from_str(day.trim()).map_err(|_| invalid()))
+                .collect::<Result<_, _>>()?;
+        }
+        Ok(recurrence)
+    }
+}
+
+impl fmt::Display for Recurrence {
+    /// Canonical RRULE form, as written to the task list.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let frequency = match self.frequency {
+            Frequency::Daily => "DAILY",
+            Frequency::Weekly => "WEEKLY",
+            Frequency::Monthly => "MONTHLY",
+        };
+        write!(f, "FREQ={}", frequency)?;
+        if self.interval > 1 {
+            write!(f, ";INTERVAL={}", self.interval)?;
+        }
+        if !self.weekdays.is_empty() {
+            let days: Vec<&str> = self.weekdays.iter().map(|w| byday(*w)).collect();
+            write!(f, ";BYDAY={}", days.join(","))?;
+        }
+        if let Some(day) = self.month_day {
+            write!(f, ";BYMONTHDAY={}", day)?;
+        }
+        Ok(())
+    }
+}
+
+fn byday(weekday: Weekday) -> &'static str {
+    match weekday {
+        Weekday::Mon => "MO",
+        Weekday::Tue => "TU",
+        Weekday::Wed => "WE",
+        Weekday::Thu => "TH",
+        Weekday::Fri => "FR",
+        Weekday::Sat => "SA",
+        Weekday::Sun => "SU",
+    }
+}
+
+fn parse_weekdays(value: &str) -> Option<Vec<Weekday>> {
+    value
+        .split(',')
+        .map(|day| {
+            [
+                Weekday::Mon,
+                Weekday::Tue,
+                Weekday::Wed,
+                Weekday::Thu,
+                Weekday::Fri,
+                Weekday::Sat,
+                Weekday::Sun,
+            ]
+            .into_iter()
+            .find(|weekday| byday(*weekday) == day)
+        })
+        .collect()
+}
+
+/// A recurrence given on the command line, or `none` to clear it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecurrenceArg(pub Option<Recurrence>);
+
+impl FromStr for RecurrenceArg {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("none") {
+            return Ok(RecurrenceArg(None));
+        }
+        s.parse().map(|recurrence| RecurrenceArg(Some(recurrence)))
+    }
+}
@@ -0,0 +1,236 @@
+use crate::task::{Estimate, Status, Task, format_duration};
+use chrono::{Datelike, Duration, NaiveDateTime, NaiveTime};
+use std::fmt;
+use tabled::builder::Builder;
+use tabled::settings::Style;
+
+/// Time window a report covers, ending now.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Period {
+    All,
+    Today,
+    Week,
+}
+
+impl Period {
+    /// Start of the window, or `None` for all time.
+    pub fn since(&self, now: NaiveDateTime) -> Option<NaiveDateTime> {
+        let midnight = now.date().and_time(NaiveTime::MIN);
+        match self {
+            Period::All => None,
+            Period::Today => Some(midnight),
+            Period::Week => {
+                Some(midnight - Duration::days(now.weekday().num_days_from_monday() as i64))
+            },
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            Period::All => "all time",
+            Period::Today => "today",
+            Period::Week => "this week",
+        }
+    }
+}
+
+/// Tracked time of `task` that falls between `since` and `now`.
+pub fn tracked_between(task: &Task, since: Option<NaiveDateTime>, now: NaiveDateTime) -> Duration {
+    let clip = |start: NaiveDateTime, end: NaiveDateTime| {
+        let start = since.map_or(start, |since| start.max(since));
+        let end = end.min(now);
+        if end > start {
+            end - start
+        } else {
+            Duration::zero()
+        }
+    };
+    let logged = task
+        .time_log
+        .iter()
+        .fold(Duration::zero(), |total, interval| {
+            total + clip(interval.start, interval.end)
+        });
+    match task.timer {
+        Some(start) => logged + clip(start, now),
+        None => logged,
+    }
+}
+
+pub fn print_time_report(tasks: &[Task], period: Period, now: NaiveDateTime) {
+    let since = period.since(now);
+    let mut rows: Vec<(&Task, Duration)> = tasks
+        .iter()
+        .map(|task| (task, tracked_between(task, since, now)))
+        .filter(|(_, tracked)| *tracked > Duration::zero())
+        .collect();
+
+    println!("Time tracked ({})", period.label());
+    if rows.is_empty() {
+        println!("No time recorded.");
+        return;
+    }
+    rows.sort_by_key(|(_, tracked)| std::cmp::Reverse(*tracked));
+
+    let mut builder = Builder::default();
+    builder.push_record(["id", "description", "hours", "tracked"]);
+    let mut total = Duration::zero();
+    for (task, tracked) in &rows {
+        total += *tracked;
+        let running = if task.timer.is_some() { " ⏱" } else { "" };
+        builder.push_record([
+            task.id.to_string(),
+            format!("{}{}", task.description, running),
+            format!("{:.2}", hours(*tracked)),
+            format_duration(*tracked),
+        ]);
+    }
+    builder.push_record([
+        String::new(),
+        "Total".to_string(),
+        format!("{:.2}", hours(total)),
+        format_duration(total),
+    ]);
+
+    let mut table = builder.build();
+    table.with(Style::modern());
+    println!("{table}");
+}
+
+fn hours(duration: Duration) -> f64 {
+    duration.num_seconds() as f64 / 3600.0
+}
+
+/// Sum of estimates. Points and durations are kept apart since they don't mix.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct EstimateTotal {
+    pub points: f64,
+    pub minutes: i64,
+}
+
+impl EstimateTotal {
+    pub fn of<'a>(tasks: impl IntoIterator<Item = &'a Task>) -> Self {
+        let mut total = EstimateTotal::default();
+        for task in tasks {
+            match task.estimate {
+                Some(Estimate::Points(points)) => total.points += points,
+                Some(Estimate::Minutes(minutes)) => {
+                    total.minutes = total.minutes.saturating_add(minutes)
+                },
+                None => {},
+            }
+        }
+        total
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points == 0.0 && self.minutes == 0
+    }
+}
+
+impl fmt::Display for EstimateTotal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut parts = Vec::new();
+        if self.points != 0.0 {
+            parts.push(Estimate::Points(self.points).to_string());
+        }
+        if self.minutes != 0 {
+            parts.push(Estimate::Minutes(self.minutes).to_string());
+        }
+        write!(f, "{}", parts.join(" + "))
+    }
+}
+
+pub fn print_estimate_report(tasks: &[Task], now: NaiveDateTime) {
+    let done: Vec<&Task> = tasks
+        .iter()
+        .filter(|task| task.status == Status::Done && task.estimate.is_some())
+        .collect();
+
+    println!("Estimate vs. actual (done tasks)");
+    if done.is_empty() {
+        println!("No estimated tasks are done yet.");
+    } else {
+        let mut builder = Builder::default();
+        builder.push_record(["id", "description", "estimate", "tracked", "ratio"]);
+        for task in &done {
+            let tracked = task.tracked(now);
+            let ratio = match task.estimate {
+                _ if tracked == Duration::zero() => "-".to_string(),
+                Some(Estimate::Minutes(minutes)) if minutes > 0 => {
+                    format!("{:.2}x", tracked.num_minutes() as f64 / minutes as f64)
+                },
+                Some(Estimate::Points(points)) if points > 0.0 => {
+                    format!("{:.2}h/pt", hours(tracked) / points)
+                },
+                _ => "-".to_string(),
+            };
+            builder.push_record([
+                task.id.to_string(),
+                task.description.clone(),
+                display_or_dash(task.estimate),
+                format_duration(tracked),
+                ratio,
+            ]);
+        }
+        let mut table = builder.build();
+        table.with(Style::modern());
+        println!("{table}");
+
+        // Calibration only counts tasks that actually have tracked time
+        let tracked: Vec<(&Task, Duration)> = done
+            .iter()
+            .map(|task| (*task, task.tracked(now)))
+            .filter(|(_, tracked)| *tracked > Duration::zero())
+            .collect();
+        let estimated = EstimateTotal::of(tracked.iter().map(|(task, _)| *task));
+        let spent = |points: bool| {
+            tracked
+                .iter()
+                .filter(|(task, _)| matches!(task.estimate, Some(Estimate::Points(_))) == points)
+                .fold(Duration::zero(), |total, (_, tracked)| total + *tracked)
+        };
+        if estimated.minutes > 0 {
+            let actual = spent(false);
+            println!(
+                "Durations: estimated {}, tracked {} ({:.2}x)",
+                format_duration(Duration::try_minutes(estimated.minutes).unwrap_or(Duration::MAX)),
+                format_duration(actual),
+                actual.num_minutes() as f64 / estimated.minutes as f64
+            );
+        }
+        if estimated.points > 0.0 {
+            let actual = spent(true);
+            println!(
+                "Points: {} took {} ({:.2}h per point)",
+                Estimate::Points(estimated.points),
+                format_duration(actual),
+                hours(actual) / estimated.points
+            );
+        }
+    }
+
+    println!();
+    println!("Remaining work");
+    for status in [Status::NotStarted, Status::InProgress] {
+        let total = EstimateTotal::of(tasks.iter().filter(|task| task.status == status));
+        let unestimated = tasks
+            .iter()
+            .filter(|task| task.status == status && task.estimate.is_none())
+            .count();
+        println!(
+            "  {:16} {} ({} unestimated)",
+            status.as_label(),
+            if total.is_empty() {
+                "-".to_string()
+            } else {
+                total.to_string()
+            },
+            unestimated
+        );
+    }
+}
+
+fn display_or_dash(estimate: Option<Estimate>) -> String {
+    estimate.map_or_else(|| "-".to_string(), |e| e.to_string())
+}
@@ -0,0 +1,329 @@
+use crate::board::Board;
+use crate::task::{Link, Status, Task, TaskId, escape, timestamp, unescape};
+use colored::Colorize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
+
+/// Comment tags that become tasks.
+const TAGS: &[&str] = &["TODO", "FIXME", "HACK"];
+
+/// How comments start in a family of languages.
+struct Syntax {
+    /// What may come right before a tag for it to count as a comment.
+    openers: &'static [&'static str],
+    /// Whether ` * ` continues a block comment, as in C.
+    star_lines: bool,
+}
+
+const C_LIKE: Syntax = Syntax {
+    openers: &["//", "/*"],
+    star_lines: true,
+};
+const HASH: Syntax = Syntax {
+    openers: &["#"],
+    star_lines: false,
+};
+const DASHES: Syntax = Syntax {
+    openers: &["--"],
+    star_lines: false,
+};
+const SEMICOLON: Syntax = Syntax {
+    openers: &[";"],
+    star_lines: false,
+};
+const MARKUP: Syntax = Syntax {
+    openers: &["<!--"],
+    star_lines: false,
+};
+const PHP: Syntax = Syntax {
+    openers: &["//", "/*", "#"],
+    star_lines: true,
+};
+
+/// The comment syntax of `path`, from its extension or name. Prose (Markdown,
+/// plain text) and unknown files have none: a `# TODO` heading or a
+/// `* TODO` bullet is not a comment.
+fn syntax(path: &Path) -> Option<&'static Syntax> {
+    let name = path.file_name()?.to_string_lossy();
+    if matches!(&*name, "Makefile" | "Dockerfile" | "CMakeLists.txt") {
+        return Some(&HASH);
+    }
+    let extension = path.extension()?.to_string_lossy().to_ascii_lowercase();
+    Some(match extension.as_str() {
+        "rs" | "c" | "h" | "cc" | "cpp" | "hpp" | "cs" | "java" | "kt" | "kts" | "scala"
+        | "groovy" | "go" | "swift" | "dart" | "zig" | "js" | "mjs" | "cjs" | "jsx" | "ts"
+        | "tsx" | "css" | "scss" | "less" | "proto" => &C_LIKE,
+        "py" | "rb" | "sh" | "bash" | "zsh" | "fish" | "pl" | "pm" | "r" | "toml" | "yaml"
+        | "yml" | "nix" | "ps1" | "tf" | "cmake" | "mk" | "conf" | "properties" => &HASH,
+        "sql" | "lua" | "hs" | "elm" | "ada" => &DASHES,
+        "lisp" | "el" | "clj" | "scm" | "ini" | "asm" | "s" => &SEMICOLON,
+        "html" | "htm" | "xml" | "svg" | "vue" => &MARKUP,
+        "php" => &PHP,
+        _ => return None,
+    })
+}
+
+/// A tagged comment found in a source file.
+#[derive(Debug, Clone)]
+pub struct Found {
+    pub path: PathBuf,
+    /// 1-based.
+    pub line: usize,
+    /// `TODO: text`, the task's description.
+    pub description: String,
+}
+
+/// Walks `paths` like git would, skipping what `.gitignore`, `.ignore` and
+/// hidden names exclude, and returns every TODO/FIXME/HACK comment. Paths
+/// that can't be read are skipped with a warning.
+pub fn find(paths: &[String]) -> Result<Vec<Found>, Error> {
+    let (first, rest) = paths
+        .split_first()
+        .ok_or_else(|| Error::other("No paths to scan"))?;
+    let mut walker = ignore::WalkBuilder::new(first);
+    for path in rest {
+        walker.add(path);
+    }
+    // Honor .gitignore outside git repositories too
+    walker.require_git(false);
+
+    let mut found = Vec::new();
+    for entry in walker.build() {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                eprintln!("{} {}", "Warning:".yellow(), e);
+                continue;
+            },
+        };
+        if !entry.file_type().is_some_and(|kind| kind.is_file()) {
+            continue;
+        }
+        let Some(syntax) = syntax(entry.path()) else {
+            continue;
+        };
+        let content = match fs::read_to_string(entry.path()) {
+            Ok(content) => content,
+            // Binary and non-UTF-8 files have no comments worth reading
+            Err(e) if e.kind() == ErrorKind::InvalidData => continue,
+            Err(e) => {
+                eprintln!(
+                    "{} Could not read {}: {}",
+                    "Warning:".yellow(),
+                    entry.path().display(),
+                    e
+                );
+                continue;
+            },
+        };
+        for (index, line) in content.lines().enumerate() {
+            if let Some(description) = parse_line(line, syntax) {
+                found.push(Found {
+                    path: entry.path().to_path_buf(),
+                    line: index + 1,
+                    description,
+                });
+            }
+        }
+    }
+    Ok(found)
+}
+
+/// `// TODO(ana): retry on timeout` gives `TODO: retry on timeout`.
+fn parse_line(line: &str, syntax: &Syntax) -> Option<String> {
+    for tag in TAGS {
+        for (start, _) in line.match_indices(tag) {
+            let before = line[..start].trim_end();
+            // The tag must follow the comment's own opener, so a `// TODO`
+            // quoted in a `///` doc example doesn't count
+            let opens = syntax.openers.iter().any(|opener| {
+                before.strip_suffix(opener).is_some_and(|code| {
+                    let code = code.trim_end_matches(opener);
+                    !syntax.openers.iter().any(|other| code.contains(other))
+                })
+            });
+            let star_line = syntax.star_lines && before.trim_start() == "*";
+            if !(opens || star_line) {
+                continue;
+            }
+            let mut after = &line[start + tag.len()..];
+            if after.starts_with(|c: char| c.is_alphanumeric() || c == '_') {
+                // Part of a longer word, like TODOS
+                continue;
+            }
+            if after.starts_with('(')
+                && let Some(end) = after.find(')')
+            {
+                after = &after[end + 1..];
+            }
+            let text = after
+                .trim_start_matches([':', ' ', '\t', '-'])
+                .trim_end()
+                .trim_end_matches("*/")
+                .trim_end_matches("-->")
+                .trim();
+            let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+            return Some(if text.is_empty() {
+                tag.to_string()
+            } else {
+                format!("{}: {}", tag, text)
+            });
+        }
+    }
+    None
+}
+
+/// What a scan did to the board.
+#[derive(Debug, Default)]
+pub struct ScanOutcome {
+    pub added: Vec<Task>,
+    /// Tasks whose comment moved to another line.
+    pub moved: Vec<Task>,
+    /// Tasks whose comment was edited in place.
+    pub renamed: Vec<Task>,
+    /// Tasks closed by an earlier scan whose comment came back.
+    pub reopened: Vec<Task>,
+    /// Tasks whose comment is gone.
+    pub closed: Vec<Task>,
+}
+
+/// Marks a task the scan closed, so it is reopened if its comment comes
+/// back; a task closed by hand stays closed.
+const CLOSED_FIELD: &str = "scan_closed=1";
+
+/// The source file a scanned task came from, from its `scan=` field.
+fn scanned_file(task: &Task) -> Option<String> {
+    task.extra
+        .iter()
+        .find_map(|field| field.strip_prefix("scan="))
+        .map(unescape)
+}
+
+/// The `file:line` a scanned task links to.
+fn location(task: &Task, file: &str) -> Option<String> {
+    let prefix = format!("{}:", file);
+    task.links.iter().find_map(|link| match link {
+        Link::File(target) if target.starts_with(&prefix) => Some(target.clone()),
+        _ => None,
+    })
+}
+
+/// Brings the board in line with `comments`, given as (file, line,
+/// description) with files relative to the board. A comment is matched to
+/// the task with the same file and description, so edits elsewhere in the
+/// file only move its link; failing that, to the task at the same
+/// `file:line`, which is renamed. Tasks under `scopes` whose comment is gone
+/// are marked done, and reopened if it comes back.
+pub fn apply(
+    board: &mut Board,
+    comments: &[(String, usize, String)],
+    scopes: &[PathBuf],
+) -> Result<ScanOutcome, Error> {
+    let mut by_text: HashMap<(String, String), Vec<TaskId>> = HashMap::new();
+    let mut by_location: HashMap<String, TaskId> = HashMap::new();
+    for task in board.tasks() {
+        if let Some(file) = scanned_file(task) {
+            if let Some(location) = location(task, &file) {
+                by_location.insert(location, task.id);
+            }
+            by_text
+                .entry((file, task.description.clone()))
+                .or_default()
+                .push(task.id);
+        }
+    }
+
+    let mut outcome = ScanOutcome::default();
+    let mut matched = HashSet::new();
+    let mut unmatched = Vec::new();
+    for comment @ (file, line, description) in comments {
+        let id = by_text
+            .get_mut(&(file.clone(), description.clone()))
+            .and_then(|ids| (!ids.is_empty()).then(|| ids.remove(0)));
+        let Some(id) = id else {
+            unmatched.push(comment);
+            continue;
+        };
+        matched.insert(id);
+        let task = board.task_mut(id)?;
+        let location = format!("{}:{}", file, line);
+        let prefix = format!("{}:", file);
+        let link = task
+            .links
+            .iter_mut()
+            .find(|link| matches!(link, Link::File(target) if target.starts_with(&prefix)));
+        match link {
+            Some(Link::File(target)) if *target == location => {},
+            Some(link) => {
+                *link = Link::File(location);
+                outcome.moved.push(task.clone());
+            },
+            None => {
+                task.links.push(Link::File(location));
+                outcome.moved.push(task.clone());
+            },
+        }
+        if reopen(task) {
+            outcome.reopened.push(task.clone());
+        }
+    }
+
+    for (file, line, description) in unmatched {
+        let location = format!("{}:{}", file, line);
+        match by_location
+            .get(&location)
+            .copied()
+            .filter(|id| matched.insert(*id))
+        {
+            Some(id) => {
+                let task = board.task_mut(id)?;
+                task.description = description.clone();
+                task.date = timestamp();
+                if reopen(task) {
+                    outcome.reopened.push(task.clone());
+                }
+                outcome.renamed.push(task.clone());
+            },
+            None => {
+                let mut task = Task::new(0, Status::NotStarted, description.clone(), timestamp());
+                task.links.push(Link::File(location));
+                task.extra.push(format!("scan={}", escape(file)));
+                let id = board.push(task)?;
+                matched.insert(id);
+                outcome.added.push(board.task_mut(id)?.clone());
+            },
+        }
+    }
+
+    for task in board.tasks_mut() {
+        let Some(file) = scanned_file(task) else {
+            continue;
+        };
+        let in_scope = scopes
+            .iter()
+            .any(|scope| Path::new(&file).starts_with(scope));
+        if in_scope && !matched.contains(&task.id) && task.status != Status::Done {
+            task.status = Status::Done;
+            task.date = timestamp();
+            task.extra.push(CLOSED_FIELD.to_string());
+            outcome.closed.push(task.clone());
+        }
+    }
+    Ok(outcome)
+}
+
+/// Reopens a task the scan closed. Returns whether it did.
+fn reopen(task: &mut Task) -> bool {
+    let before = task.extra.len();
+    task.extra.retain(|field| field != CLOSED_FIELD);
+    if task.extra.len() == before {
+        return false;
+    }
+    if task.status == Status::Done {
+        task.status = Status::NotStarted;
+        task.date = timestamp();
+    }
+    true
+}
@@ -0,0 +1,434 @@
+use super::{Storage, atomic_write};
+use crate::board::{Board, Entry};
+use crate::config::sidecar_dir;
+use crate::task::{SEP, TIME_FORMAT, Task, TaskId, now};
+use fs2::FileExt;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{Error, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+
+/// A snapshot of the replayed board is written after this many batches.
+const SNAPSHOT_EVERY: u64 = 100;
+
+/// One change recorded in the log.
+#[derive(Debug, Clone)]
+pub struct Event {
+    /// Number of the batch (one `update`) the event belongs to.
+    pub seq: u64,
+    /// When it was appended, `YYYY-MM-DD HH:MM:SS`.
+    pub at: String,
+    pub change: Change,
+}
+
+#[derive(Debug, Clone)]
+pub enum Change {
+    Add(Task),
+    Update(Task),
+    /// Holds the task as it was when deleted.
+    Delete(Task),
+    MaxId(TaskId),
+    /// Drops the board's non-task lines; `Raw` events put them back.
+    ClearRaw,
+    /// A non-task line and the number of tasks above it. Logs written before
+    /// positions were recorded have `None`: the line goes above the tasks.
+    Raw(Option<usize>, String),
+}
+
+/// An append-only log of changes. Each line is
+/// `<seq>\t<timestamp>\t<op>\t<payload>`, where the payload of a task event
+/// is the task's TSV line. Every batch ends with a `commit` line, and a batch
+/// without one (a write cut short by a crash) is ignored and cut off by the
+/// next append. The board is the replay of all committed batches, starting
+/// from the latest snapshot in `<tasklist>.d/snapshot`.
+#[derive(Debug)]
+pub struct EventStorage {
+    path: String,
+}
+
+/// The board as of the end of the committed part of the log.
+struct Replay {
+    board: Board,
+    seq: u64,
+    /// Bytes of the log that hold committed batches.
+    len: u64,
+    /// Batches replayed on top of the snapshot.
+    since_snapshot: u64,
+}
+
+impl EventStorage {
+    pub fn new(path: &str) -> Self {
+        EventStorage {
+            path: path.to_string(),
+        }
+    }
+
+    /// Every committed event, oldest first.
+    pub fn events(&self) -> Result<Vec<Event>, Error> {
+        let mut file = self.open(false)?;
+        file.lock_shared()?;
+        let mut content = String::new();
+        file.read_to_string(&mut content)
+            .map_err(|e| self.invalid(e))?;
+        let mut events = Vec::new();
+        let mut batch = Vec::new();
+        for line in committed_lines(&content) {
+            match self.parse_line(line)? {
+                Some(event) => batch.push(event),
+                None => events.append(&mut batch),
+            }
+        }
+        Ok(events)
+    }
+
+    fn open(&self, create: bool) -> Result<File, Error> {
+        OpenOptions::new()
+            .read(true)
+            .write(create)
+            .create(create)
+            .truncate(false)
+            .open(&self.path)
+            .map_err(|e| {
+                Error::new(
+                    e.kind(),
+                    format!("Could not read task list {}: {}", self.path, e),
+                )
+            })
+    }
+
+    fn snapshot_path(&self) -> PathBuf {
+        sidecar_dir(&self.path).join("snapshot")
+    }
+
+    fn invalid(&self, message: impl std::fmt::Display) -> Error {
+        Error::new(
+            ErrorKind::InvalidData,
+            format!("{}: {}", self.path, message),
+        )
+    }
+
+    /// Parses one log line; `None` is a commit.
+    fn parse_line(&self, line: &str) -> Result<Option<Event>, Error> {
+        let bad = || self.invalid(format!("bad event `{}`", line));
+        let mut columns = line.splitn(4, SEP);
+        let (Some(seq), Some(at), Some(op)) = (columns.next(), columns.next(), columns.next())
+        else {
+            return Err(bad());
+        };
+        let seq = seq.parse().map_err(|_| bad())?;
+        let payload = columns.next().unwrap_or("");
+        let task = || Task::from_file_line(payload).ok_or_else(bad);
+        let change = match op {
+            "commit" => return Ok(None),
+            "add" => Change::Add(task()?),
+            "update" => Change::Update(task()?),
+            "delete" => Change::Delete(task()?),
+            "max_id" => Change::MaxId(payload.parse().map_err(|_| bad())?),
+            "clear_raw" => Change::ClearRaw,
+            "raw" => Change::Raw(None, payload.to_string()),
+            "raw_at" => {
+                let (after, line) = payload.split_once(SEP).ok_or_else(bad)?;
+                Change::Raw(Some(after.parse().map_err(|_| bad())?), line.to_string())
+            },
+            _ => return Err(bad()),
+        };
+        Ok(Some(Event {
+            seq,
+            at: at.to_string(),
+            change,
+        }))
+    }
+
+    /// Reads the snapshot, if there is one: the batch number and log length
+    /// it was taken at, and the board.
+    fn read_snapshot(&self) -> Option<(u64, u64, Board)> {
+        let content = fs::read_to_string(self.snapshot_path()).ok()?;
+        let lines: Vec<String> = content.lines().map(str::to_string).collect();
+        let (seq, len) = lines
+            .first()?
+            .strip_prefix("#seq=")?
+            .split_once(&format!("{SEP}len="))?;
+        let board = Board::parse(&self.path, &lines[1..], false).ok()?;
+        Some((seq.parse().ok()?, len.parse().ok()?, board))
+    }
+
+    fn replay(&self, file: &mut File) -> Result<Replay, Error> {
+        let file_len = file.metadata()?.len();
+        if let Some((seq, len, board)) = self.read_snapshot()
+            && len <= file_len
+            && let Some(replay) = self.replay_from(file, seq, len, board)?
+        {
+            return Ok(replay);
+        }
+        self.replay_from(file, 0, 0, Board::default())
+            .map(|replay| replay.expect("a replay from the start always applies"))
+    }
+
+    /// Replays the log from byte `start`, on top of `board` as of batch
+    /// `seq`. Returns `None` if the log doesn't continue from there, i.e. the
+    /// snapshot belongs to another log.
+    fn replay_from(
+        &self,
+        file: &mut File,
+        seq: u64,
+        start: u64,
+        board: Board,
+    ) -> Result<Option<Replay>, Error> {
+        // The snapshot must end on a line boundary of this log
+        file.seek(SeekFrom::Start(start.saturating_sub(1)))?;
+        let mut content = String::new();
+        file.read_to_string(&mut content)
+            .map_err(|e| self.invalid(e))?;
+        let content = match start {
+            0 => content.as_str(),
+            _ => match content.strip_prefix('\n') {
+                Some(rest) => rest,
+                None => return Ok(None),
+            },
+        };
+
+        let mut replay = Replay {
+            board,
+            seq,
+            len: start,
+            since_snapshot: 0,
+        };
+        let mut batch = Vec::new();
+        let mut read = start;
+        for line in committed_lines(content) {
+            read += line.len() as u64 + 1;
+            let event = self.parse_line(line)?;
+            let seq = event
+                .as_ref()
+                .map_or_else(|| line_seq(line), |event| Some(event.seq));
+            if seq != Some(replay.seq + 1) {
+                if replay.since_snapshot == 0 && start > 0 {
+                    return Ok(None);
+                }
+                return Err(self.invalid(format!("event out of order: `{}`", line)));
+            }
+            match event {
+                Some(event) => batch.push(event.change),
+                None => {
+                    for change in batch.drain(..) {
+                        apply(&mut replay.board, change);
+                    }
+                    replay.seq += 1;
+                    replay.since_snapshot += 1;
+                    replay.len = read;
+                },
+            }
+        }
+        Ok(Some(replay))
+    }
+
+    /// Appends the changes that turn the replayed board into `board`.
+    fn append(&self, file: &mut File, replay: &Replay, board: &Board) -> Result<(), Error> {
+        let changes = diff(&replay.board, board);
+        if changes.is_empty() {
+            return Ok(());
+        }
+        let seq = replay.seq + 1;
+        let at = now().format(TIME_FORMAT).to_string();
+        let mut batch = String::new();
+        for change in &changes {
+            let (op, payload) = match change {
+                Change::Add(task) => ("add", task.to_file_string()),
+                Change::Update(task) => ("update", task.to_file_string()),
+                Change::Delete(task) => ("delete", task.to_file_string()),
+                Change::MaxId(max_id) => ("max_id", max_id.to_string()),
+                Change::ClearRaw => ("clear_raw", String::new()),
+                Change::Raw(None, line) => ("raw", line.clone()),
+                Change::Raw(Some(after), line) => ("raw_at", format!("{after}{SEP}{line}")),
+            };
+            batch.push_str(&format!("{seq}{SEP}{at}{SEP}{op}{SEP}{payload}\n"));
+        }
+        batch.push_str(&format!("{seq}{SEP}{at}{SEP}commit{SEP}\n"));
+
+        // Drop whatever a crashed write left after the last commit
+        file.set_len(replay.len)?;
+        file.seek(SeekFrom::End(0))?;
+        file.write_all(batch.as_bytes())
+            .and_then(|_| file.sync_data())
+            .map_err(|e| {
+                Error::new(
+                    e.kind(),
+                    format!("Could not append to {}: {}", self.path, e),
+                )
+            })?;
+
+        let len = replay.len + batch.len() as u64;
+        if seq == 1 {
+            // A new log: any snapshot left over belongs to an older one
+            match fs::remove_file(self.snapshot_path()) {
+                Err(e) if e.kind() != ErrorKind::NotFound => return Err(e),
+                _ => {},
+            }
+        } else if replay.since_snapshot + 1 >= SNAPSHOT_EVERY {
+            self.write_snapshot(seq, len, board)?;
+        }
+        Ok(())
+    }
+
+    fn write_snapshot(&self, seq: u64, len: u64, board: &Board) -> Result<(), Error> {
+        let path = self.snapshot_path();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        atomic_write(&path.to_string_lossy(), |writer| {
+            writeln!(writer, "#seq={seq}{SEP}len={len}")?;
+            writeln!(writer, "#max_id={}", board.max_id)?;
+            for entry in &board.entries {
+                match entry {
+                    Entry::Task(task) => task.write_to(writer)?,
+                    Entry::Raw(line) => writeln!(writer, "{}", line)?,
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn edit(&self, edit: &mut dyn FnMut(&mut Board) -> Result<(), Error>) -> Result<(), Error> {
+        let mut file = self.open(true)?;
+        file.lock_exclusive()
+            .map_err(|e| Error::other(format!("Failed to lock {}: {}", self.path, e)))?;
+        let replay = self.replay(&mut file)?;
+        let mut board = replay.board.clone();
+        edit(&mut board)?;
+        self.append(&mut file, &replay, &board)
+    }
+}
+
+impl Storage for EventStorage {
+    fn load(&self) -> Result<Board, Error> {
+        let mut file = self.open(false)?;
+        file.lock_shared()
+            .map_err(|e| Error::other(format!("Failed to lock {}: {}", self.path, e)))?;
+        Ok(self.replay(&mut file)?.board)
+    }
+
+    fn save(&self, board: &Board) -> Result<(), Error> {
+        self.edit(&mut |current| {
+            *current = board.clone();
+            Ok(())
+        })
+    }
+
+    fn update(&self, edit: &mut dyn FnMut(&mut Board) -> Result<(), Error>) -> Result<(), Error> {
+        self.edit(edit)
+    }
+}
+
+/// The complete lines of `content`; a last line without a newline is a
+/// write that never finished.
+fn committed_lines(content: &str) -> impl Iterator<Item = &str> {
+    content
+        .split_inclusive('\n')
+        .filter_map(|line| line.strip_suffix('\n'))
+}
+
+fn line_seq(line: &str) -> Option<u64> {
+    line.split(SEP).next()?.parse().ok()
+}
+
+fn apply(board: &mut Board, change: Change) {
+    let position = |board: &Board, task: &Task| {
+        let key = task.key();
+        board
+            .entries
+            .iter()
+            .position(|entry| matches!(entry, Entry::Task(t) if t.key() == key))
+    };
+    match change {
+        Change::Add(task) | Change::Update(task) => {
+            board.max_id = board.max_id.max(task.id);
+            match position(board, &task) {
+                Some(index) => board.entries[index] = Entry::Task(task),
+                None => board.entries.push(Entry::Task(task)),
+            }
+        },
+        Change::Delete(task) => {
+            if let Some(index) = position(board, &task) {
+                board.entries.remove(index);
+            }
+        },
+        Change::MaxId(max_id) => board.max_id = max_id,
+        Change::ClearRaw => board
+            .entries
+            .retain(|entry| matches!(entry, Entry::Task(_))),
+        Change::Raw(after, line) => {
+            // Below the task it followed and any lines already put back there
+            let mut tasks = 0;
+            let index = board
+                .entries
+                .iter()
+                .take_while(|entry| match entry {
+                    Entry::Raw(_) => true,
+                    Entry::Task(_) => {
+                        tasks += 1;
+                        tasks <= after.unwrap_or(0)
+                    },
+                })
+                .count();
+            board.entries.insert(index, Entry::Raw(line));
+        },
+    }
+}
+
+/// The non-task lines of `board`, each with the number of tasks above it.
+fn raw_lines(board: &Board) -> Vec<(usize, String)> {
+    let mut tasks = 0;
+    let mut lines = Vec::new();
+    for entry in &board.entries {
+        match entry {
+            Entry::Task(_) => tasks += 1,
+            Entry::Raw(line) => lines.push((tasks, line.clone())),
+        }
+    }
+    lines
+}
+
+/// The changes that turn `before` into `after`. Tasks are matched by key,
+/// so a renumbered task is an update.
+fn diff(before: &Board, after: &Board) -> Vec<Change> {
+    let mut changes = Vec::new();
+    if before.max_id != after.max_id {
+        changes.push(Change::MaxId(after.max_id));
+    }
+
+    let old: HashMap<String, &Task> = before.tasks().map(|task| (task.key(), task)).collect();
+    let mut kept = HashSet::new();
+    for task in after.tasks() {
+        let key = task.key();
+        match old.get(&key) {
+            None => changes.push(Change::Add(task.clone())),
+            Some(old) if old.to_file_string() != task.to_file_string() => {
+                changes.push(Change::Update(task.clone()))
+            },
+            Some(_) => {},
+        }
+        kept.insert(key);
+    }
+    for task in before.tasks() {
+        if !kept.contains(&task.key()) {
+            changes.push(Change::Delete(task.clone()));
+        }
+    }
+
+    // Non-task lines move with the tasks around them on replay; they are
+    // only logged again if that doesn't put them where they are now
+    let mut replayed = before.clone();
+    for change in &changes {
+        apply(&mut replayed, change.clone());
+    }
+    let after_raw = raw_lines(after);
+    if raw_lines(&replayed) != after_raw {
+        changes.push(Change::ClearRaw);
+        changes.extend(
+            after_raw
+                .into_iter()
+                .map(|(tasks, line)| Change::Raw(Some(tasks), line)),
+        );
+    }
+    changes
+}
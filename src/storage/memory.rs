@@ -0,0 +1,33 @@
+use super::Storage;
+use crate::board::Board;
+use std::cell::RefCell;
+use std::io::{Error, ErrorKind};
+
+/// Keeps the board in memory only; used by `--dry-run` so commands can be
+/// tried without touching the real board.
+#[derive(Debug)]
+pub struct MemoryStorage {
+    board: RefCell<Option<Board>>,
+}
+
+impl MemoryStorage {
+    pub fn new(board: Option<Board>) -> Self {
+        MemoryStorage {
+            board: RefCell::new(board),
+        }
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn load(&self) -> Result<Board, Error> {
+        self.board
+            .borrow()
+            .clone()
+            .ok_or_else(|| Error::new(ErrorKind::NotFound, "Board is empty"))
+    }
+
+    fn save(&self, board: &Board) -> Result<(), Error> {
+        *self.board.borrow_mut() = Some(board.clone());
+        Ok(())
+    }
+}
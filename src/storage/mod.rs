@@ -0,0 +1,295 @@
+//! Where boards live. `Mngr` only talks to the `Storage` trait; the backend
+//! is picked per board by the `backend` config key, or else by the file's
+//! extension.
+
+mod events;
+mod memory;
+mod sorted;
+mod sqlite;
+mod structured;
+mod tsv;
+
+pub use events::{Change, EventStorage};
+pub use memory::MemoryStorage;
+pub use sorted::SortedStorage;
+pub use sqlite::SqliteStorage;
+pub use structured::{Format, StructuredStorage};
+pub use tsv::TsvStorage;
+
+use crate::backup;
+use crate::board::{self, Board};
+use crate::config::Config;
+use crate::crypto;
+use crate::task::{Status, Task, TaskId, now};
+use chrono::NaiveDate;
+use clap::ValueEnum;
+use fs2::FileExt;
+use std::fmt;
+use std::fs;
+use std::io::{BufWriter, Error, ErrorKind, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+pub trait Storage: fmt::Debug {
+    /// Reads the whole board. A board that was never saved is `NotFound`.
+    fn load(&self) -> Result<Board, Error>;
+
+    /// Replaces the stored board with `board`.
+    fn save(&self, board: &Board) -> Result<(), Error>;
+
+    /// Loads the board (empty if it doesn't exist yet), lets `edit` change it
+    /// and saves it. Nothing is saved if `edit` fails.
+    fn update(&self, edit: &mut dyn FnMut(&mut Board) -> Result<(), Error>) -> Result<(), Error> {
+        let mut board = match self.load() {
+            Ok(board) => board,
+            Err(e) if e.kind() == ErrorKind::NotFound => Board::default(),
+            Err(e) => return Err(e),
+        };
+        edit(&mut board)?;
+        self.save(&board)
+    }
+
+    /// Lets `edit` change the task with `id` and saves it, returning the
+    /// saved task. Nothing is saved if `edit` fails. Backends that keep tasks
+    /// in rows override this so one edit doesn't load the whole board.
+    fn update_task(
+        &self,
+        id: TaskId,
+        edit: &mut dyn FnMut(&mut Task) -> Result<(), Error>,
+    ) -> Result<Task, Error> {
+        let mut saved = None;
+        self.update(&mut |board| {
+            // The whole board is written anyway, so older tasks get their
+            // UUIDs now as they would on any other write
+            board.assign_missing_uuids();
+            let task = board.task_mut(id)?;
+            edit(task)?;
+            saved = Some(task.clone());
+            Ok(())
+        })?;
+        saved.ok_or_else(|| board::not_found(id))
+    }
+
+    /// Removes the task with `id` and returns it. Backends that keep tasks
+    /// in rows override this.
+    fn delete_task(&self, id: TaskId) -> Result<Task, Error> {
+        let mut removed = None;
+        self.update(&mut |board| {
+            removed = Some(board.remove(id)?);
+            Ok(())
+        })?;
+        removed.ok_or_else(|| board::not_found(id))
+    }
+
+    /// Looks up a single task. Backends with an index override this.
+    fn find(&self, id: TaskId) -> Result<Option<Task>, Error> {
+        Ok(self.load()?.tasks().find(|task| task.id == id).cloned())
+    }
+
+    /// Tasks matching `query`, in board order. Backends with an index
+    /// override this.
+    fn query(&self, query: &TaskQuery) -> Result<Vec<Task>, Error> {
+        Ok(self
+            .load()?
+            .tasks()
+            .filter(|task| query.matches(task))
+            .cloned()
+            .collect())
+    }
+}
+
+/// The part of a task filter a backend can answer from its indexes.
+#[derive(Debug, Clone, Default)]
+pub struct TaskQuery {
+    pub status: Option<Status>,
+    /// Only tasks due on or before this day.
+    pub due_by: Option<NaiveDate>,
+}
+
+impl TaskQuery {
+    pub fn matches(&self, task: &Task) -> bool {
+        self.status.is_none_or(|status| task.status == status)
+            && self
+                .due_by
+                .is_none_or(|due_by| task.due.is_some_and(|due| due <= due_by))
+    }
+}
+
+/// The storage backends a board can be configured with. Their names are the
+/// ones `--to` and the `backend` config key accept.
+#[derive(Debug, Clone, Copy, Default, PartialEq, ValueEnum)]
+pub enum Backend {
+    /// One tab-separated line per task.
+    #[default]
+    Tsv,
+    /// A SQLite database, for large boards.
+    Sqlite,
+    /// TSV sorted by UUID without a counter line, for boards kept in git.
+    Merge,
+    /// An append-only log of changes, replayed into the board.
+    Events,
+    Json,
+    Toml,
+    #[value(alias = "yml")]
+    Yaml,
+}
+
+impl Backend {
+    /// Guesses the backend from a file name: `.json`, `.toml`, `.yaml`/`.yml`
+    /// and `.sqlite`/`.db`; anything else is TSV.
+    pub fn from_extension(path: &str) -> Backend {
+        let extension = Path::new(path)
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_ascii_lowercase());
+        match extension.as_deref() {
+            Some("json") => Backend::Json,
+            Some("toml") => Backend::Toml,
+            Some("yaml" | "yml") => Backend::Yaml,
+            Some("sqlite" | "db") => Backend::Sqlite,
+            _ => Backend::Tsv,
+        }
+    }
+}
+
+impl FromStr for Backend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        <Backend as ValueEnum>::from_str(s.trim(), true).map_err(|_| {
+            let names: Vec<String> = Backend::value_variants()
+                .iter()
+                .map(ToString::to_string)
+                .collect();
+            format!(
+                "unknown backend `{}` (expected one of: {})",
+                s,
+                names.join(", ")
+            )
+        })
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let value = self.to_possible_value().expect("no backend is skipped");
+        write!(f, "{}", value.get_name())
+    }
+}
+
+/// The backend the board at `tasklist_path` is stored with.
+pub fn backend_of(tasklist_path: &str, config: &Config) -> Backend {
+    config
+        .backend
+        .unwrap_or_else(|| Backend::from_extension(tasklist_path))
+}
+
+/// Opens the board at `tasklist_path` with the backend it is stored with.
+pub fn open(tasklist_path: &str, config: &Config, strict: bool) -> Box<dyn Storage> {
+    open_as(
+        backend_of(tasklist_path, config),
+        tasklist_path,
+        config,
+        strict,
+    )
+}
+
+/// Opens the board at `path` as `backend`, whatever its config says.
+pub fn open_as(backend: Backend, path: &str, config: &Config, strict: bool) -> Box<dyn Storage> {
+    match backend {
+        Backend::Tsv => Box::new(TsvStorage::new(
+            path,
+            strict || config.strict,
+            config.clone(),
+        )),
+        Backend::Sqlite => Box::new(SqliteStorage::new(path, config.clone())),
+        Backend::Merge => Box::new(SortedStorage::new(
+            path,
+            strict || config.strict,
+            config.clone(),
+        )),
+        Backend::Events => Box::new(EventStorage::new(path)),
+        Backend::Json => Box::new(StructuredStorage::new(path, Format::Json, config.clone())),
+        Backend::Toml => Box::new(StructuredStorage::new(path, Format::Toml, config.clone())),
+        Backend::Yaml => Box::new(StructuredStorage::new(path, Format::Yaml, config.clone())),
+    }
+}
+
+/// Backs the current file up as the board's config asks, prunes old backups,
+/// then replaces the file through `atomic_write`, encrypted if the board is.
+pub fn persist<F>(path: &str, config: &Config, write_fn: F) -> Result<(), Error>
+where
+    F: FnOnce(&mut dyn Write) -> Result<(), Error>,
+{
+    take_backup(path, config)?;
+    if config.encrypted {
+        let mut plaintext = Vec::new();
+        write_fn(&mut plaintext)?;
+        let sealed = crypto::seal(&plaintext)?;
+        return atomic_write(path, |writer| writer.write_all(&sealed));
+    }
+    atomic_write(path, write_fn)
+}
+
+/// Copies the file to the board's backups and prunes old ones, unless the
+/// config turns backups off.
+pub fn take_backup(path: &str, config: &Config) -> Result<(), Error> {
+    if config.backups > 0 {
+        let now = now();
+        backup::create(path, now)?;
+        backup::prune(path, config.backups, config.backup_days, now)?;
+    }
+    Ok(())
+}
+
+/// Reads a text board, decrypting it if it is encrypted.
+pub fn read_text(path: &str) -> Result<String, Error> {
+    let bytes = fs::read(path).map_err(|e| {
+        Error::new(
+            e.kind(),
+            format!("Could not read task list {}: {}", path, e),
+        )
+    })?;
+    String::from_utf8(crypto::open(path, bytes)?).map_err(|e| {
+        Error::new(
+            ErrorKind::InvalidData,
+            format!("{} is not valid UTF-8: {}", path, e),
+        )
+    })
+}
+
+/// Writes a sibling temporary file and renames it over `path`, so readers
+/// never see a half-written file.
+pub fn atomic_write<F>(path: &str, write_fn: F) -> Result<(), Error>
+where
+    F: FnOnce(&mut dyn Write) -> Result<(), Error>,
+{
+    let parent = Path::new(path).parent().unwrap_or_else(|| Path::new("."));
+
+    // Create a temporary file in the same directory
+    let temp_file = tempfile::Builder::new()
+        .prefix(".tasklist.tmp")
+        .tempfile_in(parent)
+        .map_err(|e| Error::new(e.kind(), format!("Failed to create temporary file: {}", e)))?;
+
+    // Get the file handle and lock it exclusively
+    let file = temp_file.as_file();
+    file.lock_exclusive()
+        .map_err(|e| Error::other(format!("Failed to lock temporary file: {}", e)))?;
+
+    // Write to the temporary file
+    {
+        let mut writer = BufWriter::new(file);
+        write_fn(&mut writer)?;
+        writer.flush()?;
+    } // Writer dropped here, releasing the file reference
+
+    // Unlock the file
+    temp_file.as_file().unlock().ok();
+
+    // Atomically replace the original file
+    temp_file
+        .persist(path)
+        .map_err(|e| Error::other(format!("Failed to persist temporary file: {}", e)))?;
+
+    Ok(())
+}
@@ -0,0 +1,57 @@
+use super::{Storage, persist, read_text};
+use crate::board::{Board, Entry};
+use crate::config::Config;
+use std::io::Error;
+
+/// TSV lines with tasks sorted by UUID, so tasks added on two branches land
+/// on unrelated lines and git can merge the file; `tsk merge-driver` handles
+/// the rest. There is no `#max_id=` counter line for both branches to bump:
+/// the next ID follows the highest task ID, and an ID that a plain merge
+/// left on two tasks is resolved on load (see `Board::parse_sorted`).
+#[derive(Debug)]
+pub struct SortedStorage {
+    path: String,
+    strict: bool,
+    config: Config,
+}
+
+impl SortedStorage {
+    pub fn new(path: &str, strict: bool, config: Config) -> Self {
+        SortedStorage {
+            path: path.to_string(),
+            strict,
+            config,
+        }
+    }
+}
+
+impl Storage for SortedStorage {
+    fn load(&self) -> Result<Board, Error> {
+        let content = read_text(&self.path)?;
+        let lines: Vec<String> = content.lines().map(str::to_string).collect();
+        let mut board = Board::parse_sorted(&self.path, &lines, self.strict)?;
+        // Lists read better in ID order; save puts them back in UUID order
+        board.entries.sort_by_key(|entry| match entry {
+            Entry::Raw(_) => None,
+            Entry::Task(task) => Some(task.id),
+        });
+        Ok(board)
+    }
+
+    fn save(&self, board: &Board) -> Result<(), Error> {
+        let mut tasks: Vec<_> = board.tasks().collect();
+        tasks.sort_by_key(|task| (task.uuid, task.id));
+        persist(&self.path, &self.config, |writer| {
+            // Comments stay on top, in their order
+            for entry in &board.entries {
+                if let Entry::Raw(line) = entry {
+                    writeln!(writer, "{}", line)?;
+                }
+            }
+            for task in tasks {
+                task.write_to(writer)?;
+            }
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,328 @@
+use super::{Storage, TaskQuery};
+use crate::backup;
+use crate::board::{self, Board, Entry};
+use crate::config::Config;
+use crate::dates::DAY_FORMAT;
+use crate::task::{Task, TaskId, now};
+use rusqlite::{Connection, OpenFlags, Transaction, TransactionBehavior, params};
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+
+/// Every board entry is one row, in board order. `line` holds the entry in
+/// the TSV encoding, so both backends read and write tasks the same way; the
+/// other columns only exist to be indexed.
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS meta (
+        key TEXT PRIMARY KEY,
+        value TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS entries (
+        position INTEGER PRIMARY KEY,
+        line TEXT NOT NULL,
+        id INTEGER UNIQUE,
+        status TEXT,
+        date TEXT,
+        due TEXT,
+        wait TEXT
+    );
+    CREATE INDEX IF NOT EXISTS entries_status ON entries (status);
+    CREATE INDEX IF NOT EXISTS entries_date ON entries (date);
+    CREATE INDEX IF NOT EXISTS entries_due ON entries (due);
+    CREATE INDEX IF NOT EXISTS entries_wait ON entries (wait);
+";
+
+/// A board in a single SQLite database file. Editing or deleting one task
+/// only touches its row; whole-board updates read every row and run in one
+/// transaction, see `write_board` for which rows they write.
+#[derive(Debug)]
+pub struct SqliteStorage {
+    path: String,
+    config: Config,
+}
+
+impl SqliteStorage {
+    pub fn new(path: &str, config: Config) -> Self {
+        SqliteStorage {
+            path: path.to_string(),
+            config,
+        }
+    }
+
+    fn open(&self, create: bool) -> Result<Connection, Error> {
+        let mut flags = OpenFlags::SQLITE_OPEN_READ_WRITE;
+        if create {
+            flags |= OpenFlags::SQLITE_OPEN_CREATE;
+        } else if !Path::new(&self.path).exists() {
+            return Err(Error::new(
+                ErrorKind::NotFound,
+                format!("Could not read task list {}: no such file", self.path),
+            ));
+        }
+        let conn =
+            Connection::open_with_flags(&self.path, flags).map_err(|e| db_error(&self.path, e))?;
+        conn.execute_batch(SCHEMA)
+            .map_err(|e| db_error(&self.path, e))?;
+        Ok(conn)
+    }
+
+    /// IMMEDIATE takes the write lock up front, so concurrent updates queue
+    /// instead of overwriting each other.
+    fn begin<'a>(&self, conn: &'a mut Connection) -> Result<Transaction<'a>, Error> {
+        conn.transaction_with_behavior(TransactionBehavior::Immediate)
+            .map_err(|e| db_error(&self.path, e))
+    }
+
+    fn backup(&self) -> Result<(), Error> {
+        if self.config.backups > 0 {
+            let now = now();
+            backup::create(&self.path, now)?;
+            backup::prune(
+                &self.path,
+                self.config.backups,
+                self.config.backup_days,
+                now,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Storage for SqliteStorage {
+    fn load(&self) -> Result<Board, Error> {
+        let conn = self.open(false)?;
+        read_board(&conn).map_err(|e| db_error(&self.path, e))
+    }
+
+    fn save(&self, board: &Board) -> Result<(), Error> {
+        self.backup()?;
+        let mut conn = self.open(true)?;
+        let tx = conn.transaction().map_err(|e| db_error(&self.path, e))?;
+        write_board(&tx, board, None).map_err(|e| db_error(&self.path, e))?;
+        tx.commit().map_err(|e| db_error(&self.path, e))
+    }
+
+    fn update(&self, edit: &mut dyn FnMut(&mut Board) -> Result<(), Error>) -> Result<(), Error> {
+        self.backup()?;
+        let mut conn = self.open(true)?;
+        let tx = self.begin(&mut conn)?;
+        let before = read_board(&tx).map_err(|e| db_error(&self.path, e))?;
+        let mut board = before.clone();
+        edit(&mut board)?;
+        write_board(&tx, &board, Some(&before)).map_err(|e| db_error(&self.path, e))?;
+        tx.commit().map_err(|e| db_error(&self.path, e))
+    }
+
+    fn update_task(
+        &self,
+        id: TaskId,
+        edit: &mut dyn FnMut(&mut Task) -> Result<(), Error>,
+    ) -> Result<Task, Error> {
+        self.backup()?;
+        let mut conn = self.open(true)?;
+        let tx = self.begin(&mut conn)?;
+        let (position, mut task) = find_row(&tx, id)
+            .map_err(|e| db_error(&self.path, e))?
+            .ok_or_else(|| board::not_found(id))?;
+        edit(&mut task)?;
+        if task.id != id {
+            return Err(Error::other(format!(
+                "Task {} can't be renumbered to {} on its own",
+                id, task.id
+            )));
+        }
+        let line = task.to_file_string();
+        write_row(&tx, position, &line, Some(&task)).map_err(|e| db_error(&self.path, e))?;
+        tx.commit().map_err(|e| db_error(&self.path, e))?;
+        Ok(task)
+    }
+
+    fn delete_task(&self, id: TaskId) -> Result<Task, Error> {
+        self.backup()?;
+        let mut conn = self.open(true)?;
+        let tx = self.begin(&mut conn)?;
+        let (position, task) = find_row(&tx, id)
+            .map_err(|e| db_error(&self.path, e))?
+            .ok_or_else(|| board::not_found(id))?;
+        // Close the gap in two steps: moving the later rows to negative
+        // positions first keeps each one clear of the primary key it's
+        // moving onto
+        tx.execute("DELETE FROM entries WHERE position = ?1", [position])
+            .and_then(|_| {
+                tx.execute(
+                    "UPDATE entries SET position = -position WHERE position > ?1",
+                    [position],
+                )
+            })
+            .and_then(|_| {
+                tx.execute(
+                    "UPDATE entries SET position = -position - 1 WHERE position < 0",
+                    [],
+                )
+            })
+            .map_err(|e| db_error(&self.path, e))?;
+        tx.commit().map_err(|e| db_error(&self.path, e))?;
+        Ok(task)
+    }
+
+    fn find(&self, id: TaskId) -> Result<Option<Task>, Error> {
+        let conn = self.open(false)?;
+        Ok(find_row(&conn, id)
+            .map_err(|e| db_error(&self.path, e))?
+            .map(|(_, task)| task))
+    }
+
+    fn query(&self, query: &TaskQuery) -> Result<Vec<Task>, Error> {
+        let conn = self.open(false)?;
+        let status = query.status.map(|status| status.as_label());
+        let due_by = query.due_by.map(|due| due.format(DAY_FORMAT).to_string());
+        let mut statement = conn
+            .prepare(
+                "SELECT line FROM entries
+                 WHERE id IS NOT NULL
+                   AND (?1 IS NULL OR status = ?1)
+                   AND (?2 IS NULL OR due <= ?2)
+                 ORDER BY position",
+            )
+            .map_err(|e| db_error(&self.path, e))?;
+        let lines = statement
+            .query_map(params![status, due_by], |row| row.get::<_, String>(0))
+            .and_then(|rows| rows.collect::<Result<Vec<String>, _>>())
+            .map_err(|e| db_error(&self.path, e))?;
+        Ok(lines
+            .iter()
+            .filter_map(|line| Task::from_file_line(line))
+            .collect())
+    }
+}
+
+fn read_board(conn: &Connection) -> rusqlite::Result<Board> {
+    let max_id: Option<String> = conn
+        .query_row("SELECT value FROM meta WHERE key = 'max_id'", [], |row| {
+            row.get(0)
+        })
+        .map(Some)
+        .or_else(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => Ok(None),
+            e => Err(e),
+        })?;
+    let mut board = Board {
+        max_id: max_id.and_then(|value| value.parse().ok()).unwrap_or(0),
+        entries: Vec::new(),
+    };
+
+    let mut statement = conn.prepare("SELECT line FROM entries ORDER BY position")?;
+    for line in statement.query_map([], |row| row.get::<_, String>(0))? {
+        let line = line?;
+        board.entries.push(match Task::from_file_line(&line) {
+            Some(task) => {
+                board.max_id = board.max_id.max(task.id);
+                Entry::Task(task)
+            },
+            None => Entry::Raw(line),
+        });
+    }
+    Ok(board)
+}
+
+/// Writes `board`, touching only the rows that differ from `before` when
+/// entries were only changed or appended. A whole-board update that removes
+/// entries rewrites the table; single deletes go through `delete_task`.
+fn write_board(tx: &Transaction, board: &Board, before: Option<&Board>) -> rusqlite::Result<()> {
+    tx.execute(
+        "INSERT INTO meta (key, value) VALUES ('max_id', ?1)
+         ON CONFLICT (key) DO UPDATE SET value = excluded.value",
+        [board.max_id.to_string()],
+    )?;
+
+    let old: Vec<String> = before
+        .map(|before| before.entries.iter().map(entry_line).collect())
+        .unwrap_or_default();
+    let new: Vec<String> = board.entries.iter().map(entry_line).collect();
+    let incremental = before.is_some() && new.len() >= old.len();
+    if incremental {
+        // Rows whose line changed are rewritten below; clear their IDs first
+        // so a renumbering can't trip the UNIQUE constraint midway
+        for (position, (old, new)) in old.iter().zip(&new).enumerate() {
+            if old != new {
+                tx.execute(
+                    "UPDATE entries SET id = NULL WHERE position = ?1",
+                    [position as i64],
+                )?;
+            }
+        }
+    } else {
+        tx.execute("DELETE FROM entries", [])?;
+    }
+
+    for (position, (entry, line)) in board.entries.iter().zip(&new).enumerate() {
+        if incremental && old.get(position) == Some(line) {
+            continue;
+        }
+        let task = match entry {
+            Entry::Task(task) => Some(task),
+            Entry::Raw(_) => None,
+        };
+        write_row(tx, position as i64, line, task)?;
+    }
+    Ok(())
+}
+
+/// Inserts or replaces the row at `position`.
+fn write_row(
+    tx: &Transaction,
+    position: i64,
+    line: &str,
+    task: Option<&Task>,
+) -> rusqlite::Result<()> {
+    let day =
+        |date: Option<chrono::NaiveDate>| date.map(|date| date.format(DAY_FORMAT).to_string());
+    tx.prepare_cached(
+        "INSERT INTO entries (position, line, id, status, date, due, wait)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+         ON CONFLICT (position) DO UPDATE SET
+             line = excluded.line, id = excluded.id, status = excluded.status,
+             date = excluded.date, due = excluded.due, wait = excluded.wait",
+    )?
+    .execute(params![
+        position,
+        line,
+        task.map(|task| sql_id(task.id)).transpose()?,
+        task.map(|task| task.status.as_label()),
+        task.map(|task| task.date.clone()),
+        task.and_then(|task| day(task.due)),
+        task.and_then(|task| day(task.wait)),
+    ])?;
+    Ok(())
+}
+
+/// The position and task of the row holding task `id`.
+fn find_row(conn: &Connection, id: TaskId) -> rusqlite::Result<Option<(i64, Task)>> {
+    let row: Option<(i64, String)> = conn
+        .query_row(
+            "SELECT position, line FROM entries WHERE id = ?1",
+            [sql_id(id)?],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map(Some)
+        .or_else(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => Ok(None),
+            e => Err(e),
+        })?;
+    Ok(row.and_then(|(position, line)| Some((position, Task::from_file_line(&line)?))))
+}
+
+/// SQLite integers are signed, so IDs past `i64::MAX` can't be stored.
+fn sql_id(id: TaskId) -> rusqlite::Result<i64> {
+    i64::try_from(id).map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))
+}
+
+fn entry_line(entry: &Entry) -> String {
+    match entry {
+        Entry::Task(task) => task.to_file_string(),
+        Entry::Raw(line) => line.clone(),
+    }
+}
+
+fn db_error(path: &str, e: rusqlite::Error) -> Error {
+    Error::other(format!("SQLite error in {}: {}", path, e))
+}
@@ -0,0 +1,365 @@
+use super::{Storage, persist, read_text};
+use crate::board::{Board, Entry};
+use crate::config::Config;
+use crate::dates::DAY_FORMAT;
+use crate::task::{Comment, Interval, Link, Status, TIME_FORMAT, Task, TaskId};
+use chrono::{NaiveDate, NaiveDateTime};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::io::{Error, ErrorKind};
+
+/// The human-editable formats. They share one schema, see `FileBoard`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Json,
+    Toml,
+    Yaml,
+}
+
+/// A whole board. Fields are declared in alphabetical order and empty ones
+/// are left out, so the same board always serializes to the same bytes and
+/// an edit only touches the lines of the task it changes.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FileBoard {
+    /// Highest ID ever handed out. Defaults to the highest task ID.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    max_id: Option<TaskId>,
+    /// Lines of a TSV board that weren't tasks, e.g. `#` comments.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    raw: Vec<FileRaw>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tasks: Vec<FileTask>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FileTask {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    assignee: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    comments: Vec<FileComment>,
+    /// When it was last marked done, `YYYY-MM-DD HH:MM:SS`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    completed: Option<String>,
+    /// Last update, `YYYY-MM-DD HH:MM`.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    date: String,
+    description: String,
+    /// `YYYY-MM-DD`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    due: Option<String>,
+    /// `3pt` or a duration like `1h30m`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    estimate: Option<String>,
+    /// Fields written by other tools, kept as `key=value`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    extra: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    files: Vec<String>,
+    id: TaskId,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    notes: String,
+    /// An RRULE such as `FREQ=WEEKLY;BYDAY=MO`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    recur: Option<String>,
+    /// `not_started`, `in_progress` or `done`.
+    status: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    time: Vec<FileInterval>,
+    /// Start of the running timer, `YYYY-MM-DD HH:MM:SS`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    timer: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    urls: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    uuid: Option<String>,
+    /// Hidden until this day, `YYYY-MM-DD`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    wait: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+enum FileRaw {
+    Placed(PlacedRaw),
+    /// Written before raw lines kept their place; they go after every task.
+    Line(String),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PlacedRaw {
+    /// How many tasks come before the line.
+    after: usize,
+    line: String,
+}
+
+impl FileRaw {
+    fn after(&self) -> usize {
+        match self {
+            FileRaw::Placed(raw) => raw.after,
+            FileRaw::Line(_) => usize::MAX,
+        }
+    }
+
+    fn line(&self) -> &str {
+        match self {
+            FileRaw::Placed(raw) => &raw.line,
+            FileRaw::Line(line) => line,
+        }
+    }
+}
+
+/// Text that a tsv board writes as-is must not break its line into fields.
+fn check_text(field: &str, value: &str) -> Result<(), String> {
+    match value.chars().find(|c| c.is_control()) {
+        Some(c) => Err(format!("{} contains the control character {:?}", field, c)),
+        None => Ok(()),
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FileComment {
+    date: String,
+    text: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FileInterval {
+    end: String,
+    start: String,
+}
+
+impl From<&Task> for FileTask {
+    fn from(task: &Task) -> Self {
+        let links = |want_url: bool| {
+            task.links
+                .iter()
+                .filter(|link| matches!(link, Link::Url(_)) == want_url)
+                .map(|link| link.target().to_string())
+                .collect()
+        };
+        FileTask {
+            assignee: task.assignee.clone(),
+            comments: task
+                .comments
+                .iter()
+                .map(|comment| FileComment {
+                    date: comment.date.clone(),
+                    text: comment.text.clone(),
+                })
+                .collect(),
+            completed: task.completed.map(|at| at.format(TIME_FORMAT).to_string()),
+            date: task.date.clone(),
+            description: task.description.clone(),
+            due: task.due.map(|due| due.format(DAY_FORMAT).to_string()),
+            estimate: task.estimate.map(|estimate| estimate.to_string()),
+            extra: task.extra.clone(),
+            files: links(false),
+            id: task.id,
+            notes: task.notes.clone(),
+            recur: task.recurrence.as_ref().map(|recur| recur.to_string()),
+            status: task
+                .status
+                .to_possible_value()
+                .map(|value| value.get_name().to_string())
+                .unwrap_or_default(),
+            time: task
+                .time_log
+                .iter()
+                .map(|interval| FileInterval {
+                    end: interval.end.format(TIME_FORMAT).to_string(),
+                    start: interval.start.format(TIME_FORMAT).to_string(),
+                })
+                .collect(),
+            timer: task
+                .timer
+                .map(|start| start.format(TIME_FORMAT).to_string()),
+            urls: links(true),
+            uuid: task.uuid.map(|uuid| uuid.to_string()),
+            wait: task.wait.map(|wait| wait.format(DAY_FORMAT).to_string()),
+        }
+    }
+}
+
+impl FileTask {
+    fn into_task(self) -> Result<Task, String> {
+        let status = <Status as ValueEnum>::from_str(&self.status, true)
+            .map_err(|_| format!("unknown status `{}`", self.status))?;
+        let day = |value: Option<String>, field: &str| {
+            value
+                .map(|value| {
+                    NaiveDate::parse_from_str(&value, DAY_FORMAT)
+                        .map_err(|_| format!("bad {} date `{}`", field, value))
+                })
+                .transpose()
+        };
+        let time = |value: &str| {
+            NaiveDateTime::parse_from_str(value, TIME_FORMAT)
+                .map_err(|_| format!("bad timestamp `{}`", value))
+        };
+
+        check_text("description", &self.description)?;
+        check_text("date", &self.date)?;
+        for comment in &self.comments {
+            check_text("comment date", &comment.date)?;
+        }
+        for field in &self.extra {
+            check_text("extra field", field)?;
+        }
+
+        let mut task = Task::new(self.id, status, self.description, self.date);
+        task.uuid = self
+            .uuid
+            .map(|uuid| uuid.parse().map_err(|_| format!("bad uuid `{}`", uuid)))
+            .transpose()?;
+        task.estimate = self.estimate.map(|estimate| estimate.parse()).transpose()?;
+        task.due = day(self.due, "due")?;
+        task.wait = day(self.wait, "wait")?;
+        task.assignee = self.assignee;
+        task.links = self
+            .urls
+            .into_iter()
+            .map(Link::Url)
+            .chain(self.files.into_iter().map(Link::File))
+            .collect();
+        task.recurrence = self.recur.map(|recur| recur.parse()).transpose()?;
+        task.notes = self.notes;
+        task.comments = self
+            .comments
+            .into_iter()
+            .map(|comment| Comment {
+                date: comment.date,
+                text: comment.text,
+            })
+            .collect();
+        task.time_log = self
+            .time
+            .iter()
+            .map(|interval| {
+                Ok(Interval {
+                    start: time(&interval.start)?,
+                    end: time(&interval.end)?,
+                })
+            })
+            .collect::<Result<_, String>>()?;
+        task.timer = self.timer.as_deref().map(time).transpose()?;
+        task.completed = self.completed.as_deref().map(time).transpose()?;
+        task.extra = self.extra;
+        Ok(task)
+    }
+}
+
+/// A board stored as a single JSON, TOML or YAML document.
+#[derive(Debug)]
+pub struct StructuredStorage {
+    path: String,
+    format: Format,
+    config: Config,
+}
+
+impl StructuredStorage {
+    pub fn new(path: &str, format: Format, config: Config) -> Self {
+        StructuredStorage {
+            path: path.to_string(),
+            format,
+            config,
+        }
+    }
+
+    fn invalid(&self, message: impl std::fmt::Display) -> Error {
+        Error::new(
+            ErrorKind::InvalidData,
+            format!("{}: {}", self.path, message),
+        )
+    }
+}
+
+impl Storage for StructuredStorage {
+    fn load(&self) -> Result<Board, Error> {
+        let content = read_text(&self.path)?;
+        let file: FileBoard = match self.format {
+            Format::Json => serde_json::from_str(&content).map_err(|e| self.invalid(e))?,
+            Format::Toml => toml::from_str(&content).map_err(|e| self.invalid(e))?,
+            Format::Yaml => serde_yaml_ng::from_str(&content).map_err(|e| self.invalid(e))?,
+        };
+
+        let mut board = Board::default();
+        let mut seen = HashSet::new();
+        // Raw lines may hold tabs (a tsv line too short to be a task), not line breaks
+        if let Some(raw) = file
+            .raw
+            .iter()
+            .find(|raw| raw.line().contains(['\n', '\r']))
+        {
+            return Err(self.invalid(format!("raw line {:?} spans several lines", raw.line())));
+        }
+        // Stable, so lines at the same place keep their order
+        let mut raw = file.raw;
+        raw.sort_by_key(FileRaw::after);
+        let mut raw = raw.into_iter().peekable();
+        for (index, task) in file.tasks.into_iter().enumerate() {
+            while let Some(line) = raw.next_if(|raw| raw.after() <= index) {
+                board.entries.push(Entry::Raw(line.line().to_string()));
+            }
+            // Tasks are named by position too, as their ID may be the problem
+            let name = format!("task #{} ({:?})", index + 1, task.description);
+            // IDs are positive, as `parse_id` requires of tsv boards
+            if task.id == 0 {
+                return Err(self.invalid(format!("{}: invalid task ID `{}`", name, task.id)));
+            }
+            let task = task
+                .into_task()
+                .map_err(|e| self.invalid(format!("{}: {}", name, e)))?;
+            if !seen.insert(task.id) {
+                return Err(self.invalid(format!("{}: duplicate task ID {}", name, task.id)));
+            }
+            board.max_id = board.max_id.max(task.id);
+            board.entries.push(Entry::Task(task));
+        }
+        if let Some(max_id) = file.max_id {
+            if max_id < board.max_id {
+                return Err(self.invalid(format!(
+                    "max_id = {} is below the highest task ID {}, new tasks would reuse IDs",
+                    max_id, board.max_id
+                )));
+            }
+            board.max_id = max_id;
+        }
+        board
+            .entries
+            .extend(raw.map(|line| Entry::Raw(line.line().to_string())));
+        Ok(board)
+    }
+
+    fn save(&self, board: &Board) -> Result<(), Error> {
+        let mut file = FileBoard {
+            max_id: Some(board.max_id),
+            ..FileBoard::default()
+        };
+        for entry in &board.entries {
+            match entry {
+                Entry::Task(task) => file.tasks.push(FileTask::from(task)),
+                Entry::Raw(line) => file.raw.push(FileRaw::Placed(PlacedRaw {
+                    after: file.tasks.len(),
+                    line: line.clone(),
+                })),
+            }
+        }
+        let mut content = match self.format {
+            Format::Json => serde_json::to_string_pretty(&file).map_err(Error::other)?,
+            Format::Toml => toml::to_string(&file).map_err(Error::other)?,
+            Format::Yaml => serde_yaml_ng::to_string(&file).map_err(Error::other)?,
+        };
+        if !content.ends_with('\n') {
+            content.push('\n');
+        }
+        persist(&self.path, &self.config, |writer| {
+            writer.write_all(content.as_bytes())
+        })
+    }
+}
@@ -0,0 +1,161 @@
+use super::{Storage, persist, read_text};
+use crate::board::{Board, Entry};
+use crate::config::Config;
+use crate::task::{SEP, TaskId};
+use colored::Colorize;
+use std::io::{Error, ErrorKind};
+
+/// The original format: a `#max_id=` header, then one tab-separated line
+/// per task. Comments and unparseable lines are kept in place.
+///
+/// The header also carries a CRC-32 of everything below it and the number
+/// of tasks, e.g. `#max_id=5\tchecksum=1a2b3c4d\ttasks=4`, so a file changed
+/// behind tsk's back (a truncating sync tool, a bad merge) is noticed on
+/// load. Files without them are accepted and get them on the next write.
+#[derive(Debug)]
+pub struct TsvStorage {
+    path: String,
+    strict: bool,
+    config: Config,
+}
+
+/// Whether the body of a file still matches its header.
+#[derive(Debug, PartialEq)]
+pub enum Integrity {
+    /// Written before checksums, or by hand without one.
+    Unchecked,
+    Intact,
+    /// Describes what looks wrong.
+    Mismatch(String),
+}
+
+impl TsvStorage {
+    pub fn new(path: &str, strict: bool, config: Config) -> Self {
+        TsvStorage {
+            path: path.to_string(),
+            strict,
+            config,
+        }
+    }
+
+    /// Reads the board and checks it against its header.
+    pub fn read(&self) -> Result<(Board, Integrity), Error> {
+        let content = read_text(&self.path)?;
+        let lines: Vec<String> = content.lines().map(str::to_string).collect();
+        let board = Board::parse(&self.path, &lines, self.strict)?;
+        let integrity = self.check(&content, &board);
+        Ok((board, integrity))
+    }
+
+    fn check(&self, content: &str, board: &Board) -> Integrity {
+        let (header, body) = content.split_once('\n').unwrap_or((content, ""));
+        let field = |name: &str| {
+            header
+                .split(SEP)
+                .find_map(|field| field.strip_prefix(name)?.strip_prefix('='))
+        };
+        let Some(expected) = field("checksum") else {
+            return Integrity::Unchecked;
+        };
+        if expected == checksum(body) {
+            return Integrity::Intact;
+        }
+        let tasks = board.tasks().count();
+        let message = match field("tasks").and_then(|count| count.parse::<usize>().ok()) {
+            Some(recorded) if recorded > tasks => format!(
+                "{} has {} task(s) fewer than tsk last wrote: it looks truncated or damaged. \
+                 Restore it with `tsk backup restore 1`, or accept it with `tsk rehash`",
+                self.path,
+                recorded - tasks
+            ),
+            _ => format!(
+                "{} was changed outside tsk (checksum mismatch). If you edited it by hand, \
+                 run `tsk rehash`; otherwise see `tsk backup list`",
+                self.path
+            ),
+        };
+        Integrity::Mismatch(message)
+    }
+
+    /// Recomputes the header of a file that was edited by hand, keeping the
+    /// body byte for byte. Returns the number of tasks.
+    pub fn rehash(&self) -> Result<usize, Error> {
+        let content = read_text(&self.path)?;
+        let lines: Vec<String> = content.lines().map(str::to_string).collect();
+        let board = Board::parse(&self.path, &lines, self.strict)?;
+        let body = match content.split_once('\n') {
+            Some((header, body)) if header.starts_with("#max_id=") => body.to_string(),
+            None if content.starts_with("#max_id=") => String::new(),
+            _ => content.clone(),
+        };
+        let tasks = board.tasks().count();
+        persist(&self.path, &self.config, |writer| {
+            writeln!(writer, "{}", header(board.max_id, &body, tasks))?;
+            writer.write_all(body.as_bytes())
+        })?;
+        Ok(tasks)
+    }
+}
+
+impl Storage for TsvStorage {
+    fn load(&self) -> Result<Board, Error> {
+        let (board, integrity) = self.read()?;
+        if let Integrity::Mismatch(message) = integrity {
+            eprintln!("{} {}", "Warning:".yellow(), message);
+        }
+        Ok(board)
+    }
+
+    fn save(&self, board: &Board) -> Result<(), Error> {
+        let mut body = String::new();
+        for entry in &board.entries {
+            match entry {
+                Entry::Task(task) => body.push_str(&task.to_file_string()),
+                Entry::Raw(line) => body.push_str(line),
+            }
+            body.push('\n');
+        }
+        persist(&self.path, &self.config, |writer| {
+            writeln!(
+                writer,
+                "{}",
+                header(board.max_id, &body, board.tasks().count())
+            )?;
+            writer.write_all(body.as_bytes())
+        })
+    }
+
+    fn update(&self, edit: &mut dyn FnMut(&mut Board) -> Result<(), Error>) -> Result<(), Error> {
+        let mut board = match self.read() {
+            Ok((_, Integrity::Mismatch(message))) if self.strict => {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("{} (strict mode: not writing)", message),
+                ));
+            },
+            Ok((board, integrity)) => {
+                if let Integrity::Mismatch(message) = integrity {
+                    eprintln!("{} {}", "Warning:".yellow(), message);
+                }
+                board
+            },
+            Err(e) if e.kind() == ErrorKind::NotFound => Board::default(),
+            Err(e) => return Err(e),
+        };
+        edit(&mut board)?;
+        self.save(&board)
+    }
+}
+
+fn header(max_id: TaskId, body: &str, tasks: usize) -> String {
+    format!(
+        "#max_id={}{SEP}checksum={}{SEP}tasks={}",
+        max_id,
+        checksum(body),
+        tasks
+    )
+}
+
+fn checksum(body: &str) -> String {
+    format!("{:08x}", crc32fast::hash(body.as_bytes()))
+}
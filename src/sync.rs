@@ -0,0 +1,288 @@
+use crate::board::Board;
+use crate::config::sidecar_dir;
+use crate::crypto;
+use crate::storage::atomic_write;
+use crate::task::{SEP, Status, Task, TaskId, escape, timestamp, unescape};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+/// The marker that ends a synced checklist item; Markdown renders it as nothing.
+const MARKER_START: &str = "<!-- tsk:";
+const MARKER_END: &str = " -->";
+
+/// A `- [ ]` / `- [x]` line of a Markdown file.
+#[derive(Debug, Clone, PartialEq)]
+struct Item {
+    /// Everything before the checkbox: indentation and bullet.
+    prefix: String,
+    checked: bool,
+    text: String,
+    uuid: Option<Uuid>,
+}
+
+impl Item {
+    fn parse(line: &str) -> Option<Item> {
+        let indent = line.len() - line.trim_start().len();
+        let rest = &line[indent..];
+        let bullet = rest
+            .chars()
+            .next()
+            .filter(|c| matches!(c, '-' | '*' | '+'))?;
+        let rest = rest[1..].strip_prefix(' ')?;
+        let checked = match rest.get(..3)? {
+            "[ ]" => false,
+            "[x]" | "[X]" => true,
+            _ => return None,
+        };
+        let mut text = rest[3..].trim();
+        let mut uuid = None;
+        if let Some(start) = text.rfind(MARKER_START)
+            && let Some(marker) = text[start + MARKER_START.len()..].strip_suffix(MARKER_END)
+        {
+            uuid = Uuid::parse_str(marker.trim()).ok();
+            text = text[..start].trim_end();
+        }
+        Some(Item {
+            prefix: format!("{}{}", &line[..indent], bullet),
+            checked,
+            text: text.split_whitespace().collect::<Vec<_>>().join(" "),
+            uuid,
+        })
+    }
+
+    fn render(&self) -> String {
+        let mut line = format!(
+            "{} [{}] {}",
+            self.prefix,
+            if self.checked { "x" } else { " " },
+            self.text
+        );
+        if let Some(uuid) = self.uuid {
+            line.push_str(&format!(" {}{}{}", MARKER_START, uuid, MARKER_END));
+        }
+        line
+    }
+}
+
+/// An item as it was after the last sync, to tell which side changed since.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Synced {
+    pub checked: bool,
+    pub description: String,
+}
+
+/// The result of syncing one file: its new lines, what to remember for next
+/// time and a line per change.
+#[derive(Debug, Default)]
+pub struct SyncOutcome {
+    pub lines: Vec<String>,
+    pub state: HashMap<Uuid, Synced>,
+    pub report: Vec<String>,
+}
+
+/// Syncs the checklist items in `lines` with `board`. New items become
+/// tasks and get a marker; for the rest, whichever side changed since the
+/// last sync (`state`) wins, the file when both did. Items whose task was
+/// deleted are dropped from the file; tasks whose item was deleted stay.
+pub fn sync(
+    board: &mut Board,
+    lines: &[String],
+    state: &HashMap<Uuid, Synced>,
+) -> Result<SyncOutcome, Error> {
+    let ids: HashMap<Uuid, TaskId> = board
+        .tasks()
+        .filter_map(|task| Some((task.uuid?, task.id)))
+        .collect();
+    let mut outcome = SyncOutcome::default();
+    let mut seen = HashSet::new();
+
+    for line in lines {
+        let Some(mut item) = Item::parse(line).filter(|item| !item.text.is_empty()) else {
+            outcome.lines.push(line.clone());
+            continue;
+        };
+        // A copied line carries the same marker; the copy is a new task
+        if item.uuid.is_some_and(|uuid| !seen.insert(uuid)) {
+            item.uuid = None;
+        }
+
+        match item
+            .uuid
+            .and_then(|uuid| ids.get(&uuid).map(|id| (uuid, *id)))
+        {
+            Some((uuid, id)) => {
+                let task = board.task_mut(id)?;
+                sync_item(&mut item, task, state.get(&uuid), &mut outcome.report);
+            },
+            None if item.uuid.is_some_and(|uuid| state.contains_key(&uuid)) => {
+                outcome.report.push(format!(
+                    "Removed from the file (deleted on the board): {}",
+                    item.text
+                ));
+                continue;
+            },
+            None => {
+                let status = if item.checked {
+                    Status::Done
+                } else {
+                    Status::NotStarted
+                };
+                let mut task = Task::new(0, status, item.text.clone(), timestamp());
+                // Keep a marker from another board, so the task stays linked
+                task.uuid = item.uuid.or(task.uuid);
+                item.uuid = task.uuid;
+                let id = board.push(task)?;
+                outcome
+                    .report
+                    .push(format!("Added task {}: {}", id, item.text));
+            },
+        }
+
+        if let Some(uuid) = item.uuid {
+            outcome.state.insert(
+                uuid,
+                Synced {
+                    checked: item.checked,
+                    description: item.text.clone(),
+                },
+            );
+        }
+        outcome.lines.push(item.render());
+    }
+
+    for (uuid, synced) in state {
+        if !outcome.state.contains_key(uuid)
+            && let Some(id) = ids.get(uuid)
+        {
+            outcome.report.push(format!(
+                "No longer in the file, left on the board: task {} ({})",
+                id, synced.description
+            ));
+        }
+    }
+    Ok(outcome)
+}
+
+fn sync_item(item: &mut Item, task: &mut Task, base: Option<&Synced>, report: &mut Vec<String>) {
+    // Without a record of the last sync, the file's side counts as the edit
+    let done = task.status == Status::Done;
+    let base_checked = base.map_or(done, |base| base.checked);
+    if item.checked != base_checked {
+        if item.checked != done {
+            task.status = if item.checked {
+                Status::Done
+            } else {
+                Status::NotStarted
+            };
+            task.date = timestamp();
+            report.push(format!(
+                "Task {} is now {}: {}",
+                task.id, task.status, task.description
+            ));
+        }
+    } else if item.checked != done {
+        item.checked = done;
+        report.push(format!(
+            "{} in the file: {}",
+            if done { "Ticked" } else { "Unticked" },
+            item.text
+        ));
+    }
+
+    let base_description = base.map_or(&task.description, |base| &base.description);
+    if item.text != *base_description {
+        if item.text != task.description {
+            report.push(format!("Task {} renamed to: {}", task.id, item.text));
+            task.description = item.text.clone();
+            task.date = timestamp();
+        }
+    } else if item.text != task.description {
+        report.push(format!("Renamed in the file: {}", task.description));
+        item.text = task.description.clone();
+    }
+}
+
+/// Where a board remembers its synced files: `<tasklist>.d/markdown-sync`,
+/// a `<file>\t<uuid>\t<0|1>\t<description>` line per item. The file of an
+/// encrypted board is encrypted as a whole, like its history.
+pub fn state_path(tasklist_path: &str) -> PathBuf {
+    sidecar_dir(tasklist_path).join("markdown-sync")
+}
+
+/// The state of `file` after its last sync; empty if it was never synced.
+pub fn load_state(tasklist_path: &str, file: &str) -> Result<HashMap<Uuid, Synced>, Error> {
+    let content = read_state(&state_path(tasklist_path))?;
+    Ok(content
+        .lines()
+        .filter_map(|line| {
+            let [name, uuid, checked, description] =
+                line.split(SEP).collect::<Vec<_>>().try_into().ok()?;
+            (unescape(name) == file).then_some(())?;
+            Some((
+                Uuid::parse_str(uuid).ok()?,
+                Synced {
+                    checked: checked == "1",
+                    description: unescape(description),
+                },
+            ))
+        })
+        .collect())
+}
+
+/// The state file's contents, decrypted; empty if there is none yet.
+fn read_state(path: &Path) -> Result<String, Error> {
+    match fs::read(path) {
+        Ok(bytes) => crypto::open(&path.display().to_string(), bytes)
+            .map(|bytes| String::from_utf8_lossy(&bytes).into_owned()),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(String::new()),
+        Err(e) => Err(Error::new(
+            e.kind(),
+            format!("Could not read {}: {}", path.display(), e),
+        )),
+    }
+}
+
+/// Replaces what is remembered about `file`, keeping other files' lines.
+pub fn save_state(
+    tasklist_path: &str,
+    encrypted: bool,
+    file: &str,
+    state: &HashMap<Uuid, Synced>,
+) -> Result<(), Error> {
+    let path = state_path(tasklist_path);
+    let mut content = String::new();
+    for line in read_state(&path)?.lines().filter(|line| {
+        line.split(SEP)
+            .next()
+            .is_some_and(|name| unescape(name) != file)
+    }) {
+        content.push_str(line);
+        content.push('\n');
+    }
+    let mut items: Vec<(&Uuid, &Synced)> = state.iter().collect();
+    items.sort_by_key(|(uuid, _)| **uuid);
+    for (uuid, synced) in items {
+        let checked = if synced.checked { "1" } else { "0" };
+        content.push_str(
+            &[
+                escape(file).as_str(),
+                &uuid.to_string(),
+                checked,
+                &escape(&synced.description),
+            ]
+            .join(SEP),
+        );
+        content.push('\n');
+    }
+
+    let content = if encrypted {
+        crypto::seal(content.as_bytes())?
+    } else {
+        content.into_bytes()
+    };
+    fs::create_dir_all(sidecar_dir(tasklist_path))?;
+    atomic_write(&path.to_string_lossy(), |writer| writer.write_all(&content))
+}
@@ -1,7 +1,13 @@
+use crate::dates::DAY_FORMAT;
+use crate::recurrence::{Frequency, Recurrence};
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime};
 use clap::ValueEnum;
+use std::collections::BTreeMap;
 use std::fmt;
 use std::io::{self, Write};
+use std::str::FromStr;
 use tabled::Tabled;
+use uuid::Uuid;
 #[derive(Debug, Clone, Copy, ValueEnum, Eq, Hash, PartialEq, Tabled)]
 pub enum Status {
     #[value(name = "not_started", alias = "ns")]
@@ -15,7 +21,27 @@ pub enum Status {
     Done,
 }
 
+/// Short numeric handle of a task; IDs start at 1 and are never reused.
+pub type TaskId = u64;
+
 pub const SEP: &str = "\t";
+pub const DATE_FORMAT: &str = "%Y-%m-%d %H:%M";
+/// Timer stamps keep seconds so short intervals add up correctly.
+pub const TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+pub fn timestamp() -> String {
+    chrono::Local::now().format(DATE_FORMAT).to_string()
+}
+
+pub fn now() -> NaiveDateTime {
+    chrono::Local::now().naive_local()
+}
+
+/// Formats a duration as `1h 05m`.
+pub fn format_duration(duration: Duration) -> String {
+    let minutes = duration.num_minutes().max(0);
+    format!("{}h {:02}m", minutes / 60, minutes % 60)
+}
 
 #[allow(dead_code)]
 impl Status {
@@ -24,11 +50,16 @@ impl Status {
     pub const NOT_STARTED_LABEL: &'static str = "🚀 Not Started";
 
     pub fn from_str(s: &str) -> Self {
+        Self::from_label(s).unwrap_or(Status::NotStarted)
+    }
+
+    /// Like `from_str`, but `None` for a label this version doesn't know.
+    pub fn from_label(s: &str) -> Option<Self> {
         match s {
-            Self::DONE_LABEL => Status::Done,
-            Self::IN_PROGRESS_LABEL => Status::InProgress,
-            Self::NOT_STARTED_LABEL => Status::NotStarted,
-            _ => Status::NotStarted,
+            Self::DONE_LABEL => Some(Status::Done),
+            Self::IN_PROGRESS_LABEL => Some(Status::InProgress),
+            Self::NOT_STARTED_LABEL => Some(Status::NotStarted),
+            _ => None,
         }
     }
 
@@ -46,13 +77,183 @@ impl fmt::Display for Status {
     }
 }
 
-#[derive(Debug, Tabled)]
+/// A timestamped remark attached to a task.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comment {
+    pub date: String,
+    pub text: String,
+}
+
+/// A closed span of tracked work.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Interval {
+    pub start: NaiveDateTime,
+    pub end: NaiveDateTime,
+}
+
+impl Interval {
+    pub fn duration(&self) -> Duration {
+        self.end - self.start
+    }
+}
+
+/// The largest estimate accepted, in minutes (about 4000 years); beyond it
+/// sums and durations would overflow.
+const MAX_ESTIMATE_MINUTES: i64 = i32::MAX as i64;
+
+/// Planned effort: abstract story points or a time budget in minutes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Estimate {
+    Points(f64),
+    Minutes(i64),
+}
+
+impl Estimate {
+    pub fn is_zero(&self) -> bool {
+        match self {
+            Estimate::Points(points) => *points == 0.0,
+            Estimate::Minutes(minutes) => *minutes == 0,
+        }
+    }
+}
+
+impl FromStr for Estimate {
+    type Err = String;
+
+    /// Accepts points (`3`, `3pt`, `5sp`) or durations (`90m`, `2h`, `1h30m`, `1d` = 8h).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let input = s.trim().to_ascii_lowercase();
+        let invalid = || format!("invalid estimate `{}` (try 3pt, 2h, 1h30m or 1d)", s);
+
+        let number_end = input
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(input.len());
+        if ["", "p", "pt", "pts", "sp"].contains(&&input[number_end..]) {
+            let points: f64 = input[..number_end].parse().map_err(|_| invalid())?;
+            if !points.is_finite() {
+                return Err(format!("estimate `{}` is too large", s));
+            }
+            return Ok(Estimate::Points(points));
+        }
+
+        let mut minutes = 0.0;
+        let mut rest = input.as_str();
+        while !rest.is_empty() {
+            let number_end = rest
+                .find(|c: char| !c.is_ascii_digit() && c != '.')
+                .ok_or_else(invalid)?;
+            let value: f64 = rest[..number_end].parse().map_err(|_| invalid())?;
+            rest = &rest[number_end..];
+            let unit_end = rest
+                .find(|c: char| c.is_ascii_digit())
+                .unwrap_or(rest.len());
+            minutes += value
+                * match rest[..unit_end].trim() {
+                    "m" | "min" | "mins" => 1.0,
+                    "h" | "hr" | "hrs" => 60.0,
+                    "d" => 8.0 * 60.0,
+                    _ => return Err(invalid()),
+                };
+            rest = &rest[unit_end..];
+        }
+        if !minutes.is_finite() || minutes.round() > MAX_ESTIMATE_MINUTES as f64 {
+            return Err(format!("estimate `{}` is too large", s));
+        }
+        Ok(Estimate::Minutes(minutes.round() as i64))
+    }
+}
+
+impl fmt::Display for Estimate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Estimate::Points(points) => write!(f, "{}pt", points),
+            Estimate::Minutes(minutes) => match (minutes / 60, minutes % 60) {
+                (0, m) => write!(f, "{}m", m),
+                (h, 0) => write!(f, "{}h", h),
+                (h, m) => write!(f, "{}h{}m", h, m),
+            },
+        }
+    }
+}
+
+/// A reference attached to a task. File paths are stored relative to the
+/// board's directory when they live under it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Link {
+    Url(String),
+    File(String),
+}
+
+impl Link {
+    pub fn target(&self) -> &str {
+        match self {
+            Link::Url(url) => url,
+            Link::File(path) => path,
+        }
+    }
+}
+
+fn display_links(links: &[Link]) -> String {
+    if links.is_empty() {
+        String::new()
+    } else {
+        format!("🔗{}", links.len())
+    }
+}
+
+fn display_estimate(estimate: &Option<Estimate>) -> String {
+    estimate.map(|e| e.to_string()).unwrap_or_default()
+}
+
+fn display_assignee(assignee: &Option<String>) -> String {
+    assignee.clone().unwrap_or_default()
+}
+
+fn display_due(due: &Option<NaiveDate>) -> String {
+    due.map(|d| d.format(DAY_FORMAT).to_string())
+        .unwrap_or_default()
+}
+
+#[derive(Debug, Clone, Tabled)]
 pub struct Task {
-    pub id: i32,
+    pub id: TaskId,
     #[tabled(inline)]
     pub status: Status,
     pub description: String,
     pub date: String,
+    /// Stable identity across boards; `id` is only a short display handle.
+    /// Lines written by older versions have none until their next write.
+    #[tabled(skip)]
+    pub uuid: Option<Uuid>,
+    #[tabled(display = "display_estimate")]
+    pub estimate: Option<Estimate>,
+    #[tabled(display = "display_due")]
+    pub due: Option<NaiveDate>,
+    /// Hidden from the list views until this date.
+    #[tabled(display = "display_due")]
+    pub wait: Option<NaiveDate>,
+    #[tabled(display = "display_assignee")]
+    pub assignee: Option<String>,
+    #[tabled(display = "display_links")]
+    pub links: Vec<Link>,
+    #[tabled(skip)]
+    pub recurrence: Option<Recurrence>,
+    /// Free-form Markdown body; the description stays the short title.
+    #[tabled(skip)]
+    pub notes: String,
+    #[tabled(skip)]
+    pub comments: Vec<Comment>,
+    #[tabled(skip)]
+    pub time_log: Vec<Interval>,
+    /// Start of the running timer, if any.
+    #[tabled(skip)]
+    pub timer: Option<NaiveDateTime>,
+    /// When the task was last marked done; cleared when it is reopened.
+    #[tabled(skip)]
+    pub completed: Option<NaiveDateTime>,
+    /// Trailing `key=value` fields this version does not understand, kept verbatim.
+    #[tabled(skip)]
+    pub extra: Vec<String>,
 }
 
 impl fmt::Display for Task {
@@ -69,25 +270,422 @@ impl fmt::Display for Task {
 }
 
 impl Task {
-    pub fn new(id: i32, status: Status, description: String, date: String) -> Task {
+    pub fn new(id: TaskId, status: Status, description: String, date: String) -> Task {
         Task {
             id,
             status,
             description,
             date,
+            uuid: Some(Uuid::new_v4()),
+            estimate: None,
+            due: None,
+            wait: None,
+            assignee: None,
+            links: Vec::new(),
+            recurrence: None,
+            notes: String::new(),
+            comments: Vec::new(),
+            time_log: Vec::new(),
+            timer: None,
+            completed: None,
+            extra: Vec::new(),
         }
     }
+
+    /// Parses a tab-separated task line: `id`, `status`, `description`, `date`,
+    /// followed by optional `key=value` fields.
+    pub fn from_file_line(line: &str) -> Option<Task> {
+        let parts: Vec<&str> = line.split(SEP).collect();
+        if parts.len() < 3 {
+            return None;
+        }
+        let id = parse_id(parts[0])?;
+        let date = parts.get(3).copied().unwrap_or("").to_string();
+        let mut task = Task::new(id, Status::from_str(parts[1]), parts[2].to_string(), date);
+        task.uuid = None;
+
+        for field in parts.iter().skip(4) {
+            match field.split_once('=') {
+                Some(("due", value)) => match NaiveDate::parse_from_str(value, DAY_FORMAT) {
+                    Ok(due) => task.due = Some(due),
+                    Err(_) => task.extra.push(field.to_string()),
+                },
+                Some(("wait", value)) => match NaiveDate::parse_from_str(value, DAY_FORMAT) {
+                    Ok(wait) => task.wait = Some(wait),
+                    Err(_) => task.extra.push(field.to_string()),
+                },
+                Some(("assignee", value)) => task.assignee = Some(unescape(value)),
+                Some(("url", value)) => task.links.push(Link::Url(unescape(value))),
+                Some(("file", value)) => task.links.push(Link::File(unescape(value))),
+                Some(("recur", value)) => match value.parse() {
+                    Ok(recurrence) => task.recurrence = Some(recurrence),
+                    Err(_) => task.extra.push(field.to_string()),
+                },
+                Some(("uuid", value)) => match Uuid::parse_str(value) {
+                    Ok(uuid) => task.uuid = Some(uuid),
+                    Err(_) => task.extra.push(field.to_string()),
+                },
+                Some(("notes", value)) => task.notes = unescape(value),
+                Some(("estimate", value)) => match value.parse() {
+                    Ok(estimate) => task.estimate = Some(estimate),
+                    Err(_) => task.extra.push(field.to_string()),
+                },
+                Some(("comment", value)) => {
+                    let (date, text) = value.split_once('|').unwrap_or(("", value));
+                    task.comments.push(Comment {
+                        date: date.to_string(),
+                        text: unescape(text),
+                    });
+                },
+                Some(("time", value)) => match parse_interval(value) {
+                    Some(interval) => task.time_log.push(interval),
+                    None => task.extra.push(field.to_string()),
+                },
+                Some(("timer", value)) => match NaiveDateTime::parse_from_str(value, TIME_FORMAT) {
+                    Ok(start) => task.timer = Some(start),
+                    Err(_) => task.extra.push(field.to_string()),
+                },
+                Some(("completed", value)) => {
+                    match NaiveDateTime::parse_from_str(value, TIME_FORMAT) {
+                        Ok(at) => task.completed = Some(at),
+                        Err(_) => task.extra.push(field.to_string()),
+                    }
+                },
+                _ => task.extra.push(field.to_string()),
+            }
+        }
+        Some(task)
+    }
+
     pub fn to_file_string(&self) -> String {
-        format!(
+        let mut line = format!(
             "{}{SEP}{}{SEP}{}{SEP}{}",
             self.id,
             self.status.as_label(),
             self.description,
             self.date
-        )
+        );
+        if let Some(uuid) = self.uuid {
+            line.push_str(&format!("{SEP}uuid={}", uuid));
+        }
+        if let Some(estimate) = self.estimate {
+            line.push_str(&format!("{SEP}estimate={}", estimate));
+        }
+        if let Some(due) = self.due {
+            line.push_str(&format!("{SEP}due={}", due.format(DAY_FORMAT)));
+        }
+        if let Some(wait) = self.wait {
+            line.push_str(&format!("{SEP}wait={}", wait.format(DAY_FORMAT)));
+        }
+        if let Some(assignee) = &self.assignee {
+            line.push_str(&format!("{SEP}assignee={}", escape(assignee)));
+        }
+        for link in &self.links {
+            let key = match link {
+                Link::Url(_) => "url",
+                Link::File(_) => "file",
+            };
+            line.push_str(&format!("{SEP}{}={}", key, escape(link.target())));
+        }
+        if let Some(recurrence) = &self.recurrence {
+            line.push_str(&format!("{SEP}recur={}", recurrence));
+        }
+        if !self.notes.is_empty() {
+            line.push_str(&format!("{SEP}notes={}", escape(&self.notes)));
+        }
+        for comment in &self.comments {
+            line.push_str(&format!(
+                "{SEP}comment={}|{}",
+                comment.date,
+                escape(&comment.text)
+            ));
+        }
+        for interval in &self.time_log {
+            line.push_str(&format!(
+                "{SEP}time={}|{}",
+                interval.start.format(TIME_FORMAT),
+                interval.end.format(TIME_FORMAT)
+            ));
+        }
+        if let Some(start) = self.timer {
+            line.push_str(&format!("{SEP}timer={}", start.format(TIME_FORMAT)));
+        }
+        if let Some(at) = self.completed {
+            line.push_str(&format!("{SEP}completed={}", at.format(TIME_FORMAT)));
+        }
+        for field in &self.extra {
+            line.push_str(SEP);
+            line.push_str(field);
+        }
+        line
+    }
+
+    /// Stamps the task with `at` if it is done and `was_done` is false, unless
+    /// it already carries a time; a task that isn't done loses its time.
+    pub fn stamp_completion(&mut self, was_done: bool, at: NaiveDateTime) {
+        if self.status != Status::Done {
+            self.completed = None;
+        } else if self.completed.is_none() && !was_done {
+            self.completed = Some(at);
+        }
+    }
+
+    /// Identifies the task across renumbering and copies of the board: its
+    /// UUID, or its ID for a task that has none yet.
+    pub fn key(&self) -> String {
+        match self.uuid {
+            Some(uuid) => uuid.to_string(),
+            None => format!("id:{}", self.id),
+        }
+    }
+
+    /// The file line split into named fields. `id`, `status`, `description`
+    /// and `date` hold their bare value; every other field holds its
+    /// `key=value` items in file order.
+    pub fn fields(&self) -> BTreeMap<String, Vec<String>> {
+        let line = self.to_file_string();
+        let columns: Vec<&str> = line.split(SEP).collect();
+        let mut fields: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for (name, value) in ["id", "status", "description", "date"].iter().zip(&columns) {
+            fields.insert(name.to_string(), vec![value.to_string()]);
+        }
+        for field in columns.iter().skip(4) {
+            let name = field.split_once('=').map_or(*field, |(name, _)| name);
+            fields
+                .entry(name.to_string())
+                .or_default()
+                .push(field.to_string());
+        }
+        fields
+    }
+
+    /// Builds the next occurrence of a recurring task that was just completed:
+    /// same title, estimate, assignee, links, notes and extra fields, a fresh status and the
+    /// next due date. The ID is assigned when it is added to the board.
+    /// `None` if the task doesn't recur; an error if the next date is out of range.
+    pub fn next_occurrence(&self, today: NaiveDate) -> io::Result<Option<Task>> {
+        let Some(mut recurrence) = self.recurrence.clone() else {
+            return Ok(None);
+        };
+        let anchor = self.due.unwrap_or(today);
+        if recurrence.frequency == Frequency::Monthly && recurrence.month_day.is_none() {
+            // Pin the day, so a due date clamped to the 28th springs back to the 31st
+            recurrence.month_day = Some(anchor.day());
+        }
+        let due = recurrence.next_due(anchor, today).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Task {} recurs {}, which has no date after {}",
+                    self.id,
+                    recurrence.describe(),
+                    anchor
+                ),
+            )
+        })?;
+        let mut next = Task::new(0, Status::NotStarted, self.description.clone(), timestamp());
+        next.estimate = self.estimate;
+        next.assignee = self.assignee.clone();
+        next.links = self.links.clone();
+        next.due = Some(due);
+        next.recurrence = Some(recurrence);
+        next.notes = self.notes.clone();
+        next.extra = self.extra.clone();
+        Ok(Some(next))
     }
 
-    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+    /// The line under a task on the kanban board: its date, then badges for
+    /// the estimate, due date, a later wait date, recurrence and links.
+    pub fn card_meta(&self, today: NaiveDate) -> String {
+        let mut meta = self.date.clone();
+        let extras = [
+            self.estimate.map(|estimate| estimate.to_string()),
+            self.due.map(|due| format!("due {}", due.format("%m-%d"))),
+            self.wait
+                .filter(|wait| *wait > today)
+                .map(|wait| format!("💤 {}", wait.format("%m-%d"))),
+            self.recurrence.as_ref().map(|_| "↻".to_string()),
+            (!self.links.is_empty()).then(|| format!("🔗{}", self.links.len())),
+        ];
+        for extra in extras.into_iter().flatten() {
+            if !meta.is_empty() {
+                meta.push_str(" · ");
+            }
+            meta.push_str(&extra);
+        }
+        meta
+    }
+
+    /// Whether the task is scheduled to start after `today`.
+    pub fn is_waiting(&self, today: NaiveDate) -> bool {
+        self.wait.is_some_and(|wait| wait > today)
+    }
+
+    /// Total tracked time, counting a running timer up to `now`.
+    pub fn tracked(&self, now: NaiveDateTime) -> Duration {
+        let logged = self
+            .time_log
+            .iter()
+            .fold(Duration::zero(), |total, interval| {
+                total + interval.duration()
+            });
+        match self.timer {
+            Some(start) => logged + (now - start),
+            None => logged,
+        }
+    }
+
+    pub fn start_timer(&mut self, now: NaiveDateTime) {
+        if self.timer.is_none() {
+            self.timer = Some(now);
+        }
+    }
+
+    /// Closes the running timer into the time log. Returns the recorded interval.
+    pub fn stop_timer(&mut self, now: NaiveDateTime) -> Option<Interval> {
+        let start = self.timer.take()?;
+        let interval = Interval {
+            start,
+            end: now.max(start),
+        };
+        self.time_log.push(interval);
+        Some(interval)
+    }
+
+    pub fn write_to<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
         writeln!(writer, "{}", self.to_file_string())
     }
 }
+
+/// Parses a task ID column; zero, negative and non-numeric IDs are invalid.
+pub fn parse_id(s: &str) -> Option<TaskId> {
+    s.parse::<TaskId>().ok().filter(|id| *id > 0)
+}
+
+/// A task as named on the command line: its short numeric ID or a unique
+/// prefix of its UUID. All-digit refs are always IDs, so a deleted ID can't
+/// fall through to some other task's UUID.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaskRef(pub String);
+
+/// The shortest UUID prefix accepted in place of an ID.
+pub const MIN_UUID_PREFIX: usize = 4;
+
+impl FromStr for TaskRef {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty()
+            || s.starts_with('-')
+            || !s.chars().all(|c| c.is_ascii_hexdigit() || c == '-')
+        {
+            return Err(format!("`{}` is not a task ID or UUID prefix", s));
+        }
+        if !s.chars().all(|c| c.is_ascii_digit()) && s.len() < MIN_UUID_PREFIX {
+            return Err(format!(
+                "UUID prefix `{}` is too short (at least {} characters)",
+                s, MIN_UUID_PREFIX
+            ));
+        }
+        Ok(TaskRef(s.to_ascii_lowercase()))
+    }
+}
+
+impl fmt::Display for TaskRef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Field changes requested by `add`/`update`; `None` leaves a field alone.
+#[derive(Debug, Clone, Default)]
+pub struct TaskChanges {
+    pub status: Option<Status>,
+    pub description: Option<String>,
+    /// A zero estimate clears the current one.
+    pub estimate: Option<Estimate>,
+    pub due: Option<Option<NaiveDate>>,
+    pub wait: Option<Option<NaiveDate>>,
+    pub assignee: Option<Option<String>>,
+    pub recurrence: Option<Option<Recurrence>>,
+}
+
+impl TaskChanges {
+    pub fn is_empty(&self) -> bool {
+        self.status.is_none()
+            && self.description.is_none()
+            && self.estimate.is_none()
+            && self.due.is_none()
+            && self.wait.is_none()
+            && self.assignee.is_none()
+            && self.recurrence.is_none()
+    }
+
+    pub fn apply(self, task: &mut Task) {
+        if let Some(status) = self.status {
+            task.status = status;
+        }
+        if let Some(description) = self.description {
+            task.description = description;
+        }
+        if let Some(estimate) = self.estimate {
+            task.estimate = (!estimate.is_zero()).then_some(estimate);
+        }
+        if let Some(due) = self.due {
+            task.due = due;
+        }
+        if let Some(wait) = self.wait {
+            task.wait = wait;
+        }
+        if let Some(assignee) = self.assignee {
+            task.assignee = assignee;
+        }
+        if let Some(recurrence) = self.recurrence {
+            task.recurrence = recurrence;
+        }
+    }
+}
+
+fn parse_interval(value: &str) -> Option<Interval> {
+    let (start, end) = value.split_once('|')?;
+    Some(Interval {
+        start: NaiveDateTime::parse_from_str(start, TIME_FORMAT).ok()?,
+        end: NaiveDateTime::parse_from_str(end, TIME_FORMAT).ok()?,
+    })
+}
+
+/// Escapes a value so it fits in a single tab-separated field.
+pub fn escape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\t' => out.push_str("\\t"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Reverses [`escape`].
+pub fn unescape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => out.push('\t'),
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
@@ -1,5 +1,8 @@
+use crate::dates::today;
+use crate::filter::TaskFilter;
 use crate::manager::Mngr;
-use crate::task::{Status, Task};
+use crate::task::{Status, Task, TaskChanges, TaskId, format_duration, now};
+use crate::user::initials;
 use crossterm::{
     event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyModifiers},
     execute,
@@ -14,6 +17,7 @@ use ratatui::{
     widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap},
 };
 use std::io;
+use std::time::Duration;
 
 pub struct App {
     manager: Mngr,
@@ -22,18 +26,26 @@ pub struct App {
     mode: AppMode,
     input: String,
     error_message: Option<String>,
+    show_details: bool,
+    show_history: bool,
+    /// The history pane and the task it was built for.
+    history: Option<(Option<TaskId>, Vec<Line<'static>>)>,
+    /// Also list tasks scheduled for a later date.
+    show_all: bool,
 }
 
 #[derive(PartialEq)]
 enum AppMode {
     Normal,
     AddingTask,
+    AddingComment,
     ConfirmDelete,
+    ChoosingLink,
 }
 
 impl App {
     pub fn new(manager: Mngr) -> io::Result<App> {
-        let tasks = manager.get_tasks()?;
+        let tasks = visible_tasks(&manager, false)?;
         let mut list_state = ListState::default();
         if !tasks.is_empty() {
             list_state.select(Some(0));
@@ -45,11 +57,16 @@ impl App {
             mode: AppMode::Normal,
             input: String::new(),
             error_message: None,
+            show_details: false,
+            show_history: false,
+            history: None,
+            show_all: false,
         })
     }
 
     fn reload_tasks(&mut self) -> io::Result<()> {
-        self.tasks = self.manager.get_tasks()?;
+        self.tasks = visible_tasks(&self.manager, self.show_all)?;
+        self.history = None;
         if self.tasks.is_empty() {
             self.list_state.select(None);
         } else if let Some(selected) = self.list_state.selected() {
@@ -62,6 +79,21 @@ impl App {
         Ok(())
     }
 
+    /// The history pane of the selected task, built again only when the
+    /// selection changes or the board is reloaded.
+    fn selected_history(&mut self) -> Vec<Line<'static>> {
+        let task = self.list_state.selected().and_then(|i| self.tasks.get(i));
+        let id = task.map(|task| task.id);
+        match &self.history {
+            Some((cached, lines)) if *cached == id => lines.clone(),
+            _ => {
+                let lines = history_lines(&self.manager, task);
+                self.history = Some((id, lines.clone()));
+                lines
+            },
+        }
+    }
+
     fn next(&mut self) {
         if self.tasks.is_empty() {
             return;
@@ -101,7 +133,13 @@ impl App {
             && let Some(task) = self.tasks.get(selected)
         {
             self.manager
-                .update_task(task.id, status, None)
+                .update_task(
+                    task.id,
+                    TaskChanges {
+                        status: Some(status),
+                        ..TaskChanges::default()
+                    },
+                )
                 .map_err(io::Error::other)?;
             self.reload_tasks()?;
         }
@@ -120,10 +158,70 @@ impl App {
         Ok(())
     }
 
+    /// Starts a timer on the selected task, or stops it if it is already running.
+    fn toggle_timer(&mut self) -> io::Result<()> {
+        if let Some(selected) = self.list_state.selected()
+            && let Some(task) = self.tasks.get(selected)
+        {
+            if task.timer.is_some() {
+                self.manager.stop_timer().map_err(io::Error::other)?;
+            } else {
+                self.manager
+                    .start_timer(task.id)
+                    .map_err(io::Error::other)?;
+            }
+            self.reload_tasks()?;
+        }
+        Ok(())
+    }
+
+    fn selected_task(&self) -> Option<&Task> {
+        self.list_state.selected().and_then(|i| self.tasks.get(i))
+    }
+
+    /// Opens the only link of the selected task, or asks which one to open.
+    fn open_links(&mut self) -> io::Result<()> {
+        let Some(task) = self.selected_task() else {
+            return Ok(());
+        };
+        match task.links.as_slice() {
+            [] => Err(io::Error::other("Task has no links")),
+            [link] => self.manager.open_link(link),
+            _ => {
+                self.mode = AppMode::ChoosingLink;
+                Ok(())
+            },
+        }
+    }
+
+    fn open_link(&mut self, index: usize) -> io::Result<()> {
+        self.mode = AppMode::Normal;
+        if let Some(link) = self.selected_task().and_then(|task| task.links.get(index)) {
+            self.manager.open_link(link)?;
+        }
+        Ok(())
+    }
+
+    fn comment_current_task(&mut self) -> io::Result<()> {
+        if let Some(selected) = self.list_state.selected()
+            && let Some(task) = self.tasks.get(selected)
+            && !self.input.is_empty()
+        {
+            self.manager
+                .add_comment(task.id, self.input.clone())
+                .map_err(io::Error::other)?;
+            self.input.clear();
+            self.mode = AppMode::Normal;
+            self.show_details = true;
+            self.reload_tasks()?;
+        }
+        Ok(())
+    }
+
     fn add_task(&mut self) -> io::Result<()> {
         if !self.input.is_empty() {
             self.manager
-                .add_task(self.input.clone())
+                .add_task(self.input.clone(), TaskChanges::default())
                 .map_err(io::Error::other)?;
             self.input.clear();
             self.mode = AppMode::Normal;
@@ -137,6 +235,19 @@ impl App {
     }
 }
 
+fn visible_tasks(manager: &Mngr, show_all: bool) -> io::Result<Vec<Task>> {
+    let filter = TaskFilter {
+        all: show_all,
+        ..TaskFilter::default()
+    };
+    let today = today();
+    Ok(manager
+        .get_tasks()?
+        .into_iter()
+        .filter(|task| filter.matches(task, today))
+        .collect())
+}
+
 pub fn run(manager: Mngr) -> io::Result<()> {
     // Setup terminal
     enable_raw_mode()?;
@@ -169,6 +280,10 @@ fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> io::Result<(
     loop {
         terminal.draw(|f| ui(f, &mut app))?;
 
+        // Wake up periodically so the running timer keeps ticking
+        if !event::poll(Duration::from_secs(1))? {
+            continue;
+        }
         if let Event::Key(key) = event::read()? {
             match app.mode {
                 AppMode::Normal => match key.code {
@@ -183,10 +298,36 @@ fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> io::Result<(
                         app.input.clear();
                         app.error_message = None;
                     },
-                    KeyCode::Char('d') => {
-                        if app.list_state.selected().is_some() && !app.tasks.is_empty() {
-                            app.mode = AppMode::ConfirmDelete;
-                            app.error_message = None;
+                    KeyCode::Char('d')
+                        if app.list_state.selected().is_some() && !app.tasks.is_empty() =>
+                    {
+                        app.mode = AppMode::ConfirmDelete;
+                        app.error_message = None;
+                    },
+                    KeyCode::Char('c')
+                        if app.list_state.selected().is_some() && !app.tasks.is_empty() =>
+                    {
+                        app.mode = AppMode::AddingComment;
+                        app.input.clear();
+                        app.error_message = None;
+                    },
+                    KeyCode::Char('i') | KeyCode::Tab => app.show_details = !app.show_details,
+                    KeyCode::Char('h') => app.show_history = !app.show_history,
+                    KeyCode::Char('a') => {
+                        app.show_all = !app.show_all;
+                        if let Err(e) = app.reload_tasks() {
+                            app.error_message = Some(format!("Error reloading: {}", e));
+                        }
+                    },
+                    KeyCode::Char('o') => {
+                        app.error_message = None;
+                        if let Err(e) = app.open_links() {
+                            app.error_message = Some(format!("Error: {}", e));
+                        }
+                    },
+                    KeyCode::Char('t') => {
+                        if let Err(e) = app.toggle_timer() {
+                            app.error_message = Some(format!("Error: {}", e));
                         }
                     },
                     KeyCode::Char('1') => {
@@ -213,9 +354,14 @@ fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> io::Result<(
                     },
                     _ => {},
                 },
-                AppMode::AddingTask => match key.code {
+                AppMode::AddingTask | AppMode::AddingComment => match key.code {
                     KeyCode::Enter => {
-                        if let Err(e) = app.add_task() {
+                        let result = if app.mode == AppMode::AddingTask {
+                            app.add_task()
+                        } else {
+                            app.comment_current_task()
+                        };
+                        if let Err(e) = result {
                             app.error_message = Some(format!("Error: {}", e));
                             app.mode = AppMode::Normal;
                         }
@@ -233,6 +379,16 @@ fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> io::Result<(
                     },
                     _ => {},
                 },
+                AppMode::ChoosingLink => match key.code {
+                    KeyCode::Char(c @ '1'..='9') => {
+                        let index = c as usize - '1' as usize;
+                        if let Err(e) = app.open_link(index) {
+                            app.error_message = Some(format!("Error: {}", e));
+                        }
+                    },
+                    KeyCode::Esc | KeyCode::Char('q') => app.mode = AppMode::Normal,
+                    _ => {},
+                },
                 AppMode::ConfirmDelete => match key.code {
                     KeyCode::Char('y') | KeyCode::Char('Y') => {
                         if let Err(e) = app.delete_current_task() {
@@ -270,10 +426,7 @@ fn ui(f: &mut Frame, app: &mut App) {
                 .fg(Color::Cyan)
                 .add_modifier(Modifier::BOLD),
         )]),
-        Line::from(vec![Span::styled(
-            format!("Total tasks: {}", app.tasks.len()),
-            Style::default().fg(Color::DarkGray),
-        )]),
+        Line::from(timer_spans(app)),
     ])
     .alignment(Alignment::Center)
     .block(Block::default().borders(Borders::ALL));
@@ -299,11 +452,52 @@ fn ui(f: &mut Frame, app: &mut App) {
                     format!("{} ", task.status),
                     Style::default().fg(status_color),
                 ),
+                assignee_badge(task.assignee.as_deref()),
+                Span::raw(if task.assignee.is_some() { " " } else { "" }),
                 Span::raw(&task.description),
                 Span::styled(
                     format!(" ({})", task.date),
                     Style::default().fg(Color::DarkGray),
                 ),
+                Span::styled(
+                    task.estimate
+                        .map(|estimate| format!(" [{}]", estimate))
+                        .unwrap_or_default(),
+                    Style::default().fg(Color::Cyan),
+                ),
+                Span::styled(
+                    task.due
+                        .map(|due| format!(" due {}", due))
+                        .unwrap_or_default(),
+                    Style::default().fg(Color::Red),
+                ),
+                Span::styled(
+                    if task.recurrence.is_some() {
+                        " ↻"
+                    } else {
+                        ""
+                    },
+                    Style::default().fg(Color::Cyan),
+                ),
+                Span::styled(
+                    task.wait
+                        .filter(|_| task.is_waiting(today()))
+                        .map(|wait| format!(" 💤 {}", wait))
+                        .unwrap_or_default(),
+                    Style::default().fg(Color::DarkGray),
+                ),
+                Span::styled(
+                    if task.links.is_empty() {
+                        String::new()
+                    } else {
+                        format!(" 🔗{}", task.links.len())
+                    },
+                    Style::default().fg(Color::Blue),
+                ),
+                Span::styled(
+                    if task.timer.is_some() { " ⏱" } else { "" },
+                    Style::default().fg(Color::Magenta),
+                ),
             ]);
 
             ListItem::new(content)
@@ -312,9 +506,11 @@ fn ui(f: &mut Frame, app: &mut App) {
 
     let items = List::new(items)
         .block(Block::default().borders(Borders::ALL).title(match app.mode {
-            AppMode::Normal => "Tasks (↑↓/jk: navigate, 1/2/3: status, n: new, d: delete, r: reload, q: quit)",
+            AppMode::Normal => "Tasks (↑↓/jk: navigate, 1/2/3: status, n: new, c: comment, i: details, q: quit)",
             AppMode::AddingTask => "Adding Task (Enter: save, Esc: cancel)",
+            AppMode::AddingComment => "Adding Comment (Enter: save, Esc: cancel)",
             AppMode::ConfirmDelete => "Delete task? (y/n)",
+            AppMode::ChoosingLink => "Open which link? (1-9, Esc: cancel)",
         }))
         .highlight_style(
             Style::default()
@@ -323,18 +519,76 @@ fn ui(f: &mut Frame, app: &mut App) {
         )
         .highlight_symbol(">> ");
 
-    f.render_stateful_widget(items, chunks[1], &mut app.list_state);
+    if app.show_details || app.show_history {
+        let panes = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(55), Constraint::Percentage(45)])
+            .split(chunks[1]);
+        f.render_stateful_widget(items, panes[0], &mut app.list_state);
+
+        let side = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(if app.show_details && app.show_history {
+                [Constraint::Percentage(60), Constraint::Percentage(40)]
+            } else {
+                [Constraint::Percentage(100), Constraint::Percentage(0)]
+            })
+            .split(panes[1]);
+        let history = if app.show_history {
+            app.selected_history()
+        } else {
+            Vec::new()
+        };
+        let selected = app.list_state.selected().and_then(|i| app.tasks.get(i));
+        if app.show_details {
+            let details = Paragraph::new(detail_lines(selected))
+                .block(Block::default().borders(Borders::ALL).title("Details"))
+                .wrap(Wrap { trim: false });
+            f.render_widget(details, side[0]);
+        }
+        if app.show_history {
+            let history = Paragraph::new(history)
+                .block(Block::default().borders(Borders::ALL).title("History"))
+                .wrap(Wrap { trim: false });
+            f.render_widget(history, side[if app.show_details { 1 } else { 0 }]);
+        }
+    } else {
+        f.render_stateful_widget(items, chunks[1], &mut app.list_state);
+    }
 
     // Input or Help
     match app.mode {
-        AppMode::AddingTask => {
+        AppMode::ChoosingLink => {
+            let links: Vec<Line> = app
+                .selected_task()
+                .map(|task| task.links.as_slice())
+                .unwrap_or_default()
+                .iter()
+                .take(9)
+                .enumerate()
+                .map(|(index, link)| {
+                    Line::from(vec![
+                        Span::styled(
+                            format!("{} ", index + 1),
+                            Style::default().fg(Color::Yellow),
+                        ),
+                        Span::raw(app.manager.resolve_link(link)),
+                    ])
+                })
+                .collect();
+            let chooser =
+                Paragraph::new(links).block(Block::default().borders(Borders::ALL).title("Links"));
+            f.render_widget(chooser, chunks[2]);
+        },
+        AppMode::AddingTask | AppMode::AddingComment => {
+            let title = if app.mode == AppMode::AddingTask {
+                "New Task Description"
+            } else {
+                "New Comment"
+            };
             let input = Paragraph::new(app.input.as_str())
                 .style(Style::default().fg(Color::Yellow))
-                .block(
-                    Block::default()
-                        .borders(Borders::ALL)
-                        .title("New Task Description"),
-                );
+                .block(Block::default().borders(Borders::ALL).title(title));
             f.render_widget(input, chunks[2]);
         },
         _ => {
@@ -357,7 +611,9 @@ fn ui(f: &mut Frame, app: &mut App) {
                 ]),
                 Line::from(vec![
                     Span::styled("Actions: ", Style::default().add_modifier(Modifier::BOLD)),
-                    Span::raw("n new task, d delete, r reload"),
+                    Span::raw(
+                        "n new task, c comment, t timer, o open link, i details, h history, a show scheduled, d delete, r reload",
+                    ),
                 ]),
                 Line::from(vec![
                     Span::styled("Exit: ", Style::default().add_modifier(Modifier::BOLD)),
@@ -387,3 +643,149 @@ fn ui(f: &mut Frame, app: &mut App) {
         },
     }
 }
+
+/// Initials on a background colour derived from the name, like a tiny avatar.
+fn assignee_badge(assignee: Option<&str>) -> Span<'static> {
+    let Some(assignee) = assignee else {
+        return Span::raw("");
+    };
+    const COLORS: [Color; 6] = [
+        Color::Red,
+        Color::Green,
+        Color::Yellow,
+        Color::Blue,
+        Color::Magenta,
+        Color::Cyan,
+    ];
+    let hash = assignee.bytes().fold(0usize, |hash, byte| {
+        hash.wrapping_mul(31).wrapping_add(byte as usize)
+    });
+    Span::styled(
+        format!(" {} ", initials(assignee)),
+        Style::default()
+            .fg(Color::Black)
+            .bg(COLORS[hash % COLORS.len()])
+            .add_modifier(Modifier::BOLD),
+    )
+}
+
+fn timer_spans(app: &App) -> Vec<Span<'_>> {
+    let mut spans = vec![Span::styled(
+        format!("Total tasks: {}", app.tasks.len()),
+        Style::default().fg(Color::DarkGray),
+    )];
+    if let Some(task) = app.tasks.iter().find(|task| task.timer.is_some()) {
+        let now = now();
+        let elapsed = now - task.timer.unwrap_or(now);
+        spans.push(Span::styled(
+            format!(
+                "  ⏱ [{}] {} {}",
+                task.id,
+                task.description,
+                format_duration(elapsed)
+            ),
+            Style::default()
+                .fg(Color::Magenta)
+                .add_modifier(Modifier::BOLD),
+        ));
+    }
+    spans
+}
+
+fn detail_lines(task: Option<&Task>) -> Vec<Line<'_>> {
+    let Some(task) = task else {
+        return vec![Line::from(Span::styled(
+            "No task selected",
+            Style::default().fg(Color::DarkGray),
+        ))];
+    };
+
+    let heading = Style::default()
+        .fg(Color::Cyan)
+        .add_modifier(Modifier::BOLD);
+    let mut lines = vec![Line::from(Span::styled(
+        &task.description,
+        Style::default().add_modifier(Modifier::BOLD),
+    ))];
+    if let Some(estimate) = task.estimate {
+        lines.push(Line::from(format!(
+            "Estimate: {}  Tracked: {}",
+            estimate,
+            format_duration(task.tracked(now()))
+        )));
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled("Notes", heading)));
+    if task.notes.is_empty() {
+        lines.push(Line::from(Span::styled(
+            "(none)",
+            Style::default().fg(Color::DarkGray),
+        )));
+    } else {
+        lines.extend(task.notes.lines().map(Line::from));
+    }
+
+    if !task.links.is_empty() {
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled("Links", heading)));
+        for (index, link) in task.links.iter().enumerate() {
+            lines.push(Line::from(format!("{}. {}", index + 1, link.target())));
+        }
+    }
+
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled("Comments", heading)));
+    if task.comments.is_empty() {
+        lines.push(Line::from(Span::styled(
+            "(none)",
+            Style::default().fg(Color::DarkGray),
+        )));
+    }
+    for comment in &task.comments {
+        lines.push(Line::from(vec![
+            Span::styled(
+                format!("{} ", comment.date),
+                Style::default().fg(Color::DarkGray),
+            ),
+            Span::raw(&comment.text),
+        ]));
+    }
+    lines
+}
+
+/// Who changed the selected task's status or description, newest first.
+fn history_lines(manager: &Mngr, task: Option<&Task>) -> Vec<Line<'static>> {
+    let records = match task.map(|task| manager.history_of(task)) {
+        None => return vec![],
+        Some(Err(e)) => {
+            return vec![Line::from(Span::styled(
+                e.to_string(),
+                Style::default().fg(Color::Red),
+            ))];
+        },
+        Some(Ok(records)) => records,
+    };
+    if records.is_empty() {
+        return vec![Line::from(Span::styled(
+            "(no recorded changes)",
+            Style::default().fg(Color::DarkGray),
+        ))];
+    }
+    records
+        .into_iter()
+        .rev()
+        .map(|record| {
+            Line::from(vec![
+                Span::styled(
+                    format!("{} ", record.at),
+                    Style::default().fg(Color::DarkGray),
+                ),
+                Span::styled(
+                    format!("{} ", record.actor),
+                    Style::default().fg(Color::Cyan),
+                ),
+                Span::raw(record.change.to_string()),
+            ])
+        })
+        .collect()
+}
@@ -0,0 +1,42 @@
+use std::path::Path;
+use std::process::Command;
+
+/// Name of the person running `tsk`: `git config user.name` for the board's
+/// repository, falling back to `$USER`.
+pub fn current_user(board_dir: &Path) -> Option<String> {
+    let from_git = Command::new("git")
+        .arg("-C")
+        .arg(board_dir)
+        .args(["config", "user.name"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .filter(|name| !name.is_empty());
+
+    from_git.or_else(|| {
+        std::env::var("USER")
+            .or_else(|_| std::env::var("USERNAME"))
+            .ok()
+            .filter(|name| !name.is_empty())
+    })
+}
+
+/// Short badge for a name: `Alice Smith` → `AS`, `alice` → `Al`.
+pub fn initials(name: &str) -> String {
+    let words: Vec<&str> = name.split_whitespace().collect();
+    match words.as_slice() {
+        [] => String::new(),
+        [single] => {
+            let mut chars = single.chars();
+            let first = chars.next().map(|c| c.to_uppercase().to_string());
+            let second = chars.next().map(|c| c.to_lowercase().to_string());
+            first.into_iter().chain(second).collect()
+        },
+        [first, .., last] => [first, last]
+            .iter()
+            .filter_map(|word| word.chars().next())
+            .flat_map(char::to_uppercase)
+            .collect(),
+    }
+}
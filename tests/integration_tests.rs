@@ -15,183 +15,245 @@ fn run_command(temp_dir: &PathBuf, args: &[&str]) -> std::process::Output {
         .expect("Failed to run command")
 }
 
-#[test]
-fn test_add_task_creates_metadata() {
-    let temp_dir = TempDir::new().unwrap();
-    let temp_path = temp_dir.path().to_path_buf();
+const BACKENDS: &[&str] = &["tsv", "sqlite", "json", "toml", "yaml", "merge", "events"];
+
+// An empty `.tasklist` board for every backend, each in its own directory
+fn backend_boards() -> Vec<(&'static str, TempDir)> {
+    BACKENDS
+        .iter()
+        .map(|backend| {
+            let temp_dir = TempDir::new().unwrap();
+            let output = run_command(
+                &temp_dir.path().to_path_buf(),
+                &["--file", ".tasklist", "config", "backend", backend],
+            );
+            assert!(
+                output.status.success(),
+                "{}: {}",
+                backend,
+                String::from_utf8_lossy(&output.stderr)
+            );
+            (*backend, temp_dir)
+        })
+        .collect()
+}
 
-    // Add a task using relative path (will be created in temp_dir)
+// The board as a tsv file: the file itself on tsv boards, a conversion otherwise
+fn board_tsv(backend: &str, temp_path: &PathBuf) -> String {
+    if backend == "tsv" {
+        return fs::read_to_string(temp_path.join(".tasklist")).unwrap();
+    }
     let output = run_command(
-        &temp_path,
-        &["--file", ".tasklist", "add", "--description", "First task"],
+        temp_path,
+        &[
+            "--file",
+            ".tasklist",
+            "convert",
+            "board.tsv",
+            "--to",
+            "tsv",
+            "--force",
+        ],
     );
-
     assert!(
         output.status.success(),
-        "Command failed: {:?}",
+        "{}: {}",
+        backend,
         String::from_utf8_lossy(&output.stderr)
     );
+    fs::read_to_string(temp_path.join("board.tsv")).unwrap()
+}
 
-    let tasklist_path = temp_path.join(".tasklist");
-    assert!(
-        tasklist_path.exists(),
-        "File was not created at {:?}",
-        tasklist_path
-    );
+#[test]
+fn test_add_task_creates_metadata() {
+    for (backend, temp_dir) in backend_boards() {
+        let temp_path = temp_dir.path().to_path_buf();
 
-    // Verify metadata exists and task was added
-    let content = fs::read_to_string(&tasklist_path).unwrap();
-    assert!(content.starts_with("#max_id=1"), "Content: {}", content);
-    assert!(content.contains("First task"));
+        // Add a task using relative path (will be created in temp_dir)
+        let output = run_command(
+            &temp_path,
+            &["--file", ".tasklist", "add", "--description", "First task"],
+        );
+
+        assert!(
+            output.status.success(),
+            "Command failed: {:?}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+
+        let tasklist_path = temp_path.join(".tasklist");
+        assert!(
+            tasklist_path.exists(),
+            "File was not created at {:?}",
+            tasklist_path
+        );
+
+        // Verify metadata exists and task was added
+        let content = board_tsv(backend, &temp_path);
+        assert!(content.starts_with("#max_id=1"), "Content: {}", content);
+        assert!(content.contains("First task"));
+    }
 }
 
 #[test]
 fn test_add_multiple_tasks_increments_id() {
-    let temp_dir = TempDir::new().unwrap();
-    let temp_path = temp_dir.path().to_path_buf();
+    for (backend, temp_dir) in backend_boards() {
+        let temp_path = temp_dir.path().to_path_buf();
 
-    // Add first task
-    run_command(
-        &temp_path,
-        &["--file", ".tasklist", "add", "--description", "Task 1"],
-    );
+        // Add first task
+        run_command(
+            &temp_path,
+            &["--file", ".tasklist", "add", "--description", "Task 1"],
+        );
 
-    // Add second task
-    run_command(
-        &temp_path,
-        &["--file", ".tasklist", "add", "--description", "Task 2"],
-    );
+        // Add second task
+        run_command(
+            &temp_path,
+            &["--file", ".tasklist", "add", "--description", "Task 2"],
+        );
 
-    // Verify max_id is 2
-    let tasklist_path = temp_path.join(".tasklist");
-    let content = fs::read_to_string(&tasklist_path).unwrap();
-    assert!(content.starts_with("#max_id=2"));
-    assert!(content.contains("Task 1"));
-    assert!(content.contains("Task 2"));
+        // Verify max_id is 2
+        let content = board_tsv(backend, &temp_path);
+        assert!(content.starts_with("#max_id=2"));
+        assert!(content.contains("Task 1"));
+        assert!(content.contains("Task 2"));
 
-    // Count task lines (excluding metadata)
-    let task_count = content.lines().filter(|l| !l.starts_with("#")).count();
-    assert_eq!(task_count, 2);
+        // Count task lines (excluding metadata)
+        let task_count = content.lines().filter(|l| !l.starts_with("#")).count();
+        assert_eq!(task_count, 2);
+    }
 }
 
 #[test]
 fn test_update_task_preserves_metadata() {
-    let temp_dir = TempDir::new().unwrap();
-    let temp_path = temp_dir.path().to_path_buf();
+    for (backend, temp_dir) in backend_boards() {
+        let temp_path = temp_dir.path().to_path_buf();
 
-    // Add a task
-    run_command(
-        &temp_path,
-        &[
-            "--file",
-            ".tasklist",
-            "add",
-            "--description",
-            "Original task",
-        ],
-    );
+        // Add a task
+        run_command(
+            &temp_path,
+            &[
+                "--file",
+                ".tasklist",
+                "add",
+                "--description",
+                "Original task",
+            ],
+        );
 
-    // Update the task
-    let output = run_command(
-        &temp_path,
-        &[
-            "--file",
-            ".tasklist",
-            "update",
-            "--id",
-            "1",
-            "--status",
-            "in_progress",
-        ],
-    );
+        // Update the task
+        let output = run_command(
+            &temp_path,
+            &[
+                "--file",
+                ".tasklist",
+                "update",
+                "--id",
+                "1",
+                "--status",
+                "in_progress",
+            ],
+        );
 
-    assert!(output.status.success());
+        assert!(output.status.success());
 
-    // Verify metadata is preserved
-    let tasklist_path = temp_path.join(".tasklist");
-    let content = fs::read_to_string(&tasklist_path).unwrap();
-    assert!(content.starts_with("#max_id=1"));
-    assert!(content.contains("In Progress"));
+        // Verify metadata is preserved
+        let content = board_tsv(backend, &temp_path);
+        assert!(content.starts_with("#max_id=1"));
+        assert!(content.contains("In Progress"));
+    }
 }
 
 #[test]
 fn test_update_nonexistent_task_fails() {
-    let temp_dir = TempDir::new().unwrap();
-    let temp_path = temp_dir.path().to_path_buf();
+    for (backend, temp_dir) in backend_boards() {
+        let temp_path = temp_dir.path().to_path_buf();
 
-    // Add a task
-    run_command(
-        &temp_path,
-        &["--file", ".tasklist", "add", "--description", "Task 1"],
-    );
+        // Add a task
+        run_command(
+            &temp_path,
+            &["--file", ".tasklist", "add", "--description", "Task 1"],
+        );
 
-    // Try to update non-existent task
-    let output = run_command(
-        &temp_path,
-        &[
-            "--file",
-            ".tasklist",
-            "update",
-            "--id",
-            "999",
-            "--status",
-            "done",
-        ],
-    );
+        // Try to update non-existent task
+        let output = run_command(
+            &temp_path,
+            &[
+                "--file",
+                ".tasklist",
+                "update",
+                "--id",
+                "999",
+                "--status",
+                "done",
+            ],
+        );
 
-    assert!(!output.status.success());
-    let stderr = String::from_utf8_lossy(&output.stderr);
-    assert!(stderr.contains("Task with ID 999 not found"));
+        assert!(!output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            stderr.contains("Task with ID 999 not found"),
+            "{}: {}",
+            backend,
+            stderr
+        );
+    }
 }
 
 #[test]
 fn test_delete_task_preserves_metadata() {
-    let temp_dir = TempDir::new().unwrap();
-    let temp_path = temp_dir.path().to_path_buf();
+    for (backend, temp_dir) in backend_boards() {
+        let temp_path = temp_dir.path().to_path_buf();
 
-    // Add two tasks
-    run_command(
-        &temp_path,
-        &["--file", ".tasklist", "add", "--description", "Task 1"],
-    );
-    run_command(
-        &temp_path,
-        &["--file", ".tasklist", "add", "--description", "Task 2"],
-    );
+        // Add two tasks
+        run_command(
+            &temp_path,
+            &["--file", ".tasklist", "add", "--description", "Task 1"],
+        );
+        run_command(
+            &temp_path,
+            &["--file", ".tasklist", "add", "--description", "Task 2"],
+        );
 
-    // Delete first task
-    let output = run_command(&temp_path, &["--file", ".tasklist", "delete", "--id", "1"]);
+        // Delete first task
+        let output = run_command(&temp_path, &["--file", ".tasklist", "delete", "--id", "1"]);
 
-    assert!(output.status.success());
+        assert!(output.status.success());
 
-    // Verify metadata is preserved
-    let tasklist_path = temp_path.join(".tasklist");
-    let content = fs::read_to_string(&tasklist_path).unwrap();
-    assert!(content.starts_with("#max_id=2"));
-    assert!(!content.contains("Task 1"));
-    assert!(content.contains("Task 2"));
+        // Verify metadata is preserved
+        let content = board_tsv(backend, &temp_path);
+        assert!(content.starts_with("#max_id=2"));
+        assert!(!content.contains("Task 1"));
+        assert!(content.contains("Task 2"));
+    }
 }
 
 #[test]
 fn test_delete_nonexistent_task_fails() {
-    let temp_dir = TempDir::new().unwrap();
-    let temp_path = temp_dir.path().to_path_buf();
+    for (backend, temp_dir) in backend_boards() {
+        let temp_path = temp_dir.path().to_path_buf();
 
-    // Add a task
-    run_command(
-        &temp_path,
-        &["--file", ".tasklist", "add", "--description", "Task 1"],
-    );
+        // Add a task
+        run_command(
+            &temp_path,
+            &["--file", ".tasklist", "add", "--description", "Task 1"],
+        );
 
-    // Try to delete non-existent task
-    let output = run_command(
-        &temp_path,
-        &["--file", ".tasklist", "delete", "--id", "999"],
-    );
+        // Try to delete non-existent task
+        let output = run_command(
+            &temp_path,
+            &["--file", ".tasklist", "delete", "--id", "999"],
+        );
 
-    assert!(!output.status.success());
-    let stderr = String::from_utf8_lossy(&output.stderr);
-    assert!(stderr.contains("Task with ID 999 not found"));
+        assert!(!output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            stderr.contains("Task with ID 999 not found"),
+            "{}: {}",
+            backend,
+            stderr
+        );
+    }
 }
 
 #[test]
@@ -232,27 +294,28 @@ fn test_migration_from_old_format() {
 
 #[test]
 fn test_list_tasks_skips_metadata() {
-    let temp_dir = TempDir::new().unwrap();
-    let temp_path = temp_dir.path().to_path_buf();
+    for (backend, temp_dir) in backend_boards() {
+        let temp_path = temp_dir.path().to_path_buf();
 
-    // Add tasks
-    run_command(
-        &temp_path,
-        &["--file", ".tasklist", "add", "--description", "Task 1"],
-    );
-    run_command(
-        &temp_path,
-        &["--file", ".tasklist", "add", "--description", "Task 2"],
-    );
+        // Add tasks
+        run_command(
+            &temp_path,
+            &["--file", ".tasklist", "add", "--description", "Task 1"],
+        );
+        run_command(
+            &temp_path,
+            &["--file", ".tasklist", "add", "--description", "Task 2"],
+        );
 
-    // List tasks
-    let output = run_command(&temp_path, &["--file", ".tasklist", "show"]);
+        // List tasks
+        let output = run_command(&temp_path, &["--file", ".tasklist", "show"]);
 
-    assert!(output.status.success());
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    assert!(stdout.contains("Task 1"));
-    assert!(stdout.contains("Task 2"));
-    assert!(!stdout.contains("#max_id"));
+        assert!(output.status.success());
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.contains("Task 1"), "{}: {}", backend, stdout);
+        assert!(stdout.contains("Task 2"));
+        assert!(!stdout.contains("#max_id"));
+    }
 }
 
 #[test]
@@ -274,119 +337,395 @@ fn test_empty_tasklist_shows_message() {
 
 #[test]
 fn test_kanban_view_works() {
-    let temp_dir = TempDir::new().unwrap();
-    let temp_path = temp_dir.path().to_path_buf();
+    for (backend, temp_dir) in backend_boards() {
+        let temp_path = temp_dir.path().to_path_buf();
 
-    // Add tasks with different statuses
-    run_command(
-        &temp_path,
-        &["--file", ".tasklist", "add", "--description", "Todo task"],
-    );
-    run_command(
-        &temp_path,
-        &[
-            "--file",
-            ".tasklist",
-            "add",
-            "--description",
-            "In progress task",
-        ],
-    );
-    run_command(
-        &temp_path,
-        &[
-            "--file",
-            ".tasklist",
-            "update",
-            "--id",
-            "2",
-            "--status",
-            "in_progress",
-        ],
-    );
+        // Add tasks with different statuses
+        run_command(
+            &temp_path,
+            &["--file", ".tasklist", "add", "--description", "Todo task"],
+        );
+        run_command(
+            &temp_path,
+            &[
+                "--file",
+                ".tasklist",
+                "add",
+                "--description",
+                "In progress task",
+            ],
+        );
+        run_command(
+            &temp_path,
+            &[
+                "--file",
+                ".tasklist",
+                "update",
+                "--id",
+                "2",
+                "--status",
+                "in_progress",
+            ],
+        );
 
-    // Show kanban view
-    let output = run_command(&temp_path, &["--file", ".tasklist", "show", "--kanban"]);
+        // Show kanban view
+        let output = run_command(&temp_path, &["--file", ".tasklist", "show", "--kanban"]);
 
-    assert!(output.status.success());
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    assert!(stdout.contains("NOT STARTED"));
-    assert!(stdout.contains("IN PROGRESS"));
-    assert!(stdout.contains("DONE"));
+        assert!(output.status.success());
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.contains("NOT STARTED"), "{}: {}", backend, stdout);
+        assert!(stdout.contains("IN PROGRESS"));
+        assert!(stdout.contains("DONE"));
+    }
 }
 
 #[test]
 fn test_verbose_flag_shows_file_path() {
-    let temp_dir = TempDir::new().unwrap();
-    let temp_path = temp_dir.path().to_path_buf();
+    for (backend, temp_dir) in backend_boards() {
+        let temp_path = temp_dir.path().to_path_buf();
 
-    // Add a task first
-    run_command(
-        &temp_path,
-        &["--file", ".tasklist", "add", "--description", "Test task"],
-    );
+        // Add a task first
+        run_command(
+            &temp_path,
+            &["--file", ".tasklist", "add", "--description", "Test task"],
+        );
 
-    // Run with verbose flag
-    let output = run_command(&temp_path, &["--file", ".tasklist", "--verbose", "show"]);
+        // Run with verbose flag
+        let output = run_command(&temp_path, &["--file", ".tasklist", "--verbose", "show"]);
 
-    assert!(output.status.success());
-    let stderr = String::from_utf8_lossy(&output.stderr);
-    assert!(stderr.contains("Using tasklist file:"));
-    assert!(stderr.contains(".tasklist"));
+        assert!(output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            stderr.contains("Using tasklist file:"),
+            "{}: {}",
+            backend,
+            stderr
+        );
+        assert!(stderr.contains(".tasklist"));
+    }
 }
 
 #[test]
 fn test_default_command_shows_tasks() {
-    let temp_dir = TempDir::new().unwrap();
-    let temp_path = temp_dir.path().to_path_buf();
+    for (backend, temp_dir) in backend_boards() {
+        let temp_path = temp_dir.path().to_path_buf();
 
-    // Add a task
-    run_command(
-        &temp_path,
-        &["--file", ".tasklist", "add", "--description", "Test task"],
-    );
+        // Add a task
+        run_command(
+            &temp_path,
+            &["--file", ".tasklist", "add", "--description", "Test task"],
+        );
 
-    // Run without subcommand (should default to show)
-    let output = run_command(&temp_path, &["--file", ".tasklist"]);
+        // Run without subcommand (should default to show)
+        let output = run_command(&temp_path, &["--file", ".tasklist"]);
 
-    assert!(output.status.success());
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    assert!(stdout.contains("Test task"));
-    assert!(stdout.contains("Project:"));
+        assert!(output.status.success());
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.contains("Test task"), "{}: {}", backend, stdout);
+        assert!(stdout.contains("Project:"));
+    }
 }
 
 #[test]
 fn test_global_kanban_flag() {
-    let temp_dir = TempDir::new().unwrap();
-    let temp_path = temp_dir.path().to_path_buf();
-
-    // Add tasks
-    run_command(
-        &temp_path,
-        &["--file", ".tasklist", "add", "--description", "Task 1"],
-    );
-    run_command(
-        &temp_path,
-        &["--file", ".tasklist", "add", "--description", "Task 2"],
-    );
-
-    // Use global --kanban flag (without subcommand)
-    let output = run_command(&temp_path, &["--file", ".tasklist", "--kanban"]);
+    for (backend, temp_dir) in backend_boards() {
+        let temp_path = temp_dir.path().to_path_buf();
 
+        // Add tasks
+        run_command(
+            &temp_path,
+            &["--file", ".tasklist", "add", "--description", "Task 1"],
+        );
+        run_command(
+            &temp_path,
+            &["--file", ".tasklist", "add", "--description", "Task 2"],
+        );
+
+        // Use global --kanban flag (without subcommand)
+        let output = run_command(&temp_path, &["--file", ".tasklist", "--kanban"]);
+
+        assert!(output.status.success());
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.contains("NOT STARTED"), "{}: {}", backend, stdout);
+        assert!(stdout.contains("IN PROGRESS"));
+        assert!(stdout.contains("DONE"));
+        assert!(stdout.contains("Task 1"));
+    }
+}
+
+#[test]
+fn test_kanban_shows_dates() {
+    for (backend, temp_dir) in backend_boards() {
+        let temp_path = temp_dir.path().to_path_buf();
+
+        // Add a task
+        run_command(
+            &temp_path,
+            &[
+                "--file",
+                ".tasklist",
+                "add",
+                "--description",
+                "Task with date",
+            ],
+        );
+
+        // Show in kanban view
+        let output = run_command(&temp_path, &["--file", ".tasklist", "show", "--kanban"]);
+
+        assert!(output.status.success());
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        // Check that dates are displayed (format: YYYY-MM-DD HH:MM)
+        assert!(stdout.contains("20"), "{}: {}", backend, stdout); // Year starts with "20"
+        assert!(stdout.contains("Task with date"));
+    }
+}
+
+#[test]
+fn test_atomic_write_prevents_corruption() {
+    for (backend, temp_dir) in backend_boards() {
+        let temp_path = temp_dir.path().to_path_buf();
+
+        // Add multiple tasks in quick succession
+        for i in 1..=5 {
+            let output = run_command(
+                &temp_path,
+                &[
+                    "--file",
+                    ".tasklist",
+                    "add",
+                    "--description",
+                    &format!("Task {}", i),
+                ],
+            );
+            assert!(output.status.success());
+        }
+
+        // Verify all tasks were added correctly
+        let content = board_tsv(backend, &temp_path);
+
+        // Should have metadata
+        assert!(content.starts_with("#max_id=5"));
+
+        // Should have all 5 tasks
+        for i in 1..=5 {
+            assert!(content.contains(&format!("Task {}", i)));
+        }
+
+        // Should have exactly 6 lines (1 metadata + 5 tasks)
+        assert_eq!(content.lines().count(), 6);
+    }
+}
+
+#[test]
+fn test_comment_and_notes_survive_updates() {
+    for (backend, temp_dir) in backend_boards() {
+        let temp_path = temp_dir.path().to_path_buf();
+
+        run_command(
+            &temp_path,
+            &["--file", ".tasklist", "add", "--description", "Deploy"],
+        );
+
+        let output = run_command(
+            &temp_path,
+            &["--file", ".tasklist", "comment", "1", "waiting on infra"],
+        );
+        assert!(output.status.success());
+
+        let output = run_command(
+            &temp_path,
+            &[
+                "--file",
+                ".tasklist",
+                "note",
+                "1",
+                "Steps:\n- build\n- ship\twith care",
+            ],
+        );
+        assert!(output.status.success());
+
+        // Notes stay on a single line in a line-based file
+        if matches!(backend, "tsv" | "merge") {
+            let content = fs::read_to_string(temp_path.join(".tasklist")).unwrap();
+            let lines = content.lines().filter(|line| !line.starts_with('#'));
+            assert_eq!(lines.count(), 1, "Content: {}", content);
+        }
+
+        run_command(
+            &temp_path,
+            &[
+                "--file",
+                ".tasklist",
+                "update",
+                "--id",
+                "1",
+                "--status",
+                "done",
+            ],
+        );
+
+        let output = run_command(&temp_path, &["--file", ".tasklist", "show", "1"]);
+        assert!(output.status.success());
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.contains("Deploy"), "{}: {}", backend, stdout);
+        assert!(stdout.contains("Done"));
+        assert!(stdout.contains("- build"));
+        assert!(stdout.contains("- ship\twith care"));
+        assert!(stdout.contains("waiting on infra"));
+    }
+}
+
+#[test]
+fn test_show_unknown_task_fails() {
+    for (backend, temp_dir) in backend_boards() {
+        let temp_path = temp_dir.path().to_path_buf();
+
+        run_command(
+            &temp_path,
+            &["--file", ".tasklist", "add", "--description", "Task 1"],
+        );
+
+        let output = run_command(&temp_path, &["--file", ".tasklist", "show", "42"]);
+        assert!(!output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            stderr.contains("Task with ID 42 not found"),
+            "{}: {}",
+            backend,
+            stderr
+        );
+    }
+}
+
+#[test]
+fn test_only_one_timer_runs_per_board() {
+    let temp_dir = TempDir::new().unwrap();
+    let temp_path = temp_dir.path().to_path_buf();
+
+    run_command(
+        &temp_path,
+        &["--file", ".tasklist", "add", "--description", "Task 1"],
+    );
+    run_command(
+        &temp_path,
+        &["--file", ".tasklist", "add", "--description", "Task 2"],
+    );
+
+    let output = run_command(&temp_path, &["--file", ".tasklist", "timer", "start", "1"]);
+    assert!(output.status.success());
+    let output = run_command(&temp_path, &["--file", ".tasklist", "timer", "start", "2"]);
     assert!(output.status.success());
     let stdout = String::from_utf8_lossy(&output.stdout);
-    assert!(stdout.contains("NOT STARTED"));
-    assert!(stdout.contains("IN PROGRESS"));
-    assert!(stdout.contains("DONE"));
-    assert!(stdout.contains("Task 1"));
+    assert!(stdout.contains("Stopped timer"), "Output: {}", stdout);
+
+    let content = fs::read_to_string(temp_path.join(".tasklist")).unwrap();
+    assert_eq!(content.matches("timer=").count(), 1, "Content: {}", content);
+    assert_eq!(content.matches("time=").count(), 1, "Content: {}", content);
+
+    let output = run_command(&temp_path, &["--file", ".tasklist", "timer", "stop"]);
+    assert!(output.status.success());
+    let output = run_command(&temp_path, &["--file", ".tasklist", "timer", "stop"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("No timer is running"));
+
+    let content = fs::read_to_string(temp_path.join(".tasklist")).unwrap();
+    assert!(!content.contains("timer="));
+    assert_eq!(content.matches("time=").count(), 2);
 }
 
 #[test]
-fn test_kanban_shows_dates() {
+fn test_time_report_sums_logged_intervals() {
+    let temp_dir = TempDir::new().unwrap();
+    let temp_path = temp_dir.path().to_path_buf();
+    let tasklist_path = temp_path.join(".tasklist");
+
+    fs::write(
+        &tasklist_path,
+        "#max_id=2\n\
+         1\t✅ Done\tBillable work\t2025-01-01 10:00\t\
+         time=2025-01-01 09:00:00|2025-01-01 10:30:00\t\
+         time=2025-01-02 09:00:00|2025-01-02 09:30:00\n\
+         2\t🚀 Not Started\tUntracked\t2025-01-01 10:00\n",
+    )
+    .unwrap();
+
+    let output = run_command(&temp_path, &["--file", ".tasklist", "report", "time"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Billable work"));
+    assert!(stdout.contains("2.00"), "Output: {}", stdout);
+    assert!(!stdout.contains("Untracked"));
+
+    // Nothing was tracked this week
+    let output = run_command(
+        &temp_path,
+        &["--file", ".tasklist", "report", "time", "--week"],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("No time recorded"));
+}
+
+#[test]
+fn test_auto_timer_follows_in_progress() {
+    let temp_dir = TempDir::new().unwrap();
+    let temp_path = temp_dir.path().to_path_buf();
+
+    run_command(
+        &temp_path,
+        &["--file", ".tasklist", "add", "--description", "Task 1"],
+    );
+    let output = run_command(
+        &temp_path,
+        &["--file", ".tasklist", "config", "auto_timer", "true"],
+    );
+    assert!(output.status.success());
+    assert!(temp_path.join(".tasklist.d/config").exists());
+
+    run_command(
+        &temp_path,
+        &[
+            "--file",
+            ".tasklist",
+            "update",
+            "--id",
+            "1",
+            "--status",
+            "ip",
+        ],
+    );
+    let content = fs::read_to_string(temp_path.join(".tasklist")).unwrap();
+    assert!(content.contains("timer="));
+
+    run_command(
+        &temp_path,
+        &[
+            "--file",
+            ".tasklist",
+            "update",
+            "--id",
+            "1",
+            "--status",
+            "d",
+        ],
+    );
+    let content = fs::read_to_string(temp_path.join(".tasklist")).unwrap();
+    assert!(!content.contains("timer="));
+    assert!(content.contains("time="));
+
+    let output = run_command(
+        &temp_path,
+        &["--file", ".tasklist", "config", "no_such_key", "1"],
+    );
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_estimates_are_stored_and_summed_in_kanban() {
     let temp_dir = TempDir::new().unwrap();
     let temp_path = temp_dir.path().to_path_buf();
 
-    // Add a task
     run_command(
         &temp_path,
         &[
@@ -394,52 +733,1898 @@ fn test_kanban_shows_dates() {
             ".tasklist",
             "add",
             "--description",
-            "Task with date",
+            "Small",
+            "--estimate",
+            "2pt",
+        ],
+    );
+    run_command(
+        &temp_path,
+        &[
+            "--file",
+            ".tasklist",
+            "add",
+            "--description",
+            "Large",
+            "--estimate",
+            "3",
         ],
     );
 
-    // Show in kanban view
+    // Estimates can be changed without touching the status
+    let output = run_command(
+        &temp_path,
+        &["--file", ".tasklist", "update", "--id", "1", "-e", "1h30m"],
+    );
+    assert!(output.status.success());
+
+    let content = fs::read_to_string(temp_path.join(".tasklist")).unwrap();
+    assert!(content.contains("estimate=1h30m"), "Content: {}", content);
+    assert!(content.contains("estimate=3pt"), "Content: {}", content);
+    assert!(content.contains("Not Started\tSmall"));
+
     let output = run_command(&temp_path, &["--file", ".tasklist", "show", "--kanban"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("NOT STARTED (3pt + 1h30m)"),
+        "Output: {}",
+        stdout
+    );
+
+    let output = run_command(&temp_path, &["--file", ".tasklist", "update", "--id", "1"]);
+    assert!(!output.status.success());
+
+    let output = run_command(
+        &temp_path,
+        &["--file", ".tasklist", "update", "--id", "1", "-e", "soon"],
+    );
+    assert!(!output.status.success());
+
+    let output = run_command(
+        &temp_path,
+        &[
+            "--file",
+            ".tasklist",
+            "update",
+            "--id",
+            "1",
+            "-e",
+            "99999999999999999999h",
+        ],
+    );
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("too large"));
+}
 
+#[test]
+fn test_estimate_report_compares_tracked_time() {
+    let temp_dir = TempDir::new().unwrap();
+    let temp_path = temp_dir.path().to_path_buf();
+    let tasklist_path = temp_path.join(".tasklist");
+
+    fs::write(
+        &tasklist_path,
+        "#max_id=2\n\
+         1\t✅ Done\tShipped\t2025-01-01 10:00\testimate=1h\t\
+         time=2025-01-01 09:00:00|2025-01-01 10:30:00\n\
+         2\t🚀 Not Started\tNext\t2025-01-01 10:00\testimate=5pt\n",
+    )
+    .unwrap();
+
+    let output = run_command(&temp_path, &["--file", ".tasklist", "report", "estimates"]);
     assert!(output.status.success());
     let stdout = String::from_utf8_lossy(&output.stdout);
-    // Check that dates are displayed (format: YYYY-MM-DD HH:MM)
-    assert!(stdout.contains("20")); // Year starts with "20"
-    assert!(stdout.contains("Task with date"));
+    assert!(stdout.contains("1.50x"), "Output: {}", stdout);
+    assert!(stdout.contains("5pt (0 unestimated)"), "Output: {}", stdout);
 }
 
 #[test]
-fn test_atomic_write_prevents_corruption() {
+fn test_completing_recurring_task_spawns_next_occurrence() {
     let temp_dir = TempDir::new().unwrap();
     let temp_path = temp_dir.path().to_path_buf();
 
-    // Add multiple tasks in quick succession
-    for i in 1..=5 {
-        let output = run_command(
-            &temp_path,
-            &[
-                "--file",
-                ".tasklist",
-                "add",
-                "--description",
-                &format!("Task {}", i),
-            ],
-        );
-        assert!(output.status.success());
-    }
+    let output = run_command(
+        &temp_path,
+        &[
+            "--file",
+            ".tasklist",
+            "add",
+            "--description",
+            "Water plants",
+            "--due",
+            "2020-01-01",
+            "--recur",
+            "daily",
+            "--estimate",
+            "15m",
+        ],
+    );
+    assert!(output.status.success());
 
-    // Verify all tasks were added correctly
-    let tasklist_path = temp_path.join(".tasklist");
-    let content = fs::read_to_string(&tasklist_path).unwrap();
+    let output = run_command(
+        &temp_path,
+        &[
+            "--file",
+            ".tasklist",
+            "update",
+            "--id",
+            "1",
+            "--status",
+            "done",
+        ],
+    );
+    assert!(output.status.success());
+
+    // An overdue chore is rescheduled after today rather than in the past
+    let tomorrow = (chrono::Local::now().date_naive() + chrono::Days::new(1))
+        .format("%Y-%m-%d")
+        .to_string();
+    let content = fs::read_to_string(temp_path.join(".tasklist")).unwrap();
+    assert!(content.starts_with("#max_id=2"), "Content: {}", content);
+    let lines: Vec<&str> = content.lines().collect();
+    assert!(lines[1].starts_with("1\t✅ Done"));
+    assert!(!lines[1].contains("recur="));
+    assert!(lines[2].starts_with("2\t🚀 Not Started\tWater plants"));
+    assert!(lines[2].contains("estimate=15m"));
+    assert!(
+        lines[2].contains(&format!("due={}", tomorrow)),
+        "Line: {}",
+        lines[2]
+    );
+    assert!(lines[2].contains("recur=FREQ=DAILY"));
 
-    // Should have metadata
-    assert!(content.starts_with("#max_id=5"));
+    let output = run_command(&temp_path, &["--file", ".tasklist", "show", "--recurring"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("daily"));
+    assert!(stdout.contains(&tomorrow));
+}
 
-    // Should have all 5 tasks
-    for i in 1..=5 {
-        assert!(content.contains(&format!("Task {}", i)));
-    }
+#[test]
+fn test_monthly_recurrence_keeps_the_day_of_the_month() {
+    let temp_dir = TempDir::new().unwrap();
+    let temp_path = temp_dir.path().to_path_buf();
+    let tsk = |args: &[&str]| {
+        let output = run_command(&temp_path, args);
+        assert!(
+            output.status.success(),
+            "{:?} failed: {}",
+            args,
+            String::from_utf8_lossy(&output.stderr)
+        );
+    };
+
+    tsk(&[
+        "add",
+        "--description",
+        "Pay rent",
+        "--due",
+        "2099-01-31",
+        "--recur",
+        "monthly",
+    ]);
+    tsk(&["update", "--id", "1", "--status", "done"]);
+    tsk(&["update", "--id", "2", "--status", "done"]);
+
+    // Jan 31 -> Feb 28 -> Mar 31, not Mar 28
+    let content = fs::read_to_string(temp_path.join(".tasklist")).unwrap();
+    let lines: Vec<&str> = content.lines().collect();
+    assert!(lines[2].contains("due=2099-02-28"), "{}", content);
+    assert!(lines[3].contains("due=2099-03-31"), "{}", content);
+    assert!(lines[3].contains("recur=FREQ=MONTHLY;BYMONTHDAY=31"));
+
+    let output = run_command(&temp_path, &["update", "--id", "3"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--wait"));
+
+    // Dates out of range are errors, not panics
+    let output = run_command(&temp_path, &["add", "-d", "Far", "--due", "500000000y"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("invalid date"));
+    tsk(&[
+        "add",
+        "--description",
+        "Never",
+        "--recur",
+        "FREQ=MONTHLY;INTERVAL=4000000000",
+    ]);
+    let output = run_command(&temp_path, &["update", "--id", "4", "--status", "done"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("has no date after"));
+    let content = fs::read_to_string(temp_path.join(".tasklist")).unwrap();
+    assert!(!content.contains("+262142"), "{}", content);
+}
+
+#[test]
+fn test_recurrence_accepts_rrule_syntax() {
+    let temp_dir = TempDir::new().unwrap();
+    let temp_path = temp_dir.path().to_path_buf();
 
-    // Should have exactly 6 lines (1 metadata + 5 tasks)
-    assert_eq!(content.lines().count(), 6);
+    let output = run_command(
+        &temp_path,
+        &[
+            "--file",
+            ".tasklist",
+            "add",
+            "--description",
+            "Sync",
+            "--recur",
+            "RRULE:FREQ=WEEKLY;INTERVAL=2;BYDAY=TU,FR",
+        ],
+    );
+    assert!(output.status.success());
+
+    let output = run_command(&temp_path, &["--file", ".tasklist", "show", "--recurring"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("every 2 weeks on Tue, Fri"),
+        "Output: {}",
+        stdout
+    );
+
+    let output = run_command(
+        &temp_path,
+        &[
+            "--file",
+            ".tasklist",
+            "add",
+            "--description",
+            "Bad",
+            "--recur",
+            "FREQ=HOURLY",
+        ],
+    );
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_snoozed_tasks_are_hidden_until_their_date() {
+    for (backend, temp_dir) in backend_boards() {
+        let temp_path = temp_dir.path().to_path_buf();
+
+        run_command(
+            &temp_path,
+            &[
+                "--file",
+                ".tasklist",
+                "add",
+                "--description",
+                "Visible task",
+            ],
+        );
+        run_command(
+            &temp_path,
+            &[
+                "--file",
+                ".tasklist",
+                "add",
+                "--description",
+                "Later task",
+                "--wait",
+                "2w",
+            ],
+        );
+        run_command(
+            &temp_path,
+            &[
+                "--file",
+                ".tasklist",
+                "add",
+                "--description",
+                "Snoozed task",
+            ],
+        );
+        let output = run_command(&temp_path, &["--file", ".tasklist", "snooze", "3", "3d"]);
+        assert!(output.status.success());
+
+        for args in [
+            vec!["--file", ".tasklist", "show"],
+            vec!["--file", ".tasklist", "show", "--kanban"],
+        ] {
+            let output = run_command(&temp_path, &args);
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            assert!(stdout.contains("Visible task"));
+            assert!(!stdout.contains("Later task"), "{}: {}", backend, stdout);
+            assert!(!stdout.contains("Snoozed task"), "{}: {}", backend, stdout);
+            assert!(stdout.contains("2 scheduled task(s) hidden"));
+        }
+
+        let output = run_command(&temp_path, &["--file", ".tasklist", "show", "--all"]);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.contains("Later task"));
+        assert!(stdout.contains("Snoozed task"));
+
+        // A past date makes the task visible again
+        run_command(
+            &temp_path,
+            &["--file", ".tasklist", "snooze", "2", "2020-01-01"],
+        );
+        run_command(
+            &temp_path,
+            &[
+                "--file",
+                ".tasklist",
+                "update",
+                "--id",
+                "3",
+                "--wait",
+                "none",
+            ],
+        );
+        let output = run_command(&temp_path, &["--file", ".tasklist", "show"]);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.contains("Later task"));
+        assert!(stdout.contains("Snoozed task"));
+        assert!(!stdout.contains("hidden"));
+    }
+}
+
+#[test]
+fn test_assign_claim_and_filter_by_assignee() {
+    let temp_dir = TempDir::new().unwrap();
+    let temp_path = temp_dir.path().to_path_buf();
+
+    for description in ["Alice task", "Mine task", "Free task"] {
+        run_command(
+            &temp_path,
+            &["--file", ".tasklist", "add", "--description", description],
+        );
+    }
+
+    let output = run_command(
+        &temp_path,
+        &["--file", ".tasklist", "assign", "1", "Alice Smith"],
+    );
+    assert!(output.status.success());
+
+    // Outside a git repository the name comes from $USER
+    let output =
+        std::process::Command::new(format!("{}/target/debug/tsk", env!("CARGO_MANIFEST_DIR")))
+            .args(["--file", ".tasklist", "claim", "2"])
+            .current_dir(&temp_path)
+            .env("USER", "tester")
+            .env("GIT_CONFIG_GLOBAL", "/dev/null")
+            .env("GIT_CONFIG_NOSYSTEM", "1")
+            .output()
+            .unwrap();
+    assert!(output.status.success());
+
+    let content = fs::read_to_string(temp_path.join(".tasklist")).unwrap();
+    assert!(content.contains("assignee=Alice Smith"));
+    assert!(content.contains("assignee=tester"));
+
+    let output = run_command(
+        &temp_path,
+        &["--file", ".tasklist", "show", "--assignee", "alice smith"],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Alice task"));
+    assert!(!stdout.contains("Mine task"));
+
+    let output = run_command(&temp_path, &["--file", ".tasklist", "show", "--unassigned"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Free task"));
+    assert!(!stdout.contains("Alice task"));
+
+    let output = run_command(&temp_path, &["--file", ".tasklist", "show", "--kanban"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("[1] (AS) Alice task"), "Output: {}", stdout);
+
+    run_command(&temp_path, &["--file", ".tasklist", "assign", "1", "none"]);
+    let content = fs::read_to_string(temp_path.join(".tasklist")).unwrap();
+    assert!(!content.contains("Alice Smith"));
+}
+
+#[test]
+fn test_links_and_attachments() {
+    let temp_dir = TempDir::new().unwrap();
+    let temp_path = temp_dir.path().to_path_buf();
+    let docs = temp_path.join("docs");
+    fs::create_dir(&docs).unwrap();
+    fs::write(docs.join("design.md"), "# Design").unwrap();
+
+    run_command(
+        &temp_path,
+        &["--file", ".tasklist", "add", "--description", "Review PR"],
+    );
+    let output = run_command(
+        &temp_path,
+        &[
+            "--file",
+            ".tasklist",
+            "link-url",
+            "1",
+            "https://example.com/pr/42",
+        ],
+    );
+    assert!(output.status.success());
+
+    let output = run_command(
+        &temp_path,
+        &["--file", ".tasklist", "link-url", "1", "not a url"],
+    );
+    assert!(!output.status.success());
+
+    // Attached from a subdirectory, stored relative to the board
+    let output = run_command(
+        &docs,
+        &["--file", "../.tasklist", "attach", "1", "./design.md"],
+    );
+    assert!(output.status.success());
+
+    let content = fs::read_to_string(temp_path.join(".tasklist")).unwrap();
+    assert!(content.contains("url=https://example.com/pr/42"));
+    assert!(
+        content.contains("file=docs/design.md"),
+        "Content: {}",
+        content
+    );
+
+    let output = run_command(&temp_path, &["--file", ".tasklist", "show", "1"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("https://example.com/pr/42"));
+    assert!(stdout.contains(&docs.join("design.md").to_string_lossy().to_string()));
+
+    let output = run_command(&temp_path, &["--file", ".tasklist", "show"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("🔗2"));
+}
+
+#[test]
+fn test_uuids_are_assigned_and_accepted_as_ids() {
+    let temp_dir = TempDir::new().unwrap();
+    let temp_path = temp_dir.path().to_path_buf();
+    let tasklist = temp_path.join(".tasklist");
+
+    // A file from an older version, without UUIDs
+    fs::write(
+        &tasklist,
+        "#max_id=2\n1\tNot Started\tFirst\t2024-01-01 10:00\n2\tNot Started\tSecond\t2024-01-01 10:00\n",
+    )
+    .unwrap();
+    run_command(&temp_path, &["--file", ".tasklist", "comment", "1", "hi"]);
+
+    let content = fs::read_to_string(&tasklist).unwrap();
+    let uuids: Vec<&str> = content
+        .lines()
+        .filter_map(|line| line.split('\t').find_map(|f| f.strip_prefix("uuid=")))
+        .collect();
+    assert_eq!(uuids.len(), 2, "Content: {}", content);
+
+    // Rewriting keeps the same UUIDs
+    run_command(&temp_path, &["--file", ".tasklist", "comment", "2", "hey"]);
+    let content = fs::read_to_string(&tasklist).unwrap();
+    assert!(uuids.iter().all(|uuid| content.contains(uuid)));
+
+    // Long enough to include a dash, so never read as an ID
+    let prefix = &uuids[1][..13];
+    let output = run_command(&temp_path, &["--file", ".tasklist", "show", prefix]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Second"));
+    assert!(stdout.contains(uuids[1]));
+
+    let output = run_command(
+        &temp_path,
+        &[
+            "--file",
+            ".tasklist",
+            "update",
+            "--id",
+            prefix,
+            "-s",
+            "done",
+        ],
+    );
+    assert!(output.status.success());
+    let content = fs::read_to_string(&tasklist).unwrap();
+    let second = content.lines().find(|l| l.contains("Second")).unwrap();
+    assert!(second.contains("Done"), "Line: {}", second);
+
+    // No UUID starts with a dash
+    let output = run_command(&temp_path, &["--file", ".tasklist", "show", "-"]);
+    assert!(!output.status.success());
+    let output = run_command(&temp_path, &["--file", ".tasklist", "show", "not-hex"]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_deleted_ids_never_fall_back_to_uuid_prefixes() {
+    let temp_dir = TempDir::new().unwrap();
+    let temp_path = temp_dir.path().to_path_buf();
+    let tasklist = temp_path.join(".tasklist");
+
+    // Task 33's UUID starts with 13, task 40's with 7
+    fs::write(
+        &tasklist,
+        "#max_id=40\n\
+         13\tNot Started\tThirteen\t2024-01-01 10:00\tuuid=aaaaaaaa-0000-4000-8000-000000000013\n\
+         33\tNot Started\tThirty-three\t2024-01-01 10:00\tuuid=13000000-0000-4000-8000-000000000033\n\
+         40\tNot Started\tForty\t2024-01-01 10:00\tuuid=7aaaaaaa-0000-4000-8000-000000000040\n",
+    )
+    .unwrap();
+    let output = run_command(&temp_path, &["--file", ".tasklist", "delete", "--id", "13"]);
+    assert!(output.status.success());
+    let after_first = fs::read_to_string(&tasklist).unwrap();
+
+    let output = run_command(&temp_path, &["--file", ".tasklist", "delete", "--id", "13"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Task with ID 13 not found"), "{}", stderr);
+    let output = run_command(&temp_path, &["--file", ".tasklist", "delete", "--id", "7"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Task with ID 7 not found"));
+    assert_eq!(fs::read_to_string(&tasklist).unwrap(), after_first);
+    assert!(after_first.contains("Thirty-three") && after_first.contains("Forty"));
+
+    // Letters make it a prefix, but it must be long enough
+    let output = run_command(&temp_path, &["--file", ".tasklist", "show", "7a"]);
+    assert!(!output.status.success());
+    let output = run_command(&temp_path, &["--file", ".tasklist", "show", "7aaa"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Forty"));
+}
+
+#[test]
+fn test_invalid_ids_are_rejected_and_renumber_compacts() {
+    let temp_dir = TempDir::new().unwrap();
+    let temp_path = temp_dir.path().to_path_buf();
+    let tasklist = temp_path.join(".tasklist");
+
+    fs::write(
+        &tasklist,
+        "#max_id=3\n1\tDone\tFirst\t\n3\tDone\tThird\t\n1\tDone\tCopy\t\n",
+    )
+    .unwrap();
+    let output = run_command(&temp_path, &["--file", ".tasklist", "show"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains(":4: duplicate task ID 1"),
+        "stderr: {}",
+        stderr
+    );
+
+    fs::write(&tasklist, "#max_id=3\n-2\tDone\tNegative\t\n").unwrap();
+    let output = run_command(&temp_path, &["--file", ".tasklist", "show"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains(":2: invalid task ID `-2`"),
+        "stderr: {}",
+        stderr
+    );
+
+    // A stale counter never hands out an existing ID
+    fs::write(
+        &tasklist,
+        "#max_id=1\n1\t✅ Done\tFirst\t\n3\t✅ Done\tThird\t\n",
+    )
+    .unwrap();
+    run_command(
+        &temp_path,
+        &["--file", ".tasklist", "add", "--description", "Fourth"],
+    );
+    let content = fs::read_to_string(&tasklist).unwrap();
+    assert!(content.contains("4\t🚀 Not Started\tFourth"), "{}", content);
+
+    let output = run_command(&temp_path, &["--file", ".tasklist", "renumber"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("3 -> 2"));
+    assert!(stdout.contains("4 -> 3"));
+    let content = fs::read_to_string(&tasklist).unwrap();
+    assert!(content.starts_with("#max_id=3\t"));
+    assert!(content.contains("2\t✅ Done\tThird"), "{}", content);
+}
+
+#[test]
+fn test_doctor_reports_and_fixes_problems() {
+    let temp_dir = TempDir::new().unwrap();
+    let temp_path = temp_dir.path().to_path_buf();
+    let tasklist = temp_path.join(".tasklist");
+
+    // Line 3 is a copy of line 2 that was edited, so it shares the UUID too
+    let uuid = "0bd0fa4b-b613-41ab-8fc4-18872c15f262";
+    let mut content = format!("#max_id=1\n1\tdone\tFirst\t2024-01-01 10:00\tuuid={uuid}\n1\t✅ Done\tSecond\tyesterday\tuuid={uuid}\n").into_bytes();
+    content.extend_from_slice(b"3\tWeird\tCaf\xE9\t\njunk\n");
+    content.extend_from_slice(b"4\t\xF0\x9F\x9A\x80 Not Started\tFine\t\tdue=2024-13-01\n");
+    fs::write(&tasklist, &content).unwrap();
+
+    let output = run_command(&temp_path, &["--file", ".tasklist", "doctor"]);
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("line 1: stale #max_id=1"), "{}", stdout);
+    assert!(stdout.contains("line 2: unknown status `done`"));
+    assert!(stdout.contains("line 3: duplicate task ID 1"));
+    assert!(stdout.contains(&format!(
+        "line 3: duplicate UUID {uuid} (already used on line 2)"
+    )));
+    assert!(stdout.contains("line 3: bad timestamp `yesterday`"));
+    assert!(stdout.contains("line 4: not valid UTF-8"));
+    assert!(stdout.contains("line 4: unknown status `Weird`"));
+    assert!(stdout.contains("line 5: malformed task line"));
+    assert!(stdout.contains("line 6: bad timestamp `due=2024-13-01`"));
+    // Checking alone changes nothing
+    assert_eq!(fs::read(&tasklist).unwrap(), content);
+
+    let output = run_command(&temp_path, &["--file", ".tasklist", "doctor", "--fix"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Backed up to"));
+    assert!(stdout.contains("Not fixed: line 5"));
+    let backups: Vec<_> = fs::read_dir(temp_path.join(".tasklist.d/backups"))
+        .unwrap()
+        .collect();
+    assert_eq!(backups.len(), 1);
+
+    let repaired = fs::read_to_string(&tasklist).unwrap();
+    assert!(repaired.starts_with("#max_id=5\n"), "{}", repaired);
+    assert!(repaired.contains("1\t✅ Done\tFirst"));
+    assert!(repaired.contains("5\t✅ Done\tSecond"));
+    assert!(repaired.contains("Caf\u{FFFD}"));
+    assert!(repaired.contains("junk"));
+    let uuids: Vec<&str> = repaired
+        .lines()
+        .filter_map(|line| line.split('\t').find_map(|f| f.strip_prefix("uuid=")))
+        .collect();
+    assert_eq!(uuids.len(), 2, "{}", repaired);
+    assert_eq!(uuids[0], uuid);
+    assert_ne!(uuids[1], uuid);
+
+    // Loading the board refuses a shared UUID like it refuses a shared ID
+    fs::write(
+        &tasklist,
+        format!(
+            "#max_id=2\n1\t🚀 Not Started\tA\t\tuuid={uuid}\n2\t🚀 Not Started\tB\t\tuuid={uuid}\n"
+        ),
+    )
+    .unwrap();
+    let output = run_command(&temp_path, &["--file", ".tasklist", "show"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains(&format!(
+            ":3: duplicate task UUID {uuid} (already used on line 2)"
+        )),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn test_comment_lines_survive_writes_and_strict_mode_rejects_junk() {
+    let temp_dir = TempDir::new().unwrap();
+    let temp_path = temp_dir.path().to_path_buf();
+    let tasklist = temp_path.join(".tasklist");
+
+    fs::write(
+        &tasklist,
+        "#max_id=2\n# Sprint 12\n1\t🚀 Not Started\tFirst\t\n\n#owner: platform\n2\t🚀 Not Started\tSecond\t\n",
+    )
+    .unwrap();
+    let output = run_command(
+        &temp_path,
+        &["--file", ".tasklist", "update", "--id", "2", "-s", "done"],
+    );
+    assert!(output.status.success());
+    run_command(&temp_path, &["--file", ".tasklist", "delete", "--id", "1"]);
+
+    let content = fs::read_to_string(&tasklist).unwrap();
+    let lines: Vec<&str> = content.lines().collect();
+    assert!(lines[0].starts_with("#max_id=2\t"), "{}", lines[0]);
+    assert_eq!(lines[1], "# Sprint 12");
+    assert_eq!(lines[2], "");
+    assert_eq!(lines[3], "#owner: platform");
+    assert!(lines[4].starts_with("2\t✅ Done\tSecond"));
+
+    fs::write(&tasklist, format!("{}junk line\n", content)).unwrap();
+    let output = run_command(&temp_path, &["--file", ".tasklist", "show"]);
+    assert!(output.status.success());
+
+    let output = run_command(&temp_path, &["--file", ".tasklist", "--strict", "show"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains(":6: malformed task line"),
+        "stderr: {}",
+        stderr
+    );
+
+    // The board can opt in for everyone
+    run_command(
+        &temp_path,
+        &["--file", ".tasklist", "config", "strict", "true"],
+    );
+    let output = run_command(
+        &temp_path,
+        &["--file", ".tasklist", "update", "--id", "2", "-s", "ip"],
+    );
+    assert!(!output.status.success());
+    assert!(fs::read_to_string(&tasklist).unwrap().contains("junk line"));
+}
+
+#[test]
+fn test_backups_rotate_and_restore() {
+    let temp_dir = TempDir::new().unwrap();
+    let temp_path = temp_dir.path().to_path_buf();
+    let tasklist = temp_path.join(".tasklist");
+    run_command(
+        &temp_path,
+        &["--file", ".tasklist", "config", "backups", "3"],
+    );
+
+    for description in ["One", "Two", "Three", "Four", "Five"] {
+        run_command(
+            &temp_path,
+            &["--file", ".tasklist", "add", "--description", description],
+        );
+    }
+    // The first add had no file to back up
+    let backups = fs::read_dir(temp_path.join(".tasklist.d/backups")).unwrap();
+    assert_eq!(backups.count(), 3);
+
+    let output = run_command(&temp_path, &["--file", ".tasklist", "backup", "list"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("  1  "), "{}", stdout);
+    assert!(stdout.contains("4 task(s)"));
+    assert!(!stdout.contains("  4  "));
+
+    run_command(&temp_path, &["--file", ".tasklist", "delete", "--id", "5"]);
+    run_command(&temp_path, &["--file", ".tasklist", "delete", "--id", "4"]);
+    let output = run_command(
+        &temp_path,
+        &["--file", ".tasklist", "backup", "restore", "2"],
+    );
+    assert!(output.status.success());
+    let content = fs::read_to_string(&tasklist).unwrap();
+    assert!(content.contains("Five"), "{}", content);
+
+    let output = run_command(
+        &temp_path,
+        &["--file", ".tasklist", "backup", "restore", "9"],
+    );
+    assert!(!output.status.success());
+}
+
+// Every storage backend a board can be configured with
+#[test]
+fn test_every_backend_supports_the_task_lifecycle() {
+    for (backend, temp_dir) in backend_boards() {
+        let temp_path = temp_dir.path().to_path_buf();
+        let tsk = |args: &[&str]| {
+            let mut full = vec!["--file", ".tasklist"];
+            full.extend_from_slice(args);
+            let output = run_command(&temp_path, &full);
+            assert!(
+                output.status.success(),
+                "{}: {:?} failed: {}",
+                backend,
+                args,
+                String::from_utf8_lossy(&output.stderr)
+            );
+            String::from_utf8_lossy(&output.stdout).to_string()
+        };
+
+        tsk(&["add", "--description", "Write docs", "--estimate", "2h"]);
+        tsk(&["add", "--description", "Ship it", "--due", "2030-01-31"]);
+        tsk(&["update", "--id", "1", "--status", "in_progress"]);
+        tsk(&["comment", "2", "after the docs"]);
+        tsk(&["assign", "2", "alice"]);
+        tsk(&["delete", "--id", "1"]);
+        tsk(&["add", "--description", "Celebrate"]);
+
+        let list = tsk(&["show"]);
+        assert!(!list.contains("Write docs"), "{}: {}", backend, list);
+        assert!(list.contains("Ship it"));
+        assert!(list.contains("Celebrate"));
+
+        let details = tsk(&["show", "2"]);
+        assert!(
+            details.contains("after the docs"),
+            "{}: {}",
+            backend,
+            details
+        );
+        assert!(details.contains("alice"));
+        assert!(details.contains("2030-01-31"));
+        // IDs are not reused after a delete
+        assert!(tsk(&["show", "3"]).contains("Celebrate"));
+
+        let done = tsk(&["show", "--status", "in_progress"]);
+        assert!(!done.contains("Ship it"), "{}: {}", backend, done);
+        let due = tsk(&["show", "--due-by", "2030-12-31"]);
+        assert!(due.contains("Ship it"), "{}: {}", backend, due);
+        assert!(!due.contains("Celebrate"));
+    }
+}
+
+#[test]
+fn test_dry_run_saves_nothing() {
+    let temp_dir = TempDir::new().unwrap();
+    let temp_path = temp_dir.path().to_path_buf();
+    let tasklist = temp_path.join(".tasklist");
+
+    run_command(
+        &temp_path,
+        &["--file", ".tasklist", "add", "--description", "Keep me"],
+    );
+    let before = fs::read_to_string(&tasklist).unwrap();
+
+    let output = run_command(
+        &temp_path,
+        &["--file", ".tasklist", "--dry-run", "delete", "--id", "1"],
+    );
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Deleted task with ID 1"));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("nothing was saved"));
+    assert_eq!(fs::read_to_string(&tasklist).unwrap(), before);
+
+    let output = run_command(
+        &temp_path,
+        &[
+            "--file",
+            ".tasklist",
+            "--dry-run",
+            "add",
+            "--description",
+            "Nope",
+        ],
+    );
+    assert!(output.status.success());
+    assert_eq!(fs::read_to_string(&tasklist).unwrap(), before);
+    assert!(!temp_path.join(".tasklist.d/backups").exists());
+}
+
+#[test]
+fn test_migrate_between_tsv_and_sqlite_keeps_ids_and_dates() {
+    let temp_dir = TempDir::new().unwrap();
+    let temp_path = temp_dir.path().to_path_buf();
+    let tasklist = temp_path.join(".tasklist");
+
+    fs::write(
+        &tasklist,
+        "#max_id=7\n# kept\n3\t✅ Done\tOld work\t2024-01-01 10:00\n7\t🚀 Not Started\tNew work\t2024-02-02 11:30\tdue=2024-03-01\n",
+    )
+    .unwrap();
+    let original = fs::read_to_string(&tasklist).unwrap();
+
+    let output = run_command(
+        &temp_path,
+        &["--file", ".tasklist", "migrate", "--to", "sqlite"],
+    );
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(fs::read(&tasklist).unwrap().starts_with(b"SQLite format 3"));
+
+    let output = run_command(&temp_path, &["--file", ".tasklist", "show", "7"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("New work"), "{}", stdout);
+    assert!(stdout.contains("2024-02-02 11:30"));
+
+    // Going back gives the same file, with UUIDs and a checksum added on the way
+    let output = run_command(
+        &temp_path,
+        &["--file", ".tasklist", "migrate", "--to", "tsv"],
+    );
+    assert!(output.status.success());
+    let content = fs::read_to_string(&tasklist).unwrap();
+    let without_uuids: Vec<String> = content
+        .lines()
+        .map(|line| {
+            line.split('\t')
+                .filter(|field| {
+                    !["uuid=", "checksum=", "tasks="]
+                        .iter()
+                        .any(|prefix| field.starts_with(prefix))
+                })
+                .collect::<Vec<_>>()
+                .join("\t")
+        })
+        .collect();
+    assert_eq!(without_uuids.join("\n") + "\n", original);
+
+    let output = run_command(
+        &temp_path,
+        &["--file", ".tasklist", "migrate", "--to", "tsv"],
+    );
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_sqlite_edits_and_deletes_keep_the_board_order() {
+    let temp_dir = TempDir::new().unwrap();
+    let temp_path = temp_dir.path().to_path_buf();
+    let tasklist = temp_path.join(".tasklist");
+
+    fs::write(
+        &tasklist,
+        "#max_id=4\n1\t🚀 Not Started\tFirst\t2024-01-01 10:00\n# kept\n2\t🚀 Not Started\tSecond\t2024-01-01 10:00\n3\t🚀 Not Started\tThird\t2024-01-01 10:00\n4\t🚀 Not Started\tFourth\t2024-01-01 10:00\n",
+    )
+    .unwrap();
+    let output = run_command(
+        &temp_path,
+        &["--file", ".tasklist", "migrate", "--to", "sqlite"],
+    );
+    assert!(output.status.success());
+
+    for args in [
+        &["--file", ".tasklist", "delete", "--id", "2"][..],
+        &["--file", ".tasklist", "update", "--id", "3", "-s", "done"],
+        &["--file", ".tasklist", "delete", "--id", "1"],
+        &["--file", ".tasklist", "comment", "4", "still open"],
+        &["--file", ".tasklist", "add", "-d", "Fifth"],
+    ] {
+        let output = run_command(&temp_path, args);
+        assert!(
+            output.status.success(),
+            "{:?}: {}",
+            args,
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    let output = run_command(&temp_path, &["--file", ".tasklist", "delete", "--id", "2"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Task with ID 2 not found"));
+
+    let output = run_command(&temp_path, &["--file", ".tasklist", "show", "3"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Done at:"), "{}", stdout);
+    let output = run_command(&temp_path, &["--file", ".tasklist", "show", "4"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("still open"));
+    let output = run_command(&temp_path, &["--file", ".tasklist", "history", "3"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Done"), "{}", stdout);
+
+    let output = run_command(
+        &temp_path,
+        &["--file", ".tasklist", "migrate", "--to", "tsv"],
+    );
+    assert!(output.status.success());
+    let content = fs::read_to_string(&tasklist).unwrap();
+    let rows: Vec<String> = content
+        .lines()
+        .skip(1)
+        .map(|line| line.split('\t').take(3).collect::<Vec<_>>().join(" "))
+        .collect();
+    assert_eq!(
+        rows,
+        [
+            "# kept",
+            "3 ✅ Done Third",
+            "4 🚀 Not Started Fourth",
+            "5 🚀 Not Started Fifth"
+        ]
+    );
+}
+
+#[test]
+fn test_checksum_flags_outside_changes_until_rehash() {
+    let temp_dir = TempDir::new().unwrap();
+    let temp_path = temp_dir.path().to_path_buf();
+    let tasklist = temp_path.join(".tasklist");
+
+    for description in ["First", "Second", "Third"] {
+        run_command(
+            &temp_path,
+            &["--file", ".tasklist", "add", "--description", description],
+        );
+    }
+    let content = fs::read_to_string(&tasklist).unwrap();
+    let header = content.lines().next().unwrap();
+    assert!(header.starts_with("#max_id=3\tchecksum="), "{}", header);
+    assert!(header.ends_with("\ttasks=3"), "{}", header);
+    let output = run_command(&temp_path, &["--file", ".tasklist", "show"]);
+    assert!(String::from_utf8_lossy(&output.stderr).is_empty());
+
+    // A sync tool cut the file short: still readable, but flagged
+    let truncated: Vec<&str> = content.lines().take(3).collect();
+    fs::write(&tasklist, truncated.join("\n") + "\n").unwrap();
+    let output = run_command(&temp_path, &["--file", ".tasklist", "show"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Second"));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("1 task(s) fewer"), "{}", stderr);
+    assert!(stderr.contains("tsk backup restore 1"));
+
+    // Strict mode won't write on top of it
+    let output = run_command(
+        &temp_path,
+        &[
+            "--file",
+            ".tasklist",
+            "--strict",
+            "update",
+            "--id",
+            "1",
+            "-s",
+            "done",
+        ],
+    );
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("strict mode: not writing"));
+
+    // Accepting the loss, then editing by hand
+    let output = run_command(&temp_path, &["--file", ".tasklist", "rehash"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("(2 task(s))"));
+    let edited = fs::read_to_string(&tasklist)
+        .unwrap()
+        .replace("Second", "Second, edited");
+    fs::write(&tasklist, &edited).unwrap();
+    let output = run_command(&temp_path, &["--file", ".tasklist", "show"]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("changed outside tsk"));
+    let output = run_command(&temp_path, &["--file", ".tasklist", "rehash"]);
+    assert!(output.status.success());
+    let content = fs::read_to_string(&tasklist).unwrap();
+    assert_eq!(
+        content.split_once('\n').unwrap().1,
+        edited.split_once('\n').unwrap().1
+    );
+    let output = run_command(&temp_path, &["--file", ".tasklist", "show"]);
+    assert!(String::from_utf8_lossy(&output.stderr).is_empty());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Second, edited"));
+}
+
+#[test]
+fn test_structured_formats_are_picked_by_extension_and_convert_losslessly() {
+    let temp_dir = TempDir::new().unwrap();
+    let temp_path = temp_dir.path().to_path_buf();
+    let tasklist = temp_path.join(".tasklist");
+
+    fs::write(
+        &tasklist,
+        "#max_id=4\n# Release\n2\t⏳ In Progress\tShip it\t2024-01-01 10:00\tuuid=2b0c9a0e-5a57-4b4e-9a38-1f1e7f0d9a11\testimate=3pt\tdue=2024-02-01\tassignee=alice\turl=https://example.com\tnotes=line\\nnext\tcomment=2024-01-02 09:00|looks good\ttime=2024-01-01 10:00:00|2024-01-01 11:00:00\tcustom=kept\n\n# Later\n3\t🚀 Not Started\tDocs\t2024-01-01 10:00\tuuid=3b0c9a0e-5a57-4b4e-9a38-1f1e7f0d9a11\n",
+    )
+    .unwrap();
+    run_command(&temp_path, &["--file", ".tasklist", "rehash"]);
+    let original = fs::read_to_string(&tasklist).unwrap();
+
+    for name in ["board.toml", "board.json", "board.yaml"] {
+        let output = run_command(&temp_path, &["--file", ".tasklist", "convert", name]);
+        assert!(
+            output.status.success(),
+            "{}: {}",
+            name,
+            String::from_utf8_lossy(&output.stderr)
+        );
+
+        // Picked by extension, without any config
+        let output = run_command(&temp_path, &["--file", name, "show", "2"]);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.contains("looks good"), "{}: {}", name, stdout);
+
+        // Saving an unchanged board gives the same bytes
+        let before = fs::read_to_string(temp_path.join(name)).unwrap();
+        run_command(&temp_path, &["--file", name, "assign", "2", "alice"]);
+        let after = fs::read_to_string(temp_path.join(name)).unwrap();
+        assert_eq!(before, after, "{}", name);
+
+        let back = format!("{}.tsv", name);
+        run_command(
+            &temp_path,
+            &["--file", name, "convert", &back, "--to", "tsv"],
+        );
+        assert_eq!(
+            fs::read_to_string(temp_path.join(&back)).unwrap(),
+            original,
+            "{}",
+            name
+        );
+    }
+
+    let toml = fs::read_to_string(temp_path.join("board.toml")).unwrap();
+    assert!(toml.contains("[[tasks]]"));
+    assert!(toml.contains("status = \"in_progress\""));
+
+    let output = run_command(
+        &temp_path,
+        &["--file", ".tasklist", "convert", "board.toml"],
+    );
+    assert!(!output.status.success());
+
+    // Hand edits get the same ID checks as tsv boards
+    let task = |id: u32| {
+        format!(
+            "[[tasks]]\ndescription = \"T{}\"\nid = {}\nstatus = \"done\"\n",
+            id, id
+        )
+    };
+    for (content, error) in [
+        (task(1) + &task(0), "task #2 (\"T0\"): invalid task ID `0`"),
+        (task(3) + &task(3), "task #2 (\"T3\"): duplicate task ID 3"),
+        (
+            format!("max_id = 2\n{}", task(5)),
+            "max_id = 2 is below the highest task ID 5",
+        ),
+        // tsv writes descriptions as-is, so they must stay on one line
+        (
+            "[[tasks]]\ndescription = \"line one\\nline two\\tand tab\"\nid = 1\nstatus = \"done\"\n"
+                .to_string(),
+            "description contains the control character '\\n'",
+        ),
+        (
+            format!("raw = [{{ after = 0, line = \"a\\nb\" }}]\n{}", task(1)),
+            "spans several lines",
+        ),
+    ] {
+        fs::write(temp_path.join("hand.toml"), content).unwrap();
+        let output = run_command(&temp_path, &["--file", "hand.toml", "show"]);
+        assert!(!output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains(error), "{}", stderr);
+    }
+    fs::write(temp_path.join("hand.toml"), task(5)).unwrap();
+    run_command(
+        &temp_path,
+        &["--file", "hand.toml", "add", "--description", "Next"],
+    );
+    let toml = fs::read_to_string(temp_path.join("hand.toml")).unwrap();
+    assert!(toml.starts_with("max_id = 6"), "{}", toml);
+}
+
+#[test]
+fn test_merge_driver_combines_concurrent_edits() {
+    let temp_dir = TempDir::new().unwrap();
+    let temp_path = temp_dir.path().to_path_buf();
+    let uuid = |n: u32| format!("uuid=00000000-0000-4000-8000-00000000000{}", n);
+    let task = |id: u32, status: &str, description: &str, extra: &str| {
+        format!(
+            "{}\t{}\t{}\t2025-01-01 10:00\t{}{}\n",
+            id,
+            status,
+            description,
+            uuid(id),
+            extra
+        )
+    };
+    let base =
+        task(1, "🚀 Not Started", "Write docs", "") + &task(2, "🚀 Not Started", "Ship it", "");
+    // Ours starts task 1, comments on task 2 and adds task 3
+    let ours = task(1, "⏳ In Progress", "Write docs", "")
+        + &task(
+            2,
+            "🚀 Not Started",
+            "Ship it",
+            "\tcomment=2025-01-02 09:00|ours",
+        )
+        + &task(3, "🚀 Not Started", "Ours new", "");
+    // Theirs assigns task 1, comments on task 2 and adds its own task 3
+    let theirs = task(1, "🚀 Not Started", "Write docs", "\tassignee=alice")
+        + &task(
+            2,
+            "🚀 Not Started",
+            "Ship it",
+            "\tcomment=2025-01-02 09:30|theirs",
+        )
+        + &format!(
+            "3\t🚀 Not Started\tTheirs new\t2025-01-01 10:00\t{}\n",
+            "uuid=00000000-0000-4000-8000-000000000009"
+        );
+    for (name, content) in [("base", &base), ("ours", &ours), ("theirs", &theirs)] {
+        fs::write(temp_path.join(name), content).unwrap();
+    }
+
+    let output = run_command(&temp_path, &["merge-driver", "base", "ours", "theirs"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let merged = fs::read_to_string(temp_path.join("ours")).unwrap();
+    assert!(!merged.starts_with("#max_id="), "Merged: {}", merged);
+    let line = |description: &str| {
+        merged
+            .lines()
+            .find(|line| line.contains(description))
+            .unwrap_or_else(|| panic!("{} missing: {}", description, merged))
+            .to_string()
+    };
+    assert!(line("Write docs").contains("In Progress"));
+    assert!(line("Write docs").contains("assignee=alice"));
+    assert!(line("Ship it").contains("|ours"));
+    assert!(line("Ship it").contains("|theirs"));
+    assert!(line("Ours new").starts_with("3\t"));
+    // Their task 3 is a different task, so it gets a fresh ID
+    assert!(line("Theirs new").starts_with("4\t"), "Merged: {}", merged);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("3 -> 4"));
+
+    // Both sides renaming the same task is a conflict; ours is kept
+    fs::write(temp_path.join("base"), &base).unwrap();
+    fs::write(
+        temp_path.join("ours"),
+        task(1, "🚀 Not Started", "Write the docs", ""),
+    )
+    .unwrap();
+    fs::write(
+        temp_path.join("theirs"),
+        task(1, "🚀 Not Started", "Write all docs", ""),
+    )
+    .unwrap();
+    let output = run_command(&temp_path, &["merge-driver", "base", "ours", "theirs"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("description"));
+    let merged = fs::read_to_string(temp_path.join("ours")).unwrap();
+    assert!(merged.contains("Write the docs"), "Merged: {}", merged);
+
+    // An encrypted side is decrypted, and the result stays encrypted
+    fs::write(temp_path.join("base"), &base).unwrap();
+    fs::write(temp_path.join("ours"), &base).unwrap();
+    fs::write(temp_path.join("theirs"), &theirs).unwrap();
+    let tsk = |args: &[&str]| {
+        std::process::Command::new(format!("{}/target/debug/tsk", env!("CARGO_MANIFEST_DIR")))
+            .args(args)
+            .current_dir(&temp_path)
+            .env("TSK_PASSPHRASE", "s3cret")
+            .output()
+            .unwrap()
+    };
+    assert!(tsk(&["--file", "theirs", "encrypt"]).status.success());
+    assert!(!fs::read_to_string(temp_path.join("theirs")).is_ok_and(|c| c.contains("alice")));
+    let output = run_command(&temp_path, &["merge-driver", "base", "ours", "theirs"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("TSK_PASSPHRASE"));
+    let output = tsk(&["merge-driver", "base", "ours", "theirs"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let merged = fs::read(temp_path.join("ours")).unwrap();
+    assert!(merged.starts_with(b"tsk-encrypted-v1\n"));
+    let output = tsk(&["--file", "ours", "show", "1"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("alice"));
+}
+
+#[test]
+fn test_merge_backend_sorts_by_uuid_without_a_counter() {
+    let temp_dir = TempDir::new().unwrap();
+    let temp_path = temp_dir.path().to_path_buf();
+    run_command(
+        &temp_path,
+        &["--file", ".tasklist", "config", "backend", "merge"],
+    );
+    for description in ["One", "Two", "Three"] {
+        run_command(
+            &temp_path,
+            &["--file", ".tasklist", "add", "--description", description],
+        );
+    }
+
+    let content = fs::read_to_string(temp_path.join(".tasklist")).unwrap();
+    assert!(!content.contains("#max_id="), "{}", content);
+    let uuids: Vec<&str> = content
+        .lines()
+        .filter_map(|line| line.split('\t').find(|field| field.starts_with("uuid=")))
+        .collect();
+    assert_eq!(uuids.len(), 3);
+    let mut sorted = uuids.clone();
+    sorted.sort();
+    assert_eq!(uuids, sorted);
+
+    // A plain git merge of two branches that each added task 4 keeps both
+    let task = |id: u32, description: &str, uuid: &str| {
+        format!(
+            "{}\t🚀 Not Started\t{}\t2025-01-01 10:00\tuuid={}-0000-4000-8000-000000000000\n",
+            id, description, uuid
+        )
+    };
+    fs::write(
+        temp_path.join(".tasklist"),
+        task(1, "One", "10000000")
+            + &task(4, "Ours", "40000000")
+            + &task(4, "Theirs", "50000000")
+            + "#max_id=4\n",
+    )
+    .unwrap();
+    let output = run_command(&temp_path, &["--file", ".tasklist", "show"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(String::from_utf8_lossy(&output.stderr).contains("\"Theirs\" is now 5"));
+    run_command(
+        &temp_path,
+        &["--file", ".tasklist", "add", "--description", "Six"],
+    );
+    let content = fs::read_to_string(temp_path.join(".tasklist")).unwrap();
+    assert!(content.contains("5\t🚀 Not Started\tTheirs"), "{}", content);
+    assert!(content.contains("6\t🚀 Not Started\tSix"), "{}", content);
+    // The counter an older version left at the end is gone
+    assert!(!content.contains("#max_id="), "{}", content);
+}
+
+#[test]
+fn test_event_log_keeps_history_and_ignores_torn_writes() {
+    let temp_dir = TempDir::new().unwrap();
+    let temp_path = temp_dir.path().to_path_buf();
+    let tsk = |args: &[&str]| {
+        let output = run_command(&temp_path, args);
+        assert!(
+            output.status.success(),
+            "{:?} failed: {}",
+            args,
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+
+    fs::write(
+        temp_path.join(".tasklist"),
+        "#max_id=2\n# Sprint 12\n\
+         1\t🚀 Not Started\tWrite docs\t2025-01-01 10:00\tuuid=00000000-0000-4000-8000-000000000001\n\
+         \n# Later\n\
+         2\t🚀 Not Started\tShip it\t2025-01-01 10:00\tuuid=00000000-0000-4000-8000-000000000002\n",
+    )
+    .unwrap();
+    tsk(&["migrate", "--to", "events"]);
+    tsk(&["update", "--id", "1", "--status", "in_progress"]);
+    tsk(&["assign", "1", "alice"]);
+    tsk(&["delete", "--id", "2"]);
+
+    let log = fs::read_to_string(temp_path.join(".tasklist")).unwrap();
+    assert!(
+        log.lines().any(|line| line.contains("\tdelete\t2\t")),
+        "Log: {}",
+        log
+    );
+    let history = tsk(&["log", "1"]);
+    assert!(history.contains("added"), "History: {}", history);
+    assert!(history.contains("In Progress"));
+    assert!(history.contains("assignee: - -> alice"));
+
+    // A write cut short by a crash is ignored, then cut off by the next one
+    let mut torn = log.clone();
+    torn.push_str("4\t2025-01-01 10:00:00\tdelete\t1\t");
+    fs::write(temp_path.join(".tasklist"), &torn).unwrap();
+    assert!(tsk(&["show"]).contains("Write docs"));
+    tsk(&["add", "--description", "Celebrate"]);
+    let log = fs::read_to_string(temp_path.join(".tasklist")).unwrap();
+    assert!(!log.contains("\tdelete\t1\t"), "Log: {}", log);
+    let list = tsk(&["show"]);
+    assert!(list.contains("Write docs"));
+    assert!(list.contains("Celebrate"));
+    assert!(!list.contains("Ship it"));
+
+    // Boards on other backends have no log
+    tsk(&["migrate", "--to", "tsv"]);
+    assert!(!run_command(&temp_path, &["log", "1"]).status.success());
+
+    // Comments and blank lines were replayed where they were
+    let content = fs::read_to_string(temp_path.join(".tasklist")).unwrap();
+    let lines: Vec<&str> = content.lines().skip(1).collect();
+    assert_eq!(lines[0], "# Sprint 12", "{}", content);
+    assert!(lines[1].contains("Write docs"), "{}", content);
+    assert_eq!(lines[2..4], ["", "# Later"], "{}", content);
+    assert!(lines[4].contains("Celebrate"), "{}", content);
+}
+
+#[test]
+fn test_history_records_status_and_description_changes() {
+    for (backend, temp_dir) in backend_boards() {
+        let temp_path = temp_dir.path().to_path_buf();
+        let tsk = |args: &[&str]| {
+            let output = std::process::Command::new(format!(
+                "{}/target/debug/tsk",
+                env!("CARGO_MANIFEST_DIR")
+            ))
+            .args(args)
+            .current_dir(&temp_path)
+            .env("USER", "alice")
+            .env("GIT_CONFIG_GLOBAL", "/dev/null")
+            .env("GIT_CONFIG_NOSYSTEM", "1")
+            .output()
+            .unwrap();
+            assert!(
+                output.status.success(),
+                "{:?} failed: {}",
+                args,
+                String::from_utf8_lossy(&output.stderr)
+            );
+            String::from_utf8_lossy(&output.stdout).to_string()
+        };
+
+        tsk(&["add", "--description", "Write docs"]);
+        tsk(&["add", "--description", "Ship it"]);
+        tsk(&["update", "--id", "1", "--status", "done"]);
+        tsk(&["update", "--id", "1", "--description", "Write the docs"]);
+        tsk(&["assign", "1", "bob"]);
+        tsk(&[
+            "--dry-run",
+            "update",
+            "--id",
+            "1",
+            "--status",
+            "in_progress",
+        ]);
+        tsk(&["delete", "--id", "2"]);
+        // Renumbering doesn't lose track of a task
+        tsk(&["renumber"]);
+
+        let history = tsk(&["history", "1"]);
+        let lines: Vec<&str> = history.lines().skip(1).collect();
+        assert_eq!(lines.len(), 3, "{}: {}", backend, history);
+        assert!(lines[0].contains("alice") && lines[0].contains("created \"Write docs\""));
+        assert!(
+            lines[1].contains("Not Started -> ✅ Done"),
+            "History: {}",
+            history
+        );
+        assert!(lines[2].contains("\"Write docs\" -> \"Write the docs\""));
+
+        let log = fs::read_to_string(temp_path.join(".tasklist.d/history")).unwrap();
+        assert!(
+            log.lines().any(|line| line.contains("\tdeleted\tShip it")),
+            "Log: {}",
+            log
+        );
+    }
+}
+
+#[test]
+fn test_encrypted_board_needs_the_right_passphrase() {
+    let temp_dir = TempDir::new().unwrap();
+    let temp_path = temp_dir.path().to_path_buf();
+    let tsk = |args: &[&str], passphrase: &str| {
+        std::process::Command::new(format!("{}/target/debug/tsk", env!("CARGO_MANIFEST_DIR")))
+            .args(args)
+            .current_dir(&temp_path)
+            .env("TSK_PASSPHRASE", passphrase)
+            .output()
+            .unwrap()
+    };
+    let ok = |output: std::process::Output| {
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+
+    ok(tsk(&["add", "--description", "Call ACME Corp"], "s3cret"));
+    ok(tsk(&["add", "--description", "Second"], "s3cret"));
+    fs::write(temp_path.join("TODO.md"), "- [ ] Email ACME Corp\n").unwrap();
+    ok(tsk(&["sync", "markdown", "TODO.md"], "s3cret"));
+    ok(tsk(&["encrypt"], "s3cret"));
+    ok(tsk(&["update", "--id", "2", "--status", "done"], "s3cret"));
+    ok(tsk(&["update", "--id", "3", "--status", "done"], "s3cret"));
+    ok(tsk(&["sync", "markdown", "TODO.md"], "s3cret"));
+    assert!(
+        fs::read_to_string(temp_path.join("TODO.md"))
+            .unwrap()
+            .contains("- [x] Email")
+    );
+
+    // Neither the board nor its backups, history or sync state leak the text
+    let mut files = vec![
+        temp_path.join(".tasklist"),
+        temp_path.join(".tasklist.d/history"),
+        temp_path.join(".tasklist.d/markdown-sync"),
+    ];
+    for entry in fs::read_dir(temp_path.join(".tasklist.d/backups")).unwrap() {
+        files.push(entry.unwrap().path());
+    }
+    for file in &files {
+        let content = fs::read(file).unwrap();
+        assert!(
+            !String::from_utf8_lossy(&content).contains("ACME"),
+            "Plaintext in {:?}",
+            file
+        );
+    }
+
+    assert!(ok(tsk(&["show"], "s3cret")).contains("Call ACME Corp"));
+    let wrong = tsk(&["show"], "guess");
+    assert!(!wrong.status.success());
+    assert!(String::from_utf8_lossy(&wrong.stderr).contains("wrong passphrase"));
+    let unset = run_command(&temp_path, &["show"]);
+    assert!(!unset.status.success());
+    assert!(String::from_utf8_lossy(&unset.stderr).contains("TSK_PASSPHRASE"));
+
+    ok(tsk(&["decrypt"], "s3cret"));
+    let content = fs::read_to_string(temp_path.join(".tasklist")).unwrap();
+    assert!(content.starts_with("#max_id=3"), "Content: {}", content);
+    assert!(content.contains("Call ACME Corp"));
+    assert!(ok(run_command(&temp_path, &["history", "2"])).contains("Done"));
+    let state = fs::read_to_string(temp_path.join(".tasklist.d/markdown-sync")).unwrap();
+    assert!(state.contains("\t1\tEmail ACME Corp"), "State: {}", state);
+}
+
+#[test]
+fn test_import_maps_todotxt_taskwarrior_and_csv() {
+    let temp_dir = TempDir::new().unwrap();
+    let temp_path = temp_dir.path().to_path_buf();
+    let tasklist = temp_path.join(".tasklist");
+
+    fs::write(
+        temp_path.join("todo.txt"),
+        "(A) 2024-01-01 Call Bob +Home @phone due:2024-01-05\n\
+         x 2024-01-03 2024-01-01 Pay rent rec:1m\n",
+    )
+    .unwrap();
+    let output = run_command(
+        &temp_path,
+        &["--dry-run", "import", "--from", "todotxt", "todo.txt"],
+    );
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Imported 2 task(s)"));
+    assert!(!tasklist.exists());
+
+    let output = run_command(&temp_path, &["import", "--from", "todotxt", "todo.txt"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("line 2: no field for rec:1m"), "{}", stdout);
+    let content = fs::read_to_string(&tasklist).unwrap();
+    assert!(content.contains("1\t🚀 Not Started\tCall Bob\t2024-01-01 00:00\t"));
+    assert!(content.contains("due=2024-01-05\tpriority=A\tproject=Home\tcontext=phone"));
+    assert!(content.contains("2\t✅ Done\tPay rent rec:1m\t2024-01-03 00:00\t"));
+    assert!(content.contains("\tcreated=2024-01-01\n"), "{}", content);
+
+    fs::write(
+        temp_path.join("tasks.json"),
+        r#"[{"id":1,"description":"Fix the sink","status":"pending","start":"20240102T090000Z","uuid":"5b3a1d6e-8e1f-4c5e-9b0a-1f2e3d4c5b6a","tags":["plumbing"],"annotations":[{"entry":"20240102T100000Z","description":"Bought parts"}],"size":"L"},
+{"description":"Old","status":"deleted","uuid":"6b3a1d6e-8e1f-4c5e-9b0a-1f2e3d4c5b6a"}]"#,
+    )
+    .unwrap();
+    let output = run_command(
+        &temp_path,
+        &["import", "--from", "taskwarrior-json", "tasks.json"],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Imported 1 task(s)"), "{}", stdout);
+    assert!(stdout.contains("no field for size=\"L\""));
+    assert!(stdout.contains("task 2 (Old): deleted, skipped"));
+    let output = run_command(&temp_path, &["show", "5b3a1d"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("[3] Fix the sink"), "{}", stdout);
+    assert!(stdout.contains("In Progress"));
+    assert!(stdout.contains("Bought parts"));
+    // Importing the same export again adds nothing
+    let output = run_command(
+        &temp_path,
+        &["import", "--from", "taskwarrior-json", "tasks.json"],
+    );
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Imported 0 task(s)"));
+
+    fs::write(
+        temp_path.join("tasks.csv"),
+        "Title,Status,Due Date,Color,Created,Completed\n\
+         \"Write, docs\",completed,2024-03-01,red,2024-02-01,2024-02-20\n\
+         ,todo,,,,\n",
+    )
+    .unwrap();
+    let output = run_command(&temp_path, &["import", "--from", "csv", "tasks.csv"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("column Color: no matching field"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("row 3: no description, skipped"));
+    let content = fs::read_to_string(&tasklist).unwrap();
+    assert!(content.starts_with("#max_id=4\t"));
+    assert!(content.contains("4\t✅ Done\tWrite, docs\t2024-02-20 00:00\t"));
+    assert!(content.contains("due=2024-03-01"));
+    assert!(content.contains("created=2024-02-01 00:00"));
+}
+
+#[test]
+fn test_export_formats_follow_show_filters() {
+    let temp_dir = TempDir::new().unwrap();
+    let temp_path = temp_dir.path().to_path_buf();
+
+    fs::write(
+        temp_path.join(".tasklist"),
+        "#max_id=3\n\
+         1\t🚀 Not Started\tWrite <docs>\t2024-01-01 10:00\testimate=3pt\tdue=2024-02-01\tpriority=A\tproject=site\n\
+         2\t⏳ In Progress\tShip, finally\t2024-01-02 10:00\tassignee=alice\n\
+         3\t✅ Done\tPlan\t2024-01-03 10:00\n",
+    )
+    .unwrap();
+
+    let output = run_command(&temp_path, &["export", "--format", "markdown"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("- [ ] Write <docs> (#1 · 3pt · due 2024-02-01)"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("- [ ] Ship, finally (#2 · in progress · @alice)"));
+    assert!(stdout.contains("- [x] Plan (#3)"));
+
+    let output = run_command(
+        &temp_path,
+        &[
+            "export",
+            "--format",
+            "markdown",
+            "--grouped",
+            "--assignee",
+            "alice",
+        ],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("## ⏳ In Progress\n\n- [ ] Ship, finally"),
+        "{}",
+        stdout
+    );
+    assert!(!stdout.contains("Plan"));
+
+    let output = run_command(
+        &temp_path,
+        &["export", "--format", "todotxt", "-s", "not_started"],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.starts_with("(A) Write <docs> +site due:2024-02-01"),
+        "{}",
+        stdout
+    );
+    assert_eq!(stdout.lines().count(), 1);
+
+    let output = run_command(&temp_path, &["export", "--format", "csv"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with("id,uuid,status,description,"));
+    assert!(
+        stdout.contains(",in_progress,\"Ship, finally\","),
+        "{}",
+        stdout
+    );
+
+    let output = run_command(
+        &temp_path,
+        &["export", "--format", "html", "--output", "board.html"],
+    );
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Exported 3 task(s) to board.html"));
+    let html = fs::read_to_string(temp_path.join("board.html")).unwrap();
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("<h2>🚀 NOT STARTED (3pt)</h2>"));
+    assert!(
+        html.contains("<span class=\"id\">[2] (Al)</span> Ship, finally"),
+        "{}",
+        html
+    );
+    assert!(html.contains("Write &lt;docs&gt;"));
+}
+
+#[test]
+fn test_ics_export_and_feed_follow_the_board() {
+    let temp_dir = TempDir::new().unwrap();
+    let temp_path = temp_dir.path().to_path_buf();
+
+    fs::write(
+        temp_path.join(".tasklist"),
+        "#max_id=3\n\
+         1\t🚀 Not Started\tFile taxes; really, this time\t2024-01-01 10:00\tuuid=2b0c9a0e-5a57-4b4e-9a38-1f1e7f0d9a11\tdue=2024-04-15\tpriority=H\n\
+         2\t✅ Done\tPlan\t2024-01-03 10:00\tuuid=3c0c9a0e-5a57-4b4e-9a38-1f1e7f0d9a11\tcompleted=2024-01-02 12:00:00\n\
+         3\t🚀 Not Started\tLater\t2024-01-03 10:00\tuuid=4d0c9a0e-5a57-4b4e-9a38-1f1e7f0d9a11\twait=2999-01-01\n",
+    )
+    .unwrap();
+
+    let output = run_command(&temp_path, &["export", "--format", "ics"]);
+    let ics = String::from_utf8_lossy(&output.stdout).to_string();
+    assert!(
+        ics.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"),
+        "{}",
+        ics
+    );
+    assert!(ics.contains("UID:2b0c9a0e-5a57-4b4e-9a38-1f1e7f0d9a11\r\n"));
+    assert!(ics.contains("SUMMARY:File taxes\\; really\\, this time\r\n"));
+    assert!(ics.contains("STATUS:NEEDS-ACTION\r\nPRIORITY:1\r\nDUE;VALUE=DATE:20240415\r\n"));
+    // Local noon on Jan 2 is still Jan 1 to 3 in UTC
+    assert!(
+        ics.contains("STATUS:COMPLETED\r\nCOMPLETED:2024010"),
+        "{}",
+        ics
+    );
+    assert!(ics.contains("Z\r\nPERCENT-COMPLETE:100\r\n"));
+    // Waiting tasks are left out, like in any export
+    assert_eq!(ics.matches("BEGIN:VTODO").count(), 2);
+    assert!(ics.ends_with("END:VCALENDAR\r\n"));
+
+    let output = run_command(&temp_path, &["ical-feed", "--output", "board.ics"]);
+    assert!(output.status.success());
+    let feed = temp_path.join("board.ics");
+    assert_eq!(fs::read_to_string(&feed).unwrap(), ics);
+    let output = run_command(&temp_path, &["config", "ical_feed"]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "board.ics");
+
+    run_command(&temp_path, &["update", "--id", "1", "-s", "done"]);
+    let updated = fs::read_to_string(&feed).unwrap();
+    assert_eq!(updated.matches("STATUS:COMPLETED").count(), 2);
+    assert_eq!(updated.matches("\r\nCOMPLETED:").count(), 2, "{}", updated);
+    let board = fs::read_to_string(temp_path.join(".tasklist")).unwrap();
+    assert_eq!(board.matches("\tcompleted=").count(), 2, "{}", board);
+    // Reopening forgets the completion time
+    run_command(&temp_path, &["update", "--id", "1", "-s", "ns"]);
+    let board = fs::read_to_string(temp_path.join(".tasklist")).unwrap();
+    assert_eq!(board.matches("\tcompleted=").count(), 1, "{}", board);
+    run_command(&temp_path, &["update", "--id", "1", "-s", "done"]);
+    // The UID stays, so calendar apps update the entry
+    assert!(updated.contains("UID:2b0c9a0e-5a57-4b4e-9a38-1f1e7f0d9a11\r\n"));
+    assert!(!updated.contains("SUMMARY:Later"));
+
+    // Commands that write the board outside of task edits refresh it too
+    let board = fs::read_to_string(temp_path.join(".tasklist")).unwrap();
+    fs::write(
+        temp_path.join(".tasklist"),
+        board.replace("\tPlan\t", "\tPlan ahead\t"),
+    )
+    .unwrap();
+    run_command(&temp_path, &["rehash"]);
+    assert!(
+        fs::read_to_string(&feed)
+            .unwrap()
+            .contains("SUMMARY:Plan ahead\r\n")
+    );
+    fs::remove_file(&feed).unwrap();
+    run_command(&temp_path, &["migrate", "--to", "json"]);
+    let updated = fs::read_to_string(&feed).unwrap();
+    assert!(updated.contains("SUMMARY:Plan ahead\r\n"));
+
+    run_command(&temp_path, &["ical-feed", "--off"]);
+    run_command(&temp_path, &["add", "--description", "Not in the feed"]);
+    assert_eq!(fs::read_to_string(&feed).unwrap(), updated);
+}
+
+#[test]
+fn test_markdown_sync_goes_both_ways() {
+    let temp_dir = TempDir::new().unwrap();
+    let temp_path = temp_dir.path().to_path_buf();
+    let todo = temp_path.join("TODO.md");
+
+    fs::write(
+        &todo,
+        "# Todo\n\n- [ ] Write docs\n- [x] Plan it\nSome prose\n",
+    )
+    .unwrap();
+    let output = run_command(&temp_path, &["sync", "markdown", "TODO.md"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Added task 1: Write docs"), "{}", stdout);
+    let content = fs::read_to_string(&todo).unwrap();
+    let lines: Vec<&str> = content.lines().collect();
+    assert!(
+        lines[2].starts_with("- [ ] Write docs <!-- tsk:"),
+        "{}",
+        content
+    );
+    assert!(lines[3].starts_with("- [x] Plan it <!-- tsk:"));
+    assert_eq!(lines[4], "Some prose");
+    let output = run_command(&temp_path, &["show", "2"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Done"));
+
+    // Edits on both sides since the last sync
+    run_command(&temp_path, &["update", "--id", "1", "-s", "done"]);
+    let edited = content
+        .replace("- [x] Plan it", "- [ ] Plan it properly")
+        .replace("Some prose\n", "Some prose\n- [ ] Ship\n");
+    fs::write(&todo, edited).unwrap();
+    let output = run_command(&temp_path, &["sync", "markdown", "TODO.md"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Ticked in the file: Write docs"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("Task 2 renamed to: Plan it properly"));
+    assert!(stdout.contains("Added task 3: Ship"));
+    let content = fs::read_to_string(&todo).unwrap();
+    assert!(
+        content.contains("- [x] Write docs <!-- tsk:"),
+        "{}",
+        content
+    );
+    let output = run_command(&temp_path, &["show", "2"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Plan it properly"));
+    assert!(stdout.contains("Not Started"));
+
+    // Nothing changed, nothing to do
+    let output = run_command(&temp_path, &["sync", "markdown", "TODO.md"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("3 item(s), 0 change(s)"));
+    assert_eq!(fs::read_to_string(&todo).unwrap(), content);
+
+    run_command(&temp_path, &["delete", "--id", "3"]);
+    let output = run_command(&temp_path, &["sync", "markdown", "TODO.md"]);
+    assert!(
+        String::from_utf8_lossy(&output.stdout)
+            .contains("Removed from the file (deleted on the board): Ship")
+    );
+    assert!(!fs::read_to_string(&todo).unwrap().contains("Ship"));
+}
+
+#[test]
+fn test_scan_tracks_todo_comments() {
+    let temp_dir = TempDir::new().unwrap();
+    let temp_path = temp_dir.path().to_path_buf();
+    fs::create_dir_all(temp_path.join("src")).unwrap();
+    fs::create_dir_all(temp_path.join("target")).unwrap();
+    fs::write(temp_path.join(".gitignore"), "target/\n").unwrap();
+    fs::write(
+        temp_path.join("target/gen.rs"),
+        "// TODO: ignored by .gitignore\n",
+    )
+    .unwrap();
+    // Headings, bullets and doc examples aren't comments
+    fs::write(
+        temp_path.join("README.md"),
+        "# TODO\n* TODO write more\n<!-- TODO: not in prose either -->\n",
+    )
+    .unwrap();
+    let source = temp_path.join("src/x.rs");
+    fs::write(
+        &source,
+        "/// # TODO\n/// * TODO list\n/// // TODO: an example\nfn main() {\n    // TODO(ana): retry on timeout\n    let todos = 1; // not a TODOS tag\n}\n",
+    )
+    .unwrap();
+    let script = temp_path.join("src/run.py");
+    fs::write(&script, "## FIXME drop this\n").unwrap();
+    let show = |id: &str| {
+        let output = run_command(&temp_path, &["show", id]);
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+    let scan = |args: &[&str]| {
+        let output = run_command(&temp_path, &[&["scan"], args].concat());
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+
+    let stdout = scan(&[]);
+    assert!(stdout.contains("Found 2 comment(s): 2 added"), "{}", stdout);
+    let task = show("1");
+    assert!(task.contains("TODO: retry on timeout"), "{}", task);
+    assert!(task.contains("src/x.rs:5"), "{}", task);
+    assert!(show("2").contains("FIXME: drop this"));
+
+    // A second scan changes nothing
+    let stdout = scan(&[]);
+    assert!(
+        stdout.contains("0 added, 0 moved, 0 renamed, 0 reopened, 0 done"),
+        "{}",
+        stdout
+    );
+
+    // Moved comments update their link, edited ones rename their task and
+    // removed ones close it
+    fs::write(
+        &source,
+        "use std::io;\n\n\nfn main() {\n    // TODO(ana): retry on timeout, twice\n}\n",
+    )
+    .unwrap();
+    fs::write(&script, "print()\n").unwrap();
+    let stdout = scan(&["src"]);
+    assert!(stdout.contains("0 added, 0 moved, 1 renamed"), "{}", stdout);
+    let stdout = scan(&["src"]);
+    assert!(stdout.contains("0 added, 0 moved, 0 renamed"), "{}", stdout);
+    fs::write(
+        &source,
+        "fn main() {\n    // TODO(ana): retry on timeout, twice\n}\n",
+    )
+    .unwrap();
+    let stdout = scan(&["src"]);
+    assert!(stdout.contains("0 added, 1 moved"), "{}", stdout);
+    let task = show("1");
+    assert!(task.contains("TODO: retry on timeout, twice"), "{}", task);
+    assert!(task.contains("src/x.rs:2"), "{}", task);
+    assert!(show("2").contains("Done"));
+
+    // A comment that comes back reopens its task; an unreadable path only warns
+    fs::write(&script, "print()  # FIXME drop this\n").unwrap();
+    let output = run_command(&temp_path, &["scan", "src", "missing"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Warning:"));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("0 moved, 0 renamed, 1 reopened"),
+        "{}",
+        stdout
+    );
+    assert!(!show("2").contains("Done"));
+    assert!(!show("3").contains("TODO"));
 }